@@ -0,0 +1,201 @@
+/***********************************************************
+ * ACCESSIBLE
+ ***********************************************************
+ * With "--accessible" the board is described in sentences
+ * instead of drawn as a grid, for people using a screen
+ * reader. Each time the board would be drawn they hear the
+ * disc counts, the squares each side holds row by row,
+ * what the last move flipped and the legal moves:
+ *     X has 4 discs, O has 1.
+ *     Row A: X on A2.
+ *     Row B: X on B2, B3.
+ *     Row C: X on C2. O on C3.
+ *     Row D: empty.
+ *     X played A2 and flipped B2.
+ *     Legal moves: A1, A3, C1.
+ * At the move prompt (and when playing over the network)
+ * they can also ask about the board, in any mode:
+ *     what is on d4   What one square holds.
+ *     row c           Who holds the squares of one row.
+ *     count           The disc counts.
+ *     moves           The legal moves.
+ *     describe        The whole description again.
+***********************************************************/
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::color::Highlights;
+use crate::{Board, NumLet, _X, _O, coordinates, count_pieces, get_coordinates};
+
+// True while the board is described instead of drawn
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/***********************************************************
+ * START / ACTIVE
+ ***********************************************************
+ * Describe boards from now on; and whether that is on.
+***********************************************************/
+pub fn start() {
+    ACTIVE.store(true, Ordering::SeqCst);
+}
+
+pub fn active() -> bool {
+    ACTIVE.load(Ordering::SeqCst)
+}
+
+/***********************************************************
+ * DESCRIBE
+ ***********************************************************
+ * The whole board in sentences, one to a line, with the
+ * last move and legal moves from the highlights.
+***********************************************************/
+pub fn describe(board: &Board, highlights: &Highlights) -> String {
+    let mut text = format!("{}\n", count(board));
+    for letter in 0..board.size() {
+        text += &format!("{}\n", row(board, letter));
+    }
+    if let Some(last) = highlights.last {
+        let piece = board[last.l][last.n];
+        if highlights.flipped.is_empty() {
+            text += &format!("{} played {}.\n", piece, coordinates(last));
+        }
+        else {
+            text += &format!("{} played {} and flipped {}.\n", piece, coordinates(last),
+                             list(&highlights.flipped));
+        }
+    }
+    text += &format!("{}\n", moves(&highlights.legal));
+    text
+}
+
+/***********************************************************
+ * ANSWER
+ ***********************************************************
+ * Answer a question about the board (see the top of this
+ * file), or None if the line isn't one.
+***********************************************************/
+pub fn answer(board: &Board, highlights: &Highlights, line: &str) -> Option<String> {
+    let line = line.trim().to_lowercase();
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let reply = match words.as_slice() {
+        ["describe"] => describe(board, highlights).trim_end().to_string(),
+        ["count"] => count(board),
+        ["moves"] => moves(&highlights.legal),
+        ["row", letter] => match letter.chars().collect::<Vec<char>>().as_slice() {
+            [c @ 'a'..='z'] if ((*c as u8 - b'a') as usize) < board.size() => {
+                row(board, (*c as u8 - b'a') as usize)
+            }
+            _ => format!("There is no row {}.", letter.to_uppercase()),
+        },
+        ["what", .., square] | ["what's", .., square] => {
+            let square = square.trim_end_matches('?');
+            let mut num: usize = 0;
+            let mut letter: usize = 0;
+            if !get_coordinates(&mut num, &mut letter, square.to_string())
+                || num >= board.size() || letter >= board.size() {
+                return Some(format!("There is no square {}.", square.to_uppercase()));
+            }
+            let nl = NumLet { n: num, l: letter };
+            match board[letter][num] {
+                " " if highlights.legal.contains(&nl) => format!("{} is empty, a legal move.", coordinates(nl)),
+                " " => format!("{} is empty.", coordinates(nl)),
+                piece => format!("{} has {}.", coordinates(nl), piece),
+            }
+        }
+        _ => return None,
+    };
+    Some(reply)
+}
+
+/***********************************************************
+ * COUNT / ROW / MOVES
+ ***********************************************************
+ * The sentences a description is made of: the disc
+ * counts, the squares each side holds in a row, and the
+ * legal moves.
+***********************************************************/
+fn count(board: &Board) -> String {
+    let (x_count, o_count) = count_pieces(board);
+    format!("X has {} disc{}, O has {}.", x_count, if x_count == 1 { "" } else { "s" }, o_count)
+}
+
+fn row(board: &Board, letter: usize) -> String {
+    let mut text = format!("Row {}:", (letter + 'A' as usize) as u8 as char);
+    for piece in [_X, _O] {
+        let held: Vec<NumLet> = (0..board.size()).filter(|&num| board[letter][num] == piece)
+            .map(|num| NumLet { n: num, l: letter }).collect();
+        if !held.is_empty() {
+            text += &format!(" {} on {}.", piece, list(&held));
+        }
+    }
+    if !text.ends_with('.') {
+        text += " empty.";
+    }
+    text
+}
+
+fn moves(legal: &[NumLet]) -> String {
+    if legal.is_empty() {
+        "No legal moves.".to_string()
+    }
+    else {
+        format!("Legal moves: {}.", list(legal))
+    }
+}
+
+// Locations separated by commas, for example "A1, B2"
+fn list(locations: &[NumLet]) -> String {
+    locations.iter().map(|nl| coordinates(*nl)).collect::<Vec<String>>().join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_board, legal_moves, place};
+
+    // A 4x4 board after X plays A2, with O to move
+    fn board() -> (Board, Highlights) {
+        let mut before = Board::new(4);
+        create_board(&mut before);
+        let mut after = before.clone();
+        place(&mut after, NumLet { n: 1, l: 0 }, true);
+        let legal = legal_moves(&after, false);
+        (after.clone(), Highlights::between(&before, &after, &legal))
+    }
+
+    #[test]
+    fn questions_are_answered() {
+        let (board, highlights) = board();
+        let answers = [("count", "X has 4 discs, O has 1."),
+                       ("moves", "Legal moves: A1, A3, C1."),
+                       ("row b", "Row B: X on B2, B3."),
+                       ("Row C", "Row C: X on C2. O on C3."),
+                       ("row d", "Row D: empty."),
+                       ("row e", "There is no row E."),
+                       ("row 1", "There is no row 1."),
+                       ("what is on b2", "B2 has X."),
+                       ("What's on A1?", "A1 is empty, a legal move."),
+                       ("what is d4", "D4 is empty."),
+                       ("what is on e5", "There is no square E5.")];
+        for (question, reply) in answers {
+            assert_eq!(answer(&board, &highlights, question), Some(reply.to_string()), "{}", question);
+        }
+    }
+
+    #[test]
+    fn other_lines_are_left_alone() {
+        let (board, highlights) = board();
+        for line in ["", "a1", "pass", "count me in", "what"] {
+            assert_eq!(answer(&board, &highlights, line), None, "{}", line);
+        }
+    }
+
+    #[test]
+    fn boards_are_described() {
+        let (board, highlights) = board();
+        let description = "X has 4 discs, O has 1.\nRow A: X on A2.\nRow B: X on B2, B3.\n\
+                           Row C: X on C2. O on C3.\nRow D: empty.\nX played A2 and flipped B2.\n\
+                           Legal moves: A1, A3, C1.\n";
+        assert_eq!(describe(&board, &highlights), description);
+        assert_eq!(answer(&board, &highlights, "describe"), Some(description.trim_end().to_string()));
+    }
+}
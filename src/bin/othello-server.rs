@@ -0,0 +1,64 @@
+/***********************************************************
+ * OTHELLO SERVER
+ ***********************************************************
+ * The multi-game server: many players connect with the
+ * terminal program's "--connect HOST:PORT", meet in the
+ * lobby and play any number of games at the same time,
+ * each refereed here, or watch them. Usage:
+ *     othello-server [PORT | ADDRESS:PORT] [--delay SECONDS]
+ *                    [--grace SECONDS] [--ratings FILE]
+ * The port is 5000 unless one is given. Spectators see
+ * each game "--delay" seconds behind (none by default),
+ * players whose connection drops have "--grace" seconds
+ * to come back (60 by default, 0 for none), and the
+ * registered players' ratings are kept in "--ratings"
+ * (ratings.csv unless given, see ratings.rs).
+***********************************************************/
+
+use std::time::Duration;
+use othello::{network, parse_seconds, server};
+use othello::ratings::Ratings;
+
+static USAGE: &str = "Usage: othello-server [PORT | ADDRESS:PORT] [--delay SECONDS] [--grace SECONDS] [--ratings FILE]";
+
+fn main() {
+    let mut address = network::address("5000");     // Where to listen
+    let mut delay = Duration::from_secs(0);         // How far behind spectators are
+    let mut grace = Duration::from_secs(60);        // How long players get to come back
+    let mut ratings = "ratings.csv".to_string();    // Where the ladder is kept
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                return;
+            }
+            "--delay" => match args.next().as_deref().and_then(parse_seconds) {
+                Some(seconds) => delay = seconds,
+                None => usage("--delay needs a number of seconds"),
+            },
+            "--grace" => match args.next().as_deref().and_then(parse_seconds) {
+                Some(seconds) => grace = seconds,
+                None => usage("--grace needs a number of seconds"),
+            },
+            "--ratings" => match args.next() {
+                Some(path) => ratings = path,
+                None => usage("--ratings needs a file name"),
+            },
+            _ if !arg.starts_with('-') => address = network::address(&arg),
+            _ => usage(&format!("Unknown option {}", arg)),
+        }
+    }
+    let started = Ratings::load(&ratings).and_then(|ratings| server::run(&address, delay, grace, ratings));
+    if let Err(message) = started {
+        eprintln!("{}", message);
+        std::process::exit(1);
+    }
+}
+
+// Explain what went wrong with the command line, then exit
+fn usage(message: &str) -> ! {
+    eprintln!("{}", message);
+    eprintln!("{}", USAGE);
+    std::process::exit(2);
+}
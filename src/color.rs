@@ -0,0 +1,202 @@
+/***********************************************************
+ * COLOR
+ ***********************************************************
+ * Drawing the board with ANSI colors at a terminal: the
+ * same grid as board_text(), in the theme's palette (see
+ * theme.rs). With the classic palette the board is green,
+ * with dark "X" discs and white "O" discs. Dots mark where
+ * the player to move can place, the last move is shown on
+ * yellow and the discs it flipped on cyan. Colors are
+ * only used when standard output is a terminal, the
+ * theme has a palette and the NO_COLOR environment
+ * variable isn't set (see https://no-color.org), so piped
+ * output and scripts still get plain text.
+***********************************************************/
+
+use std::env;
+use std::io::{self, IsTerminal};
+use crate::theme::{Theme, PALETTES};
+use crate::{Board, NumLet, _X, _O};
+
+/***********************************************************
+ * HIGHLIGHTS
+ ***********************************************************
+ * What to pick out on a colored board. The default picks
+ * out nothing.
+***********************************************************/
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Highlights {
+    pub legal: Vec<NumLet>,         // Where the player to move can place
+    pub last: Option<NumLet>,       // The last piece placed
+    pub flipped: Vec<NumLet>        // The pieces it flipped
+}
+
+impl Highlights {
+    /*******************************************************
+     * BETWEEN
+     *******************************************************
+     * The highlights for a board that was before and is now
+     * after: the location that was empty and now holds a
+     * piece is the last move, and the pieces that changed
+     * are the ones it flipped. Boards of different sizes
+     * (or more than one move apart) show no last move.
+    *******************************************************/
+    pub fn between(before: &Board, after: &Board, legal: &[NumLet]) -> Highlights {
+        let mut highlights = Highlights { legal: legal.to_vec(), ..Highlights::default() };
+        if before.size() != after.size() {
+            return highlights;
+        }
+        let mut placed = Vec::new();
+        for letter in 0..after.size() {
+            for num in 0..after.size() {
+                let nl = NumLet { n: num, l: letter };
+                match (before[letter][num], after[letter][num]) {
+                    (was, is) if was == is => {}
+                    (" ", _) => placed.push(nl),
+                    _ => highlights.flipped.push(nl),
+                }
+            }
+        }
+        if placed.len() == 1 {
+            highlights.last = placed.pop();
+        }
+        else {
+            highlights.flipped.clear();     // Not one move, so nothing to show
+        }
+        highlights
+    }
+}
+
+/***********************************************************
+ * ENABLED
+ ***********************************************************
+ * Whether to draw in color with a theme: only to a
+ * terminal, and not if NO_COLOR is set to anything.
+***********************************************************/
+pub fn enabled(theme: &Theme) -> bool {
+    let no_color = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    theme.palette.is_some() && !no_color && io::stdout().is_terminal()
+}
+
+/***********************************************************
+ * BOARD_TEXT
+ ***********************************************************
+ * Draw the board in the theme's colors (the classic ones
+ * if it has none), laid out like the plain grid from
+ * board_text_at() (with the location under a cursor in
+ * brackets) so the two line up the same.
+***********************************************************/
+pub fn board_text(board: &Board, highlights: &Highlights, cursor: Option<NumLet>, theme: &Theme) -> String {
+    let palette = theme.palette.unwrap_or(&PALETTES[0]);
+    let mut text = "  ".to_string();    // Proper spacing for column numbers
+    let mut line = "-".to_string();     // Used for horizontal grid lines
+    for i in 0..board.size() {
+        text += &format!("  {} ", i + 1);
+        line += "----";
+    }
+    let line = format!("  {}\n", paint(&line, palette.grid));
+    text += "\n";
+    text += &line;                      // Top of grid
+    for (i1, row) in board.iter().enumerate() {
+        text += &format!("{} {}", (i1 + 'A' as usize) as u8 as char, paint("|", palette.grid));
+        for (i2, column) in row.iter().enumerate() {
+            let nl = NumLet { n: i2, l: i1 };
+            let background = if highlights.last == Some(nl) {
+                palette.last
+            }
+            else if highlights.flipped.contains(&nl) {
+                palette.flipped
+            }
+            else {
+                palette.board
+            };
+            let (shown, foreground) = match *column {
+                piece if piece == _X => (theme.glyph(piece), palette.x),
+                piece if piece == _O => (theme.glyph(piece), palette.o),
+                _ if highlights.legal.contains(&nl) => (theme.dot.as_str(), palette.dot),
+                empty => (theme.glyph(empty), palette.empty),
+            };
+            let cell = if cursor == Some(nl) { format!("[{}]", shown) } else { format!(" {} ", shown) };
+            text += &paint(&cell, &format!("{};{}", foreground, background));
+            text += &paint("|", palette.grid);
+        }
+        text += "\n";                   // Finish the row
+        text += &line;                  // The next horizontal grid line
+    }
+    text
+}
+
+/***********************************************************
+ * PAINT
+ ***********************************************************
+ * Text in the given colors, then back to normal.
+***********************************************************/
+fn paint(text: &str, codes: &str) -> String {
+    format!("\x1b[{}m{}\x1b[0m", codes, text)
+}
+
+/***********************************************************
+ * WIDTH
+ ***********************************************************
+ * How many columns text takes on the terminal, leaving out
+ * the color codes.
+***********************************************************/
+pub fn width(text: &str) -> usize {
+    let mut columns = 0;
+    let mut in_code = false;
+    for c in text.chars() {
+        match c {
+            '\x1b' => in_code = true,
+            'm' if in_code => in_code = false,  // The end of a color code
+            _ if in_code => {}
+            _ => columns += 1,
+        }
+    }
+    columns
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_board, legal_moves, place};
+
+    #[test]
+    fn a_move_is_picked_out() {
+        let mut before = Board::new(8);
+        create_board(&mut before);
+        let mut after = before.clone();
+        place(&mut after, NumLet { n: 3, l: 2 }, true);         // X plays C4
+        let legal = legal_moves(&after, false);
+        let highlights = Highlights::between(&before, &after, &legal);
+        assert_eq!(highlights.last, Some(NumLet { n: 3, l: 2 }));
+        assert_eq!(highlights.flipped, [NumLet { n: 3, l: 3 }]);
+        assert_eq!(highlights.legal, legal);
+    }
+
+    #[test]
+    fn anything_but_one_move_shows_nothing() {
+        let mut before = Board::new(8);
+        create_board(&mut before);
+        let mut after = before.clone();
+        place(&mut after, NumLet { n: 3, l: 2 }, true);
+        place(&mut after, NumLet { n: 2, l: 2 }, false);
+        let legal = [NumLet { n: 0, l: 0 }];
+        for (before, after) in [(&before, &before), (&before, &after), (&Board::new(4), &after)] {
+            assert_eq!(Highlights::between(before, after, &legal),
+                       Highlights { legal: legal.to_vec(), ..Highlights::default() });
+        }
+    }
+
+    #[test]
+    fn widths_leave_out_color_codes() {
+        assert_eq!(width(&paint(" X ", "1;30;42")), 3);
+        assert_eq!(width("A \u{25cf}|"), 4);
+        let mut board = Board::new(4);
+        create_board(&mut board);
+        let cursor = Some(NumLet { n: 1, l: 2 });
+        let colored = board_text(&board, &Highlights::default(), cursor, &Theme::default());
+        let plain = crate::board_text_at(&board, cursor, &Theme::default());
+        let widths = |text: &str| text.lines().map(width).collect::<Vec<usize>>();
+        assert_eq!(widths(&colored), widths(&plain));
+    }
+}
@@ -0,0 +1,463 @@
+/***********************************************************
+ * BOARD DIAGRAMS
+ ***********************************************************
+ * Board diagrams for game write-ups, drawn as SVG or PNG
+ * images.
+ * A diagram shows a position of a game: the board with the
+ * column numbers across the top and the row letters down
+ * the side (as display() shows them), the discs, and any
+ * of these marks:
+ *     last        A red ring on the disc just played.
+ *     legal       A dot on every location the player to
+ *                 move can place.
+ *     numbers     The number of the move that placed each
+ *                 disc played during the game.
+ * The size and place of everything comes from Layout and
+ * draw() does the drawing on a Canvas, so every kind of
+ * image shows the same diagram. A Sheet puts several
+ * diagrams side by side, for a strip of a whole game.
+***********************************************************/
+
+use std::fs;
+use crate::game::Game;
+use crate::png;
+use crate::player::Move;
+use crate::{Board, NumLet, _X, _O, legal_moves};
+
+pub const BOARD_COLOR: [u8; 3] = [0x2e, 0x7d, 0x32];    // The green of the board
+pub const LINE_COLOR: [u8; 3] = [0x00, 0x00, 0x00];     // Grid lines and outlines
+pub const BLACK_COLOR: [u8; 3] = [0x10, 0x10, 0x10];    // "X" discs
+pub const WHITE_COLOR: [u8; 3] = [0xf5, 0xf5, 0xf5];    // "O" discs
+pub const MARK_COLOR: [u8; 3] = [0xd3, 0x2f, 0x2f];     // The last move marker
+pub const DOT_COLOR: [u8; 3] = [0x1b, 0x3d, 0x1c];      // Legal move dots
+pub const PAPER_COLOR: [u8; 3] = [0xff, 0xff, 0xff];    // Around the board
+
+pub const MIN_CELL: usize = 8;              // Smallest cell, in pixels, that still shows the marks
+pub const MAX_CELL: usize = 256;            // Largest cell, in pixels
+pub const MAX_PIXELS: usize = 32_000_000;   // Largest image, so a PNG is at most ~100MB to draw
+
+/***********************************************************
+ * MARKS
+ ***********************************************************
+ * Which marks to draw on a diagram.
+***********************************************************/
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Marks {
+    pub last: bool,         // Ring the last move
+    pub legal: bool,        // Dot the legal moves
+    pub numbers: bool       // Number the discs played
+}
+
+impl Default for Marks {
+    // The last move and legal moves, without numbers
+    fn default() -> Marks {
+        Marks { last: true, legal: true, numbers: false }
+    }
+}
+
+/***********************************************************
+ * PARSE_MARKS
+ ***********************************************************
+ * Read a comma separated list of marks such as
+ * "last,numbers", or "none".
+***********************************************************/
+pub fn parse_marks(text: &str) -> Result<Marks, String> {
+    let mut marks = Marks { last: false, legal: false, numbers: false };
+    for mark in text.split(',').map(str::trim) {
+        match mark {
+            "last" => marks.last = true,
+            "legal" => marks.legal = true,
+            "numbers" => marks.numbers = true,
+            "none" => {}
+            _ => return Err(format!("Unknown mark \"{}\" (use last, legal, numbers or none)", mark)),
+        }
+    }
+    Ok(marks)
+}
+
+/***********************************************************
+ * DIAGRAM
+ ***********************************************************
+ * Everything drawn for one position: the board and the
+ * marks that were asked for (empty when they weren't).
+***********************************************************/
+#[derive(Clone, Debug)]
+pub struct Diagram {
+    pub board: Board,                   // The position
+    pub last: Option<NumLet>,           // The location just played
+    pub legal: Vec<NumLet>,             // Where the player to move can place
+    pub numbers: Vec<(NumLet, usize)>   // The move number of each disc played
+}
+
+impl Diagram {
+    /*******************************************************
+     * FROM_GAME
+     *******************************************************
+     * The diagram of a game after the given number of
+     * moves (0 is the start).
+    *******************************************************/
+    pub fn from_game(game: &Game, at: usize, marks: Marks) -> Result<Diagram, String> {
+        let mut positions = game.positions()?;
+        if at >= positions.len() {
+            return Err(format!("The game only has {} moves", game.moves.len()));
+        }
+        let (board, is_player_one) = positions.swap_remove(at);
+        let placed = |i: usize| match game.moves[i].mv {
+            Move::Place(nl) => Some(nl),
+            _ => None,
+        };
+        let finished = game.moves[..at].last().map(|t| &t.mv) == Some(&Move::Resign);
+        Ok(Diagram {
+            last: if marks.last && at > 0 { placed(at - 1) } else { None },
+            legal: if marks.legal && !finished { legal_moves(&board, is_player_one) } else { Vec::new() },
+            numbers: if marks.numbers {
+                (0..at).filter_map(|i| placed(i).map(|nl| (nl, i + 1))).collect()
+            }
+            else {
+                Vec::new()
+            },
+            board
+        })
+    }
+}
+
+/***********************************************************
+ * LAYOUT
+ ***********************************************************
+ * Where things go on a diagram, in pixels: a margin for
+ * the coordinates on every side of the board, then a
+ * square cell for each location.
+***********************************************************/
+#[derive(Clone, Copy, Debug)]
+pub struct Layout {
+    pub size: usize,        // Number of rows and columns
+    pub cell: usize         // Width and height of a cell
+}
+
+impl Layout {
+    // The margin around the board, holding the coordinates
+    pub fn margin(&self) -> usize {
+        self.cell * 3 / 5
+    }
+
+    // Width and height of the whole diagram
+    pub fn width(&self) -> usize {
+        self.size * self.cell + 2 * self.margin()
+    }
+
+    // The left edge of a column or top edge of a row
+    pub fn edge(&self, index: usize) -> usize {
+        self.margin() + index * self.cell
+    }
+
+    // The middle of a location
+    pub fn center(&self, nl: NumLet) -> (f64, f64) {
+        let half = self.cell as f64 / 2.0;
+        (self.edge(nl.n) as f64 + half, self.edge(nl.l) as f64 + half)
+    }
+
+    // The middle of the margin above a column or left of a row
+    pub fn label(&self, index: usize) -> (f64, f64) {
+        (self.margin() as f64 / 2.0, self.edge(index) as f64 + self.cell as f64 / 2.0)
+    }
+
+    // Radius of a disc, a legal move dot and the last move ring
+    pub fn disc_radius(&self) -> f64 { self.cell as f64 * 0.42 }
+    pub fn dot_radius(&self) -> f64 { self.cell as f64 * 0.1 }
+    pub fn ring_radius(&self) -> f64 { self.cell as f64 * 0.2 }
+}
+
+/***********************************************************
+ * CANVAS
+ ***********************************************************
+ * Something a diagram can be drawn on. Coordinates are in
+ * pixels from the top left corner and text is centred on
+ * the point given.
+***********************************************************/
+pub trait Canvas {
+    fn rect(&mut self, x: f64, y: f64, width: f64, height: f64, color: [u8; 3]);
+    fn circle(&mut self, x: f64, y: f64, radius: f64, color: [u8; 3]);
+    fn ring(&mut self, x: f64, y: f64, radius: f64, thickness: f64, color: [u8; 3]);
+    fn text(&mut self, x: f64, y: f64, size: f64, text: &str, color: [u8; 3]);
+}
+
+/***********************************************************
+ * DRAW
+ ***********************************************************
+ * Draw a diagram on a canvas with its top left corner at
+ * (left, top), and a caption (a move number) centred in
+ * the margin below the board if one is given.
+***********************************************************/
+pub fn draw(canvas: &mut dyn Canvas, diagram: &Diagram, layout: Layout,
+            left: f64, top: f64, caption: Option<&str>) {
+    let width = layout.width() as f64;
+    let margin = layout.margin() as f64;
+    let cell = layout.cell as f64;
+    let side = layout.size as f64 * cell;
+    let line = (cell / 48.0).max(1.0);      // Thickness of the grid lines
+    let font = cell * 0.4;
+    canvas.rect(left, top, width, width, PAPER_COLOR);
+    canvas.rect(left + margin - line, top + margin - line, side + 2.0 * line, side + 2.0 * line, LINE_COLOR);
+    canvas.rect(left + margin, top + margin, side, side, BOARD_COLOR);
+    for i in 1..layout.size {               // The lines between the cells
+        let at = layout.edge(i) as f64 - line / 2.0;
+        canvas.rect(left + at, top + margin, line, side, LINE_COLOR);
+        canvas.rect(left + margin, top + at, side, line, LINE_COLOR);
+    }
+    for i in 0..layout.size {               // Column numbers on top and row letters on the left
+        let (outside, middle) = layout.label(i);
+        canvas.text(left + middle, top + outside, font, &(i + 1).to_string(), LINE_COLOR);
+        canvas.text(left + outside, top + middle, font, &((i as u8 + b'A') as char).to_string(), LINE_COLOR);
+    }
+    for (l, row) in diagram.board.iter().enumerate() {
+        for (n, piece) in row.iter().enumerate() {
+            let (x, y) = layout.center(NumLet { n, l });
+            let fill = if *piece == _X { BLACK_COLOR } else if *piece == _O { WHITE_COLOR } else { continue };
+            canvas.circle(left + x, top + y, layout.disc_radius(), LINE_COLOR);
+            canvas.circle(left + x, top + y, layout.disc_radius() - line, fill);
+        }
+    }
+    for nl in diagram.legal.iter() {
+        let (x, y) = layout.center(*nl);
+        canvas.circle(left + x, top + y, layout.dot_radius(), DOT_COLOR);
+    }
+    for (nl, number) in diagram.numbers.iter() {
+        let (x, y) = layout.center(*nl);
+        let color = if diagram.board[nl.l][nl.n] == _X { WHITE_COLOR } else { BLACK_COLOR };
+        canvas.text(left + x, top + y, font, &number.to_string(), color);
+    }
+    if let Some(nl) = diagram.last {
+        let (x, y) = layout.center(nl);
+        // Around the number if there is one, otherwise a small ring
+        let radius = if diagram.numbers.is_empty() { layout.ring_radius() } else { layout.disc_radius() * 0.8 };
+        canvas.ring(left + x, top + y, radius, (cell / 16.0).max(1.0), MARK_COLOR);
+    }
+    if let Some(caption) = caption {
+        canvas.text(left + width / 2.0, top + width - margin / 2.0, font, caption, LINE_COLOR);
+    }
+}
+
+/***********************************************************
+ * SHEET
+ ***********************************************************
+ * One or more diagrams laid out in rows, PER_ROW to a row,
+ * each with an optional caption.
+***********************************************************/
+pub struct Sheet {
+    pub diagrams: Vec<(Diagram, Option<String>)>,   // The diagrams and their captions
+    pub layout: Layout                              // The layout of each diagram
+}
+
+const PER_ROW: usize = 6;       // Diagrams across a sheet
+
+impl Sheet {
+    // Width and height of the whole sheet
+    pub fn dimensions(&self) -> (usize, usize) {
+        let count = self.diagrams.len().max(1);
+        let across = count.min(PER_ROW);
+        let down = count.div_ceil(PER_ROW);
+        (across * self.layout.width(), down * self.layout.width())
+    }
+
+    // Draw every diagram on the canvas
+    pub fn draw(&self, canvas: &mut dyn Canvas) {
+        let width = self.layout.width() as f64;
+        for (i, (diagram, caption)) in self.diagrams.iter().enumerate() {
+            let left = (i % PER_ROW) as f64 * width;
+            let top = (i / PER_ROW) as f64 * width;
+            draw(canvas, diagram, self.layout, left, top, caption.as_deref());
+        }
+    }
+}
+
+/***********************************************************
+ * SVG
+ ***********************************************************
+ * A canvas that writes SVG elements.
+***********************************************************/
+pub struct Svg {
+    pub body: String        // The elements drawn so far
+}
+
+impl Canvas for Svg {
+    fn rect(&mut self, x: f64, y: f64, width: f64, height: f64, color: [u8; 3]) {
+        self.body += &format!("<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>\n",
+                              x, y, width, height, hex(color));
+    }
+
+    fn circle(&mut self, x: f64, y: f64, radius: f64, color: [u8; 3]) {
+        self.body += &format!("<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\"/>\n",
+                              x, y, radius, hex(color));
+    }
+
+    fn ring(&mut self, x: f64, y: f64, radius: f64, thickness: f64, color: [u8; 3]) {
+        self.body += &format!("<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"none\" stroke=\"{}\" \
+                               stroke-width=\"{}\"/>\n", x, y, radius, hex(color), thickness);
+    }
+
+    fn text(&mut self, x: f64, y: f64, size: f64, text: &str, color: [u8; 3]) {
+        self.body += &format!("<text x=\"{}\" y=\"{}\" font-size=\"{}\" fill=\"{}\" \
+                               text-anchor=\"middle\" dominant-baseline=\"central\">{}</text>\n",
+                              x, y, size, hex(color), text);
+    }
+}
+
+/***********************************************************
+ * TO_SVG
+ ***********************************************************
+ * Draw a sheet of diagrams as an SVG image.
+***********************************************************/
+pub fn to_svg(sheet: &Sheet) -> String {
+    let (width, height) = sheet.dimensions();
+    let mut svg = Svg { body: String::new() };
+    sheet.draw(&mut svg);
+    format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" \
+             viewBox=\"0 0 {0} {1}\" font-family=\"sans-serif\">\n{2}</svg>\n", width, height, svg.body)
+}
+
+/***********************************************************
+ * SAVE
+ ***********************************************************
+ * Draw a game after each of the given numbers of moves,
+ * in cells of the given number of pixels, and write the
+ * sheet to a file: a PNG image if the name ends in ".png",
+ * otherwise SVG. With more than one position each diagram
+ * is captioned with its move number. Cells outside
+ * MIN_CELL to MAX_CELL, and sheets of more than MAX_PIXELS,
+ * are refused.
+***********************************************************/
+pub fn save(game: &Game, at: &[usize], marks: Marks, cell: usize, path: &str) -> Result<(), String> {
+    if !(MIN_CELL..=MAX_CELL).contains(&cell) {
+        return Err(format!("Cells must be from {} to {} pixels", MIN_CELL, MAX_CELL));
+    }
+    let mut diagrams = Vec::new();
+    for moves in at {
+        let caption = if at.len() > 1 { Some(moves.to_string()) } else { None };
+        diagrams.push((Diagram::from_game(game, *moves, marks)?, caption));
+    }
+    let sheet = Sheet { diagrams, layout: Layout { size: game.size, cell } };
+    let (width, height) = sheet.dimensions();
+    if width * height > MAX_PIXELS {
+        return Err(format!("The drawing would be {}x{} pixels, more than the {} allowed; \
+                            use smaller cells or fewer positions", width, height, MAX_PIXELS));
+    }
+    let bytes = if path.to_lowercase().ends_with(".png") {
+        png::to_png(&sheet)
+    }
+    else {
+        to_svg(&sheet).into_bytes()
+    };
+    fs::write(path, bytes).map_err(|e| format!("Couldn't write \"{}\": {}", path, e))
+}
+
+/***********************************************************
+ * HEX
+ ***********************************************************
+ * An SVG color.
+***********************************************************/
+fn hex(color: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    // A game of C4 by "X" then D4 by "O"
+    fn game() -> Game {
+        let mut game = Game::new(4, ["X".to_string(), "O".to_string()]);
+        game.play(Move::Place(NumLet { n: 3, l: 2 }), true, Duration::from_secs(1));
+        game.play(Move::Place(NumLet { n: 3, l: 3 }), false, Duration::from_secs(1));
+        game
+    }
+
+    const ALL: Marks = Marks { last: true, legal: true, numbers: true };
+
+    #[test]
+    fn marks_are_read_from_a_list() {
+        assert_eq!(parse_marks("last, numbers"), Ok(Marks { last: true, legal: false, numbers: true }));
+        assert_eq!(parse_marks("none"), Ok(Marks { last: false, legal: false, numbers: false }));
+        assert_eq!(parse_marks("legal,last,numbers"), Ok(ALL));
+        assert!(parse_marks("last,arrows").is_err());
+    }
+
+    #[test]
+    fn diagrams_mark_the_position_after_the_moves() {
+        let game = game();
+        let diagram = Diagram::from_game(&game, 2, ALL).unwrap();
+        assert_eq!(diagram.board, game.positions().unwrap().pop().unwrap().0);
+        assert_eq!(diagram.last, Some(NumLet { n: 3, l: 3 }));
+        assert_eq!(diagram.legal, legal_moves(&diagram.board, true));
+        assert_eq!(diagram.numbers, [(NumLet { n: 3, l: 2 }, 1), (NumLet { n: 3, l: 3 }, 2)]);
+
+        let start = Diagram::from_game(&game, 0, ALL).unwrap();
+        assert_eq!(start.last, None);
+        assert!(start.numbers.is_empty());
+        assert_eq!(start.legal.len(), 4);
+
+        let plain = Diagram::from_game(&game, 1, parse_marks("none").unwrap()).unwrap();
+        assert_eq!((plain.last, plain.legal.len(), plain.numbers.len()), (None, 0, 0));
+        assert!(Diagram::from_game(&game, 3, ALL).is_err());
+    }
+
+    #[test]
+    fn resigned_games_have_no_legal_moves() {
+        let mut game = game();
+        game.play(Move::Resign, true, Duration::from_secs(1));
+        assert!(Diagram::from_game(&game, 3, ALL).unwrap().legal.is_empty());
+    }
+
+    #[test]
+    fn svg_shows_the_board_discs_and_marks() {
+        let game = game();
+        let diagram = Diagram::from_game(&game, 2, Marks::default()).unwrap();
+        let svg = to_svg(&Sheet { diagrams: vec![(diagram.clone(), None)], layout: Layout { size: 4, cell: 50 } });
+        let width = 4 * 50 + 2 * 30;
+        assert!(svg.starts_with(&format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{0}\"", width)));
+        assert!(svg.ends_with("</svg>\n"));
+        for label in ["A", "D", "1", "4"].iter() {
+            assert!(svg.contains(&format!(">{}</text>", label)));
+        }
+        let discs = diagram.board.iter().flatten().filter(|p| **p != " ").count();
+        let circles = svg.matches("<circle").count();
+        assert_eq!(circles, 2 * discs + diagram.legal.len() + 1);    // Outlined discs and a ring on the last move
+        assert_eq!(svg.matches(&hex(MARK_COLOR)).count(), 1);
+        assert_eq!(svg.matches(&format!("fill=\"{}\"", hex(BLACK_COLOR))).count(),
+                   diagram.board.iter().flatten().filter(|p| **p == _X).count());
+    }
+
+    #[test]
+    fn sheets_lay_diagrams_out_in_rows() {
+        let game = game();
+        let layout = Layout { size: 4, cell: 50 };
+        let sheet = |count: usize| Sheet {
+            diagrams: (0..count).map(|i| (Diagram::from_game(&game, i % 3, ALL).unwrap(), Some(i.to_string())))
+                .collect(),
+            layout
+        };
+        assert_eq!(sheet(1).dimensions(), (260, 260));
+        assert_eq!(sheet(6).dimensions(), (6 * 260, 260));
+        assert_eq!(sheet(7).dimensions(), (6 * 260, 2 * 260));
+        let svg = to_svg(&sheet(7));
+        assert!(svg.contains(">6</text>"));         // The caption of the seventh diagram
+        assert_eq!(svg.matches(&format!("fill=\"{}\"", hex(BOARD_COLOR))).count(), 7);
+    }
+
+    #[test]
+    fn huge_drawings_are_refused() {
+        let game = game();
+        let path = std::env::temp_dir().join(format!("othello-diagram-{}.png", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = fs::remove_file(path);
+        assert!(save(&game, &[0], ALL, MAX_CELL + 1, path).is_err());
+        assert!(save(&game, &[0], ALL, MIN_CELL - 1, path).is_err());
+        let many = vec![2; 400];
+        let error = save(&game, &many, ALL, MAX_CELL, path).unwrap_err();
+        assert!(error.starts_with("The drawing would be "), "{}", error);
+        assert!(fs::metadata(path).is_err());       // Nothing was written
+        save(&game, &[0, 1, 2], ALL, 16, path).unwrap();
+        assert!(fs::read(path).unwrap().starts_with(b"\x89PNG"));
+        fs::remove_file(path).unwrap();
+    }
+}
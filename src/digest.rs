@@ -0,0 +1,171 @@
+/***********************************************************
+ * DIGEST
+ ***********************************************************
+ * The hashing and encoding the rest of the crate shares,
+ * with no outside library:
+ *     sha1()      For the WebSocket handshake (see
+ *                 websocket.rs) and the server's passwords
+ *                 (see ratings.rs), where its weaknesses
+ *                 don't matter.
+ *     base64()    For the handshake's answer.
+ *     new_token() For the tokens players come back to a
+ *                 game with (see network.rs) and the
+ *                 passwords' salts.
+***********************************************************/
+
+use std::collections::hash_map::RandomState;
+use std::fs::File;
+use std::hash::{BuildHasher, Hasher};
+use std::io::Read;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/***********************************************************
+ * SHA1
+ ***********************************************************
+ * The SHA-1 hash of some bytes (FIPS 180-4).
+***********************************************************/
+pub fn sha1(bytes: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x6745_2301, 0xEFCD_AB89, 0x98BA_DCFE, 0x1032_5476, 0xC3D2_E1F0];
+
+    // The message, a 1 bit, zeros to 56 bytes into a block
+    // and the length in bits
+    let mut data = bytes.to_vec();
+    data.push(0x80);
+    while data.len() % 64 != 56 {
+        data.push(0);
+    }
+    data.extend_from_slice(&((bytes.len() as u64) * 8).to_be_bytes());
+
+    for block in data.chunks(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A82_7999),
+                20..=39 => (b ^ c ^ d, 0x6ED9_EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1B_BCDC),
+                _ => (b ^ c ^ d, 0xCA62_C1D6),
+            };
+            let next = a.rotate_left(5).wrapping_add(f).wrapping_add(e).wrapping_add(k).wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = next;
+        }
+        for (total, part) in h.iter_mut().zip([a, b, c, d, e]) {
+            *total = total.wrapping_add(part);
+        }
+    }
+
+    let mut hash = [0u8; 20];
+    for (i, word) in h.iter().enumerate() {
+        hash[i * 4..i * 4 + 4].copy_from_slice(&word.to_be_bytes());
+    }
+    hash
+}
+
+/***********************************************************
+ * BASE64
+ ***********************************************************
+ * Bytes written as base64 text, with "=" padding.
+***********************************************************/
+pub fn base64(bytes: &[u8]) -> String {
+    static DIGITS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut text = String::new();
+    for group in bytes.chunks(3) {
+        let n = (group[0] as u32) << 16 | (*group.get(1).unwrap_or(&0) as u32) << 8
+            | *group.get(2).unwrap_or(&0) as u32;
+        for i in 0..4 {
+            if i <= group.len() {
+                text.push(DIGITS[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+            }
+            else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
+
+/***********************************************************
+ * NEW_TOKEN
+ ***********************************************************
+ * A token nobody can guess: 16 bytes in hex, read from
+ * /dev/urandom where there is one. Elsewhere (Windows),
+ * or if it can't be read, which is reported, the bytes
+ * are hashes of the time under the standard library's
+ * hash keys, which it takes from the operating system's
+ * random number source.
+***********************************************************/
+pub fn new_token() -> String {
+    let mut bytes = [0u8; 16];
+    let read = if cfg!(unix) {
+        File::open("/dev/urandom").and_then(|mut random| random.read_exact(&mut bytes))
+            .map_err(|e| println!("Couldn't read /dev/urandom ({}); using the hash keys instead.", e))
+            .is_ok()
+    }
+    else {
+        false
+    };
+    if !read {
+        bytes = hashed();
+    }
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// 16 bytes hashed from the time, each half under its own
+// secret keys
+fn hashed() -> [u8; 16] {
+    let mut bytes = [0u8; 16];
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos());
+    for (i, half) in bytes.chunks_mut(8).enumerate() {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u128(now);
+        hasher.write_usize(i);
+        half.copy_from_slice(&hasher.finish().to_le_bytes());
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A hash as lowercase hex
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn sha1_matches_rfc_3174() {
+        assert_eq!(hex(&sha1(b"abc")), "a9993e364706816aba3e25717850c26c9cd0d89d");
+        assert_eq!(hex(&sha1(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq")),
+                   "84983e441c3bd26ebaae4aa1f95129e5e54670f1");
+        assert_eq!(hex(&sha1(&[b'a'; 1_000_000])), "34aa973cd4c4daa4f61eeb2bdbad27316534016f");
+        assert_eq!(hex(&sha1(b"")), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+    }
+
+    #[test]
+    fn base64_matches_rfc_4648() {
+        let cases = [("", ""), ("f", "Zg=="), ("fo", "Zm8="), ("foo", "Zm9v"), ("foob", "Zm9vYg=="),
+                     ("fooba", "Zm9vYmE="), ("foobar", "Zm9vYmFy")];
+        for (bytes, text) in cases {
+            assert_eq!(base64(bytes.as_bytes()), text);
+        }
+    }
+
+    #[test]
+    fn tokens_are_hex_and_differ() {
+        let tokens: Vec<String> = (0..3).map(|_| new_token()).collect();
+        assert!(tokens.iter().all(|t| t.len() == 32 && t.chars().all(|c| c.is_ascii_hexdigit())));
+        assert!(tokens[0] != tokens[1] && tokens[1] != tokens[2] && tokens[0] != tokens[2]);
+        assert!(hashed() != hashed() && hashed() != [0; 16]);
+    }
+}
@@ -0,0 +1,315 @@
+/***********************************************************
+ * ENGINE PLAYERS
+ ***********************************************************
+ * An engine is a separate program that plays a side of the
+ * game. It is started as a child process and the game
+ * talks to it one line at a time over its stdin and stdout.
+ *
+ * Lines sent to the engine:
+ *     new <size> <X|O>    A new game on a size*size board;
+ *                         the engine plays the given piece.
+ *                         The engine answers "ready".
+ *     position <cells> <X|O>
+ *                         The board row by row ("A" first),
+ *                         one character per location ("X",
+ *                         "O" or "-" for empty), then the
+ *                         piece to move; the position string
+ *                         of position.rs. No answer.
+ *     go <milliseconds>   Choose a move for the position last
+ *                         sent within the time given. The
+ *                         engine answers "move <location>"
+ *                         (for example "move B3") or
+ *                         "move resign".
+ *     opponent <location|pass|resign>
+ *                         What the other player just did. No
+ *                         answer.
+ *     quit                The engine should exit.
+ *
+ * Locations are the row letter followed by the column
+ * number, as shown by display(). Lines from the engine that
+ * start with "#" are ignored so engines can log freely.
+ *
+ * An engine that doesn't answer in time, exits, or answers
+ * with anything but a legal move forfeits the game.
+***********************************************************/
+
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::player::{Move, Player};
+use crate::position::position_string;
+use crate::{Board, NumLet, _X, _O, get_coordinates, coordinates};
+
+/***********************************************************
+ * ENGINE_PLAYER
+ ***********************************************************
+ * A player backed by an engine program. Once the engine
+ * has crashed or broken the protocol it is considered dead
+ * and forfeits every game it is asked to play.
+***********************************************************/
+pub struct EnginePlayer {
+    command: String,            // The command line used to start the engine
+    child: Child,               // The running engine
+    stdin: ChildStdin,          // Where lines to the engine are written
+    lines: Receiver<String>,    // Lines the engine has written, read on another thread
+    timeout: Duration,          // How long the engine has to answer
+    dead: bool                  // True once the engine can no longer play
+}
+
+impl EnginePlayer {
+    /*******************************************************
+     * NEW
+     *******************************************************
+     * Start the engine. The command is split on whitespace
+     * into the program and its arguments.
+    *******************************************************/
+    pub fn new(command: &str, timeout: Duration) -> Result<EnginePlayer, String> {
+        let mut parts = command.split_whitespace();
+        let program = match parts.next() {
+            Some(program) => program,
+            None => return Err("The engine command is empty".to_string()),
+        };
+        let mut child = Command::new(program)
+            .args(parts)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Couldn't start engine \"{}\": {}", command, e))?;
+        let stdin = child.stdin.take().unwrap();    // Both were piped above
+        let stdout = child.stdout.take().unwrap();
+
+        // Read the engine's output on its own thread so that
+        // waiting for an answer can time out.
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                match line {
+                    Ok(line) => if sender.send(line).is_err() { break },
+                    Err(_) => break,
+                }
+            }
+        });
+
+        Ok(EnginePlayer { command: command.to_string(), child, stdin, lines, timeout, dead: false })
+    }
+
+    /*******************************************************
+     * SEND
+     *******************************************************
+     * Write a line to the engine. The engine is dead if the
+     * line can't be written.
+    *******************************************************/
+    fn send(&mut self, line: &str) {
+        if self.dead {
+            return;
+        }
+        if writeln!(self.stdin, "{}", line).and_then(|_| self.stdin.flush()).is_err() {
+            self.forfeit("stopped reading its input");
+        }
+    }
+
+    /*******************************************************
+     * EXPECT
+     *******************************************************
+     * Wait for the engine's answer, the first line that
+     * starts with the given word, and return the rest of
+     * that line. Returns None (and the engine is dead) if
+     * it doesn't answer in time or has exited.
+    *******************************************************/
+    fn expect(&mut self, word: &str) -> Option<String> {
+        if self.dead {
+            return None;
+        }
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            match self.lines.recv_timeout(left) {
+                Ok(line) => {
+                    let line = line.trim();
+                    if line.starts_with('#') || line.is_empty() {
+                        continue;                   // Engine log output
+                    }
+                    let mut parts = line.splitn(2, ' ');
+                    if parts.next() == Some(word) {
+                        return Some(parts.next().unwrap_or("").trim().to_string());
+                    }
+                    self.forfeit(&format!("answered \"{}\" instead of \"{}\"", line, word));
+                    return None;
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    self.forfeit("ran out of time");
+                    return None;
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.forfeit("exited");
+                    return None;
+                }
+            }
+        }
+    }
+
+    /*******************************************************
+     * FORFEIT
+     *******************************************************
+     * Mark the engine as dead and tell the users why.
+    *******************************************************/
+    fn forfeit(&mut self, reason: &str) {
+        if !self.dead {
+            println!("Engine \"{}\" {} and forfeits.", self.command, reason);
+            self.dead = true;
+        }
+    }
+}
+
+impl Player for EnginePlayer {
+    fn new_game(&mut self, board: &Board, is_player_one: bool) {
+        let piece = if is_player_one { _X } else { _O };
+        self.send(&format!("new {} {}", board.size(), piece));
+        self.expect("ready");
+    }
+
+    fn choose_move(&mut self, board: &Board,
+                   is_player_one: bool, legal: &[NumLet]) -> Move {
+        self.send(&format!("position {}", position_string(board, is_player_one)));
+        self.send(&format!("go {}", self.timeout.as_millis()));
+        let answer = match self.expect("move") {
+            Some(answer) => answer,
+            None => return Move::Resign,    // Dead engines forfeit
+        };
+        if answer.to_lowercase() == "resign" {
+            return Move::Resign;
+        }
+        let mut num: usize = 0;             // The column number index
+        let mut letter: usize = 0;          // The row letter index
+        if get_coordinates(&mut num, &mut letter, answer.clone()) {
+            let nl = NumLet { n: num, l: letter };
+            if legal.contains(&nl) {
+                return Move::Place(nl);
+            }
+        }
+        self.forfeit(&format!("played the illegal move \"{}\"", answer));
+        Move::Resign
+    }
+
+    fn opponent_moved(&mut self, _board: &Board, mv: Move) {
+        match mv {
+            Move::Place(nl) => self.send(&format!("opponent {}", coordinates(nl))),
+            Move::Pass => self.send("opponent pass"),
+            Move::Resign => self.send("opponent resign"),
+            _ => {}                             // Saving and loading aren't moves
+        }
+    }
+}
+
+impl Drop for EnginePlayer {
+    // Ask the engine to quit, and stop it if it doesn't.
+    fn drop(&mut self) {
+        let _ = writeln!(self.stdin, "quit").and_then(|_| self.stdin.flush());
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while Instant::now() < deadline {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;                         // The engine quit by itself
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+    use crate::{create_board, legal_moves};
+
+    // Start a shell script engine that writes every line it
+    // is sent to a log and runs on_go when asked to move.
+    // Returns the engine and the path of its log.
+    fn engine(name: &str, on_go: &str, timeout: Duration) -> (EnginePlayer, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("othello-engine-{}-{}", std::process::id(), name));
+        fs::create_dir_all(&dir).unwrap();
+        let log = dir.join("log");
+        let script = dir.join("engine.sh");
+        fs::write(&script, format!(
+            "while read line; do\n\
+             \x20 echo \"$line\" >> {}\n\
+             \x20 case \"$line\" in\n\
+             \x20   new*) echo ready ;;\n\
+             \x20   go*) {} ;;\n\
+             \x20   quit) exit 0 ;;\n\
+             \x20 esac\n\
+             done\n", log.display(), on_go)).unwrap();
+        let player = EnginePlayer::new(&format!("sh {}", script.display()), timeout).unwrap();
+        (player, log)
+    }
+
+    // The starting position
+    fn start() -> Board {
+        let mut board = Board::new(4);
+        create_board(&mut board);
+        board
+    }
+
+    #[test]
+    fn engines_are_sent_the_game_and_their_moves_are_played() {
+        let (mut player, log) = engine("play", "echo '# thinking'; echo move C4", Duration::from_secs(5));
+        let board = start();
+        player.new_game(&board, true);
+        assert_eq!(player.choose_move(&board, true, &legal_moves(&board, true)), Move::Place(NumLet { n: 3, l: 2 }));
+        player.opponent_moved(&board, Move::Pass);
+        player.opponent_moved(&board, Move::Place(NumLet { n: 0, l: 0 }));
+        drop(player);                               // Sends quit
+        assert_eq!(fs::read_to_string(log).unwrap(), "new 4 X\nposition -----OX--XO----- X\ngo 5000\n\
+                                                       opponent pass\nopponent A1\nquit\n");
+    }
+
+    #[test]
+    fn engines_can_resign() {
+        let (mut player, _) = engine("resign", "echo move resign", Duration::from_secs(5));
+        let board = start();
+        player.new_game(&board, false);
+        assert_eq!(player.choose_move(&board, false, &legal_moves(&board, false)), Move::Resign);
+        assert!(!player.dead);
+    }
+
+    #[test]
+    fn illegal_moves_forfeit() {
+        let (mut player, _) = engine("illegal", "echo move A1", Duration::from_secs(5));
+        let board = start();
+        player.new_game(&board, true);
+        assert_eq!(player.choose_move(&board, true, &legal_moves(&board, true)), Move::Resign);
+        assert!(player.dead);
+        // A dead engine forfeits without being asked again
+        assert_eq!(player.choose_move(&board, true, &legal_moves(&board, true)), Move::Resign);
+    }
+
+    #[test]
+    fn slow_engines_forfeit() {
+        let (mut player, _) = engine("slow", ":", Duration::from_millis(200));
+        let board = start();
+        player.new_game(&board, true);
+        let started = Instant::now();
+        assert_eq!(player.choose_move(&board, true, &legal_moves(&board, true)), Move::Resign);
+        assert!(player.dead);
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn crashed_engines_forfeit() {
+        let (mut player, _) = engine("crash", "exit 1", Duration::from_secs(5));
+        let board = start();
+        player.new_game(&board, true);
+        assert_eq!(player.choose_move(&board, true, &legal_moves(&board, true)), Move::Resign);
+        assert!(player.dead);
+    }
+
+    #[test]
+    fn missing_engines_are_errors() {
+        assert!(EnginePlayer::new("", Duration::from_secs(1)).is_err());
+        assert!(EnginePlayer::new("./no-such-engine", Duration::from_secs(1)).is_err());
+    }
+}
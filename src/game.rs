@@ -0,0 +1,537 @@
+/***********************************************************
+ * GAME RECORDS
+ ***********************************************************
+ * A Game is everything needed to pick a game back up: the
+ * board size, who is playing each side, every move in
+ * order (passes included) with the time it took, and how
+ * much time each player has used. The board itself isn't
+ * stored; it is rebuilt by replaying the moves through the
+ * rules, which also checks that the record is valid.
+ * Games normally start from the usual four pieces in the
+ * middle, but a record can hold a different starting
+ * position (a puzzle, for example). Games brought in from
+ * other programs can also carry the players' names and
+ * ratings, where and when they were played, the time
+ * control, the result and an evaluation for each move.
+ *
+ * Games are saved as plain text transcripts, one item per
+ * line:
+ *     # Othello transcript
+ *     version 3
+ *     size 8
+ *     start ---------------------------OX------XO--------------------------- X
+ *     player X human
+ *     player O engine:./my_bot --fast
+ *     name X Marc Tastet
+ *     rating X 2250.5
+ *     place Paris Open
+ *     date 2024.05.04
+ *     time_control 15:00
+ *     result +12
+ *     clock X 12.345
+ *     clock O 3.210
+ *     move X D3 2.100
+ *     move O pass 0.000
+ *     move X C3 4.000 -1.50
+ *     move X resign 1.500
+ * Clock values and move times are in seconds, and a move
+ * may end with its evaluation. Locations are the row
+ * letter and column number as display() shows them. The
+ * "start" line is a position string (see position.rs) and
+ * is only written for games that don't start from the
+ * usual position; version 1 transcripts never have one.
+ * The lines from "name" to "result", added in version 3,
+ * are only written when they are known. Blank lines and
+ * lines starting with "#" are ignored.
+***********************************************************/
+
+use std::fs;
+use std::time::Duration;
+use crate::ggf::{self, to_ggf};
+use crate::player::Move;
+use crate::position::{parse_position, position_string};
+use crate::{Board, NumLet, _X, _O, coordinates, create_board, get_coordinates,
+            legal_moves, place, valid_size};
+
+const VERSION: u32 = 3;     // The transcript format written by to_text()
+
+/***********************************************************
+ * TURN
+ ***********************************************************
+ * One move of a game, how long the player took over it
+ * and, for games from other programs, how the move was
+ * evaluated (in discs, for the player who made it).
+***********************************************************/
+#[derive(Clone, Debug, PartialEq)]
+pub struct Turn {
+    pub mv: Move,           // What the player did
+    pub time: Duration,     // How long they took
+    pub eval: Option<f64>   // The evaluation given with the move, if any
+}
+
+/***********************************************************
+ * GAME_INFO
+ ***********************************************************
+ * What is known about a game besides its moves. Everything
+ * is optional; games played here know none of it.
+***********************************************************/
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GameInfo {
+    pub names: [Option<String>; 2],     // Names of the players of "X" and "O"
+    pub ratings: [Option<f64>; 2],      // Their ratings
+    pub place: Option<String>,          // Where it was played (a tournament or server)
+    pub date: Option<String>,           // When it was played
+    pub time_control: Option<String>,   // The time each player had, as the source wrote it
+    pub result: Option<String>          // The result, as the source wrote it (for example "+12")
+}
+
+/***********************************************************
+ * GAME
+ ***********************************************************
+ * The record of a game, finished or not.
+***********************************************************/
+#[derive(Clone, Debug, PartialEq)]
+pub struct Game {
+    pub size: usize,            // Number of rows and columns on the board
+    pub players: [String; 2],   // Who plays "X" and "O" (for example "human")
+    pub start: Option<(Board, bool)>,   // The starting board and player, if not the usual ones
+    pub info: GameInfo,         // Names, ratings and so on, where known
+    pub moves: Vec<Turn>,       // Every move so far, in order
+    pub clock: [Duration; 2]    // Time used so far by "X" and "O"
+}
+
+impl Game {
+    /*******************************************************
+     * NEW
+     *******************************************************
+     * A game that hasn't started yet.
+    *******************************************************/
+    pub fn new(size: usize, players: [String; 2]) -> Game {
+        Game {
+            size,
+            players,
+            start: None,
+            info: GameInfo::default(),
+            moves: Vec::new(),
+            clock: [Duration::from_secs(0); 2]
+        }
+    }
+
+    /*******************************************************
+     * FROM_POSITION
+     *******************************************************
+     * A game that hasn't started yet and will start from
+     * the board given, with the given player to move.
+    *******************************************************/
+    pub fn from_position(board: Board, is_player_one: bool, players: [String; 2]) -> Game {
+        let mut game = Game::new(board.size(), players);
+        game.start = Some((board, is_player_one));
+        game
+    }
+
+    /*******************************************************
+     * START_POSITION
+     *******************************************************
+     * The board the game starts from and whether "X" moves
+     * first.
+    *******************************************************/
+    pub fn start_position(&self) -> (Board, bool) {
+        match &self.start {
+            Some(start) => start.clone(),
+            None => {
+                let mut board = Board::new(self.size);
+                create_board(&mut board);
+                (board, true)
+            }
+        }
+    }
+
+    /*******************************************************
+     * PLAY
+     *******************************************************
+     * Add a move to the record and charge its time to the
+     * player who made it. The move isn't checked.
+    *******************************************************/
+    pub fn play(&mut self, mv: Move, is_player_one: bool, time: Duration) {
+        self.clock[if is_player_one { 0 } else { 1 }] += time;
+        self.moves.push(Turn { mv, time, eval: None });
+    }
+
+    /*******************************************************
+     * POSITIONS
+     *******************************************************
+     * Replay the moves from the starting board. Returns the
+     * board and whether "X" is to move before every move,
+     * followed by the position after the last move, or a
+     * message saying which move breaks the rules.
+    *******************************************************/
+    pub fn positions(&self) -> Result<Vec<(Board, bool)>, String> {
+        let (mut board, mut is_player_one) = self.start_position();
+        let mut v = Vec::new();
+        for (i, turn) in self.moves.iter().enumerate() {
+            v.push((board.clone(), is_player_one));
+            let piece = if is_player_one { _X } else { _O };
+            let legal = legal_moves(&board, is_player_one);
+            match &turn.mv {
+                Move::Place(nl) if legal.contains(nl) => {
+                    place(&mut board, *nl, is_player_one);
+                }
+                Move::Pass if legal.is_empty() => {}
+                Move::Resign if i == self.moves.len() - 1 => {}
+                mv => return Err(format!("Move {} ({} {}) is not legal",
+                                         i + 1, piece, move_text(mv))),
+            }
+            is_player_one = !is_player_one;
+        }
+        v.push((board, is_player_one));
+        Ok(v)
+    }
+
+    /*******************************************************
+     * POSITION
+     *******************************************************
+     * The board and whether "X" is to move after the last
+     * move of the record.
+    *******************************************************/
+    pub fn position(&self) -> Result<(Board, bool), String> {
+        Ok(self.positions()?.pop().unwrap())    // There is always a final position
+    }
+
+    /*******************************************************
+     * TO_TEXT
+     *******************************************************
+     * Write the game as a transcript.
+    *******************************************************/
+    pub fn to_text(&self) -> String {
+        let mut text = "# Othello transcript\n".to_string();
+        text += &format!("version {}\n", VERSION);
+        text += &format!("size {}\n", self.size);
+        if let Some((board, is_player_one)) = &self.start {
+            text += &format!("start {}\n", position_string(board, *is_player_one));
+        }
+        text += &format!("player {} {}\n", _X, self.players[0]);
+        text += &format!("player {} {}\n", _O, self.players[1]);
+        for (i, piece) in [_X, _O].iter().enumerate() {
+            if let Some(name) = &self.info.names[i] {
+                text += &format!("name {} {}\n", piece, name);
+            }
+            if let Some(rating) = self.info.ratings[i] {
+                text += &format!("rating {} {}\n", piece, rating);
+            }
+        }
+        let info = [("place", &self.info.place), ("date", &self.info.date),
+                    ("time_control", &self.info.time_control), ("result", &self.info.result)];
+        for (key, value) in info.iter() {
+            if let Some(value) = value {
+                text += &format!("{} {}\n", key, value);
+            }
+        }
+        text += &format!("clock {} {:.3}\n", _X, self.clock[0].as_secs_f64());
+        text += &format!("clock {} {:.3}\n", _O, self.clock[1].as_secs_f64());
+        let mut is_player_one = self.start_position().1;   // Moves alternate, passes included
+        for turn in self.moves.iter() {
+            let piece = if is_player_one { _X } else { _O };
+            text += &format!("move {} {} {:.3}", piece, move_text(&turn.mv), turn.time.as_secs_f64());
+            if let Some(eval) = turn.eval {
+                text += &format!(" {:.2}", eval);
+            }
+            text += "\n";
+            is_player_one = !is_player_one;
+        }
+        text
+    }
+
+    /*******************************************************
+     * FROM_TEXT
+     *******************************************************
+     * Read a transcript, checking every move against the
+     * rules. Returns a message saying what is wrong if the
+     * transcript can't be used.
+    *******************************************************/
+    pub fn from_text(text: &str) -> Result<Game, String> {
+        let mut game = Game::new(0, ["human".to_string(), "human".to_string()]);
+        let mut version = None;
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let bad = || format!("Line {} of the transcript is not understood: \"{}\"", i + 1, line);
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words[0] {
+                "version" => match words.get(1).and_then(|v| v.parse::<u32>().ok()) {
+                    Some(v) if v <= VERSION => version = Some(v),
+                    Some(v) => return Err(format!("Transcript version {} is newer than this program", v)),
+                    None => return Err(bad()),
+                },
+                "size" => match words.get(1).and_then(|s| s.parse::<usize>().ok()) {
+                    Some(size) if valid_size(size) => game.size = size,
+                    _ => return Err(bad()),
+                },
+                "player" if words.len() >= 3 => {
+                    let kind = line.splitn(3, char::is_whitespace).nth(2).unwrap().trim().to_string();
+                    game.players[piece_index(words[1]).ok_or_else(bad)?] = kind;
+                }
+                "name" if words.len() >= 3 => {
+                    let name = line.splitn(3, char::is_whitespace).nth(2).unwrap().trim().to_string();
+                    game.info.names[piece_index(words[1]).ok_or_else(bad)?] = Some(name);
+                }
+                "rating" if words.len() == 3 => {
+                    let rating = words[2].parse::<f64>().map_err(|_| bad())?;
+                    game.info.ratings[piece_index(words[1]).ok_or_else(bad)?] = Some(rating);
+                }
+                "place" | "date" | "time_control" | "result" if words.len() >= 2 => {
+                    let value = Some(line[words[0].len()..].trim().to_string());
+                    match words[0] {
+                        "place" => game.info.place = value,
+                        "date" => game.info.date = value,
+                        "time_control" => game.info.time_control = value,
+                        _ => game.info.result = value,
+                    }
+                }
+                "start" if game.moves.is_empty() => {
+                    let (board, is_player_one) = parse_position(&line[5..])
+                        .map_err(|e| format!("{}: {}", bad(), e))?;
+                    game.size = board.size();
+                    game.start = Some((board, is_player_one));
+                }
+                "clock" | "move" if game.size == 0 => {
+                    return Err(format!("Line {} of the transcript comes too early: the size must come first", i + 1));
+                }
+                "clock" if words.len() == 3 => {
+                    game.clock[piece_index(words[1]).ok_or_else(bad)?] = seconds(words[2]).ok_or_else(bad)?;
+                }
+                "move" if words.len() == 4 || words.len() == 5 => {
+                    // Moves alternate, passes included
+                    let is_player_one = game.moves.len().is_multiple_of(2) == game.start_position().1;
+                    if piece_index(words[1]) != Some(if is_player_one { 0 } else { 1 }) {
+                        return Err(bad());
+                    }
+                    let mv = parse_move(words[2]).ok_or_else(bad)?;
+                    let time = seconds(words[3]).ok_or_else(bad)?;
+                    let eval = match words.get(4) {
+                        Some(eval) => Some(eval.parse::<f64>().map_err(|_| bad())?),
+                        None => None,
+                    };
+                    game.moves.push(Turn { mv, time, eval });
+                }
+                _ => return Err(bad()),
+            }
+        }
+        if version.is_none() {
+            return Err("The file is not an Othello transcript".to_string());
+        }
+        if game.size == 0 {
+            return Err("The transcript has no board size".to_string());
+        }
+        if game.start.as_ref().is_some_and(|(board, _)| board.size() != game.size) {
+            return Err("The starting position doesn't match the board size".to_string());
+        }
+        game.positions()?;                  // Make sure the moves follow the rules
+        Ok(game)
+    }
+
+    /*******************************************************
+     * SAVE
+     *******************************************************
+     * Write the game to a file: as a GGF record if the
+     * name ends in ".ggf", as JSON if it ends in ".json"
+     * (with the "json" feature), otherwise as a transcript.
+    *******************************************************/
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let lower = path.to_lowercase();
+        let text = if lower.ends_with(".ggf") {
+            to_ggf(self)
+        }
+        else if lower.ends_with(".json") {
+            json_text(self)?
+        }
+        else {
+            self.to_text()
+        };
+        fs::write(path, text).map_err(|e| format!("Couldn't save to \"{}\": {}", path, e))
+    }
+
+    /*******************************************************
+     * LOAD
+     *******************************************************
+     * Read a game from a file: a transcript, a GGF file
+     * (the first game in it) or a JSON record.
+    *******************************************************/
+    pub fn load(path: &str) -> Result<Game, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("Couldn't load \"{}\": {}", path, e))?;
+        if text.trim_start().starts_with("(;") {
+            return Ok(ggf::parse(&text)?.remove(0));
+        }
+        if text.trim_start().starts_with('{') {
+            return json_game(&text);
+        }
+        Game::from_text(&text)
+    }
+}
+
+/***********************************************************
+ * JSON_TEXT / JSON_GAME
+ ***********************************************************
+ * Write and read JSON records, or explain that the program
+ * was built without them.
+***********************************************************/
+#[cfg(feature = "json")]
+fn json_text(game: &Game) -> Result<String, String> {
+    Ok(crate::json::to_json(game))
+}
+
+#[cfg(feature = "json")]
+fn json_game(text: &str) -> Result<Game, String> {
+    crate::json::from_json(text)
+}
+
+#[cfg(not(feature = "json"))]
+fn json_text(_game: &Game) -> Result<String, String> {
+    Err(NO_JSON.to_string())
+}
+
+#[cfg(not(feature = "json"))]
+fn json_game(_text: &str) -> Result<Game, String> {
+    Err(NO_JSON.to_string())
+}
+
+#[cfg(not(feature = "json"))]
+static NO_JSON: &str = "JSON games need the program built with \"--features json\"";
+
+/***********************************************************
+ * MOVE_TEXT
+ ***********************************************************
+ * How a move is written in a transcript: its location,
+ * "pass" or "resign". Games never record the commands
+ * that aren't moves; they are written as they are typed.
+***********************************************************/
+pub fn move_text(mv: &Move) -> String {
+    match mv {
+        Move::Place(nl) => coordinates(*nl),
+        Move::Pass => "pass".to_string(),
+        Move::Resign => "resign".to_string(),
+        Move::Save(path) => format!("save {}", path),
+        Move::Load(path) => format!("load {}", path),
+        Move::Diagram(path) => format!("diagram {}", path),
+        Move::Chat(line) => line.clone(),   // Already "chat <text>"
+    }
+}
+
+/***********************************************************
+ * PARSE_MOVE
+ ***********************************************************
+ * Read a move written by move_text().
+***********************************************************/
+pub fn parse_move(text: &str) -> Option<Move> {
+    match text.to_lowercase().as_str() {
+        "pass" => Some(Move::Pass),
+        "resign" => Some(Move::Resign),
+        _ => {
+            let mut num: usize = 0;         // The column number index
+            let mut letter: usize = 0;      // The row letter index
+            if get_coordinates(&mut num, &mut letter, text.to_string()) {
+                Some(Move::Place(NumLet { n: num, l: letter }))
+            }
+            else {
+                None
+            }
+        }
+    }
+}
+
+/***********************************************************
+ * PIECE_INDEX
+ ***********************************************************
+ * 0 for "X" and 1 for "O".
+***********************************************************/
+fn piece_index(text: &str) -> Option<usize> {
+    if text == _X { Some(0) } else if text == _O { Some(1) } else { None }
+}
+
+/***********************************************************
+ * SECONDS
+ ***********************************************************
+ * Read a number of seconds such as "12.345".
+***********************************************************/
+fn seconds(text: &str) -> Option<Duration> {
+    match text.parse::<f64>() {
+        Ok(secs) => Duration::try_from_secs_f64(secs).ok(),  // Not negative or too long to hold
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    // A 4x4 game played to the end, with a pass by each side
+    pub(crate) fn sample() -> Game {
+        play(&["C4", "D4", "D3", "B4", "A3", "D2", "D1", "C1", "B1", "A4", "pass", "A1", "pass", "A2"])
+    }
+
+    // The start of the same game, then "X" resigns
+    pub(crate) fn resigned() -> Game {
+        let mut game = play(&["C4", "D4", "D3", "B4"]);
+        game.play(Move::Resign, true, Duration::from_secs(0));
+        game
+    }
+
+    // Moves played in turn, a quarter second longer each time
+    fn play(moves: &[&str]) -> Game {
+        let mut game = Game::new(4, ["human".to_string(), "computer".to_string()]);
+        for (i, mv) in moves.iter().enumerate() {
+            game.play(parse_move(mv).unwrap(), i % 2 == 0, Duration::from_millis(250 * (i as u64 + 1)));
+        }
+        game
+    }
+
+    #[test]
+    fn transcripts_round_trip() {
+        let mut game = sample();
+        game.info.names[0] = Some("Marc Tastet".to_string());
+        game.info.result = Some("+2".to_string());
+        game.moves[0].eval = Some(-1.5);
+        for game in [game, resigned(), Game::new(6, ["human".to_string(), "human".to_string()])] {
+            assert_eq!(Game::from_text(&game.to_text()), Ok(game));
+        }
+    }
+
+    #[test]
+    fn transcripts_keep_the_starting_position() {
+        let (board, is_player_one) = sample().positions().unwrap()[4].clone();
+        let mut game = Game::from_position(board, is_player_one, ["human".to_string(), "human".to_string()]);
+        game.play(parse_move("A3").unwrap(), is_player_one, Duration::from_secs(1));
+        let text = game.to_text();
+        assert!(text.contains("\nstart "));
+        assert_eq!(Game::from_text(&text), Ok(game));
+    }
+
+    #[test]
+    fn bad_transcripts_are_refused() {
+        let text = sample().to_text();
+        let bad = [text.replace("move X C4", "move O C4"), text.replace("move X C4", "move X A1"),
+                   text.replace("0.250", "1e300"), text.replace("size 4", "size 5"),
+                   text.replace("version 3", "version 4"), text.replace("version 3\n", "")];
+        for text in bad {
+            assert!(Game::from_text(&text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn the_size_must_come_before_moves_and_clocks() {
+        for text in ["version 3\nmove X D3 0.0\n", "version 3\nclock X 1.0\nsize 4\n"] {
+            assert_eq!(Game::from_text(text),
+                       Err("Line 2 of the transcript comes too early: the size must come first".to_string()));
+        }
+    }
+
+    #[test]
+    fn commands_that_are_not_moves_are_written_as_typed() {
+        assert_eq!(move_text(&Move::Save("lunch.txt".to_string())), "save lunch.txt");
+        assert_eq!(move_text(&Move::Load("lunch.txt".to_string())), "load lunch.txt");
+        assert_eq!(move_text(&Move::Diagram("lunch.svg".to_string())), "diagram lunch.svg");
+        assert_eq!(move_text(&Move::Chat("chat hello".to_string())), "chat hello");
+        assert_eq!(move_text(&Move::Resign), "resign");
+    }
+}
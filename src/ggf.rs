@@ -0,0 +1,409 @@
+/***********************************************************
+ * GGF GAME RECORDS
+ ***********************************************************
+ * GGF (Generic Game Format) is the record format of the
+ * Othello servers and of many Othello programs. A game is
+ * written as "(;" followed by properties and ";)":
+ *     (;GM[Othello]PC[GGS/os]DT[2003.12.15]PB[Alice]PW[Bob]
+ *       RB[2197.72]RW[2199.94]TI[05:00//02:00]TY[8]RE[+12.000]
+ *       BO[8 -------- -------- -------- ---O*--- ---*O---
+ *          -------- -------- -------- *]
+ *       B[f5//1.20]W[d6/-1.50/2.04]B[pa];)
+ * The properties read and written here are:
+ *     PC, DT              Place and date.
+ *     PB, PW, RB, RW      Players' names and ratings.
+ *     TI                  Time control.
+ *     TY                  Board type; its number is the size.
+ *     RE                  Result, black's discs minus white's,
+ *                         ending in ":r" after a resignation.
+ *     BO                  Starting board: the size, one
+ *                         character per location ("*" black,
+ *                         "O" white, "-" empty) and "*" or
+ *                         "O" for the player to move.
+ *     B, W                Moves: the square (column letter
+ *                         and row number, "pa" for a pass),
+ *                         then "/evaluation/time", both
+ *                         optional. Times are seconds or
+ *                         minutes:seconds.
+ * Other properties are skipped. Moves are replayed through
+ * the rules as they are read.
+***********************************************************/
+
+use std::time::Duration;
+use crate::game::{Game, Turn};
+use crate::player::Move;
+use crate::{Board, NumLet, _X, _O, count_pieces, legal_moves, place, valid_size};
+
+/***********************************************************
+ * PARSE
+ ***********************************************************
+ * Read every game in a GGF file. Returns a message saying
+ * which game is wrong if one can't be read.
+***********************************************************/
+pub fn parse(text: &str) -> Result<Vec<Game>, String> {
+    let mut games = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("(;") {
+        let end = match rest[start..].find(";)") {
+            Some(end) => start + end + 2,
+            None => return Err(format!("Game {} of the GGF file doesn't end", games.len() + 1)),
+        };
+        let game = parse_game(&rest[start..end])
+            .map_err(|e| format!("Game {} of the GGF file: {}", games.len() + 1, e))?;
+        games.push(game);
+        rest = &rest[end..];
+    }
+    if games.is_empty() {
+        return Err("There are no GGF games in the file".to_string());
+    }
+    Ok(games)
+}
+
+/***********************************************************
+ * PARSE_GAME
+ ***********************************************************
+ * Read one GGF game into a Game record. A pass that the
+ * record leaves out (a move by the player who isn't to
+ * move, when the player to move can't place) is put back,
+ * and so is a resignation, which GGF only shows in the
+ * result ("...:r"), when the loser is the player to move.
+***********************************************************/
+pub fn parse_game(record: &str) -> Result<Game, String> {
+    let mut game = Game::new(0, ["human".to_string(), "human".to_string()]);
+    let mut board_type = None;                      // The size TY[] gives
+    let mut position: Option<(Board, bool)> = None; // The board and player to move as moves are read
+    for (key, value) in properties(record) {
+        let value = value.trim().to_string();
+        match key.as_str() {
+            "PC" => game.info.place = Some(value),
+            "DT" => game.info.date = Some(value),
+            "PB" => game.info.names[0] = Some(value),
+            "PW" => game.info.names[1] = Some(value),
+            "RB" => game.info.ratings[0] = value.parse().ok(),
+            "RW" => game.info.ratings[1] = value.parse().ok(),
+            "TI" => game.info.time_control = Some(value),
+            "RE" => game.info.result = Some(value),
+            "TY" => board_type = value.chars().filter(|c| c.is_ascii_digit())
+                                      .collect::<String>().parse::<usize>().ok(),
+            "BO" => {
+                let (board, is_player_one) = parse_board(&value)?;
+                game.size = board.size();
+                let mut usual = Board::new(board.size());
+                crate::create_board(&mut usual);
+                if board != usual || !is_player_one {
+                    game.start = Some((board.clone(), is_player_one));
+                }
+                position = Some((board, is_player_one));
+            }
+            "B" | "W" => {
+                let (board, is_player_one) = match position.as_mut() {
+                    Some(position) => (&mut position.0, &mut position.1),
+                    None => return Err("A move comes before the starting board".to_string()),
+                };
+                let mut parts = value.split('/');
+                let square = parts.next().unwrap_or("").trim();
+                let eval = parts.next().and_then(|e| e.trim().parse::<f64>().ok());
+                let time = parts.next().and_then(parse_time).unwrap_or_default();
+                let legal = legal_moves(board, *is_player_one);
+                if (key == "B") != *is_player_one && legal.is_empty() {
+                    game.moves.push(Turn { mv: Move::Pass, time: Duration::from_secs(0), eval: None });
+                    *is_player_one = !*is_player_one;   // A pass the record left out
+                }
+                if (key == "B") != *is_player_one {
+                    return Err(format!("{}[{}] is played out of turn", key, value));
+                }
+                let mv = if square.eq_ignore_ascii_case("pa") || square.eq_ignore_ascii_case("pass") {
+                    if !legal_moves(board, *is_player_one).is_empty() {
+                        return Err(format!("{}[{}] passes with a move to play", key, value));
+                    }
+                    Move::Pass
+                }
+                else {
+                    let nl = parse_square(square, board.size())
+                        .ok_or_else(|| format!("{}[{}] is not a square", key, value))?;
+                    if !place(board, nl, *is_player_one) {
+                        return Err(format!("{}[{}] is not a legal move", key, value));
+                    }
+                    Move::Place(nl)
+                };
+                let clock = &mut game.clock[if *is_player_one { 0 } else { 1 }];
+                *clock = clock.checked_add(time)
+                    .ok_or_else(|| format!("{}[{}] runs the clock past what can be held", key, value))?;
+                game.moves.push(Turn { mv, time, eval });
+                *is_player_one = !*is_player_one;
+            }
+            _ => {}
+        }
+    }
+    if position.is_none() {
+        return Err("The game has no starting board".to_string());
+    }
+    if board_type.is_some_and(|size| size != game.size) {
+        return Err("The board type doesn't match the starting board".to_string());
+    }
+    let to_move = position.map(|(_, is_player_one)| is_player_one);
+    if let (Some(result), Some(is_player_one)) = (&game.info.result, to_move) {
+        let x_resigned = result.starts_with('-');           // The loser resigned
+        if result.ends_with(":r") && x_resigned == is_player_one {
+            game.moves.push(Turn { mv: Move::Resign, time: Duration::from_secs(0), eval: None });
+        }
+    }
+    Ok(game)
+}
+
+/***********************************************************
+ * TO_GGF
+ ***********************************************************
+ * Write a game as a GGF record. The players' names are
+ * used where known and otherwise the kind of player. A
+ * result is worked out for games without one: the disc
+ * difference once the game is over, "?" before then. A
+ * resignation is written as the result, ending in ":r".
+***********************************************************/
+pub fn to_ggf(game: &Game) -> String {
+    let mut text = "(;GM[Othello]".to_string();
+    if let Some(place) = &game.info.place {
+        text += &format!("PC[{}]", escape(place));
+    }
+    if let Some(date) = &game.info.date {
+        text += &format!("DT[{}]", escape(date));
+    }
+    for (i, key) in ["B", "W"].iter().enumerate() {
+        let name = game.info.names[i].as_ref().unwrap_or(&game.players[i]);
+        text += &format!("P{}[{}]", key, escape(name));
+    }
+    for (i, key) in ["B", "W"].iter().enumerate() {
+        if let Some(rating) = game.info.ratings[i] {
+            text += &format!("R{}[{}]", key, rating);
+        }
+    }
+    if let Some(time_control) = &game.info.time_control {
+        text += &format!("TI[{}]", escape(time_control));
+    }
+    text += &format!("TY[{}]", game.size);
+    let resigned = game.moves.last().map(|t| &t.mv) == Some(&Move::Resign);
+    let result = match &game.info.result {
+        Some(result) if !resigned || result.ends_with(":r") => result.clone(),
+        _ => result(game),
+    };
+    text += &format!("RE[{}]", escape(&result));
+
+    let (start, first_player_one) = game.start_position();
+    let rows: Vec<String> = start.iter()
+        .map(|row| row.iter().map(|p| if *p == _X { '*' } else if *p == _O { 'O' } else { '-' }).collect())
+        .collect();
+    text += &format!("BO[{} {} {}]", game.size, rows.join(" "), if first_player_one { '*' } else { 'O' });
+
+    let mut is_player_one = first_player_one;
+    for turn in game.moves.iter() {
+        let square = match &turn.mv {
+            Move::Place(nl) => square_name(*nl).to_lowercase(),
+            Move::Pass => "pa".to_string(),
+            _ => break,                     // A resignation only shows in the result
+        };
+        let eval = turn.eval.map(|e| format!("{:.2}", e)).unwrap_or_default();
+        text += &format!("{}[{}/{}/{:.2}]", if is_player_one { "B" } else { "W" },
+                         square, eval, turn.time.as_secs_f64());
+        is_player_one = !is_player_one;
+    }
+    text + ";)"
+}
+
+/***********************************************************
+ * RESULT
+ ***********************************************************
+ * Work out a GGF result for a game that doesn't have one.
+***********************************************************/
+fn result(game: &Game) -> String {
+    let (board, is_player_one) = match game.position() {
+        Ok(position) => position,
+        Err(_) => return "?".to_string(),
+    };
+    if game.moves.last().map(|t| &t.mv) == Some(&Move::Resign) {
+        // The player before the one to move resigned, losing
+        // by every location on the board
+        let margin = (game.size * game.size) as i32;
+        return format!("{:+}:r", if is_player_one { margin } else { -margin });
+    }
+    if !legal_moves(&board, true).is_empty() || !legal_moves(&board, false).is_empty() {
+        return "?".to_string();             // The game isn't over yet
+    }
+    let (x_count, o_count) = count_pieces(&board);
+    format!("{:+}", x_count as i32 - o_count as i32)
+}
+
+/***********************************************************
+ * PARSE_BOARD
+ ***********************************************************
+ * Read a GGF starting board: the size, a character for
+ * each location ("*" black, "O" white, "-" empty) and then
+ * "*" or "O" for the player to move. Whitespace between
+ * the locations is allowed.
+***********************************************************/
+pub fn parse_board(value: &str) -> Result<(Board, bool), String> {
+    let mut parts = value.trim().splitn(2, char::is_whitespace);
+    let size = match parts.next().and_then(|s| s.parse::<usize>().ok()) {
+        Some(size) if valid_size(size) => size,
+        _ => return Err(format!("Unsupported board \"{}\"", value)),
+    };
+    let chars: Vec<char> = parts.next().unwrap_or("").chars().filter(|c| !c.is_whitespace()).collect();
+    if chars.len() != size * size + 1 {
+        return Err(format!("Bad board \"{}\"", value));
+    }
+    let mut board = Board::new(size);
+    for (i, c) in chars[..size * size].iter().enumerate() {
+        board[i / size][i % size] = match c {
+            '*' | 'X' | 'x' => _X,
+            'O' | 'o' => _O,
+            '-' | '.' => " ",
+            _ => return Err(format!("Bad board \"{}\"", value)),
+        };
+    }
+    Ok((board, chars[size * size] != 'O' && chars[size * size] != 'o'))
+}
+
+/***********************************************************
+ * PROPERTIES
+ ***********************************************************
+ * Split a GGF record such as "(;GM[Othello]B[F5];)" into
+ * its keys and values, in order. A "\" in a value keeps
+ * the character after it (so "\]" doesn't end the value).
+***********************************************************/
+pub fn properties(ggf: &str) -> Vec<(String, String)> {
+    let mut v = Vec::new();
+    let mut key = String::new();
+    let mut chars = ggf.chars();
+    while let Some(c) = chars.next() {
+        if c == '[' {
+            let mut value = String::new();
+            while let Some(c) = chars.next() {
+                match c {
+                    ']' => break,
+                    '\\' => value.extend(chars.next()),
+                    _ => value.push(c),
+                }
+            }
+            v.push((key.clone(), value));
+            key.clear();
+        }
+        else if c.is_ascii_uppercase() {
+            key.push(c);
+        }
+        else {
+            key.clear();            // "(", ";" and ")" separate properties
+        }
+    }
+    v
+}
+
+/***********************************************************
+ * ESCAPE
+ ***********************************************************
+ * Protect the characters that would end a GGF value early.
+***********************************************************/
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace(']', "\\]")
+}
+
+/***********************************************************
+ * PARSE_TIME
+ ***********************************************************
+ * Read a GGF time: seconds ("12.5"), or minutes and
+ * seconds ("1:02.5"), or hours too ("1:00:00").
+***********************************************************/
+fn parse_time(text: &str) -> Option<Duration> {
+    let mut secs = 0.0;
+    for part in text.trim().split(':') {
+        secs = secs * 60.0 + part.parse::<f64>().ok()?;
+    }
+    Duration::try_from_secs_f64(secs).ok()     // Not negative or too long to hold
+}
+
+/***********************************************************
+ * PARSE_SQUARE
+ ***********************************************************
+ * Read a square such as "F5" (column letter, row number).
+***********************************************************/
+pub fn parse_square(square: &str, size: usize) -> Option<NumLet> {
+    let mut chars = square.chars();
+    let column = chars.next()?.to_ascii_uppercase();
+    let row: usize = chars.as_str().parse().ok()?;
+    if !column.is_ascii_uppercase() || row == 0 {
+        return None;
+    }
+    let nl = NumLet { n: column as usize - 'A' as usize, l: row - 1 };
+    if nl.n < size && nl.l < size { Some(nl) } else { None }
+}
+
+/***********************************************************
+ * SQUARE_NAME
+ ***********************************************************
+ * The name of a square, for example "F5".
+***********************************************************/
+pub fn square_name(nl: NumLet) -> String {
+    format!("{}{}", (nl.n as u8 + b'A') as char, nl.l + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::tests::{resigned, sample};
+
+    #[test]
+    fn games_round_trip() {
+        let mut game = sample();
+        game.info.names[0] = Some("Tastet [FRA] \\ Paris".to_string());
+        let text = to_ggf(&game);
+        assert!(text.contains("B[pa//2.75]"));
+        let read = parse_game(&text).unwrap();
+        assert_eq!(read.moves, game.moves);
+        assert_eq!(read.clock, game.clock);
+        assert_eq!(read.info.names, [game.info.names[0].clone(), Some("computer".to_string())]);
+        assert!(read.info.result.is_some_and(|result| result != "?"));
+    }
+
+    #[test]
+    fn resignations_round_trip() {
+        let game = resigned();
+        let text = to_ggf(&game);
+        assert!(text.contains("RE[-16:r]"));
+        assert_eq!(parse_game(&text).unwrap().moves, game.moves);
+    }
+
+    #[test]
+    fn left_out_passes_are_put_back() {
+        let game = sample();
+        let text = to_ggf(&game).replace("B[pa//2.75]", "").replace("B[pa//3.25]", "");
+        let moves: Vec<Move> = parse_game(&text).unwrap().moves.into_iter().map(|turn| turn.mv).collect();
+        assert_eq!(moves, game.moves.into_iter().map(|turn| turn.mv).collect::<Vec<Move>>());
+    }
+
+    #[test]
+    fn bad_games_are_refused() {
+        let text = to_ggf(&sample());
+        for bad in [text.replace("TY[4]", "TY[8]"), text.replace("B[d3/", "W[d3/"),
+                    text.replace("B[d3/", "B[a1/"), text.replace("BO[", "XX[")] {
+            assert!(parse_game(&bad).is_err(), "{}", bad);
+        }
+        assert!(parse("no games here").is_err());
+    }
+
+    #[test]
+    fn clocks_too_long_are_refused() {
+        let text = to_ggf(&sample());
+        assert!(text.contains("B[d3//0.25]") && text.contains("B[c4//0.75]"), "{}", text);
+        let text = text.replace("B[d3//0.25]", "B[d3//1e19]").replace("B[c4//0.75]", "B[c4//1e19]");
+        assert_eq!(parse_game(&text).map(|_| ()),
+                   Err("B[c4//1e19] runs the clock past what can be held".to_string()));
+    }
+
+    #[test]
+    fn times_are_read() {
+        assert_eq!(parse_time("12.5"), Some(Duration::from_millis(12_500)));
+        assert_eq!(parse_time("1:02.5"), Some(Duration::from_millis(62_500)));
+        assert_eq!(parse_time("1:00:00"), Some(Duration::from_secs(3600)));
+        assert_eq!(parse_time("1e20"), None);
+        assert_eq!(parse_time("-1"), None);
+        assert_eq!(parse_time("soon"), None);
+    }
+}
@@ -0,0 +1,364 @@
+/***********************************************************
+ * TEXT PROTOCOL
+ ***********************************************************
+ * With "--gtp" the program reads commands from stdin, one
+ * per line, in the spirit of the Go Text Protocol (GTP).
+ * Every command gets exactly one response:
+ *     = <result>          The command succeeded.
+ *     ? <message>         The command failed; nothing changed.
+ * followed by an empty line. A command may start with a
+ * number, which is repeated after the "=" or "?" (a number
+ * with no command fails), and anything after a "#" is a
+ * comment.
+ *
+ * Colors are "x", "black" or "b" for the first player and
+ * "o", "white" or "w" for the second. Locations are written
+ * like the game shows them, row letter and column number
+ * (for example "B3"), or "pass".
+ *
+ * Commands:
+ *     protocol_version    "2".
+ *     name / version      The program's name and version.
+ *     known_command <c>   "true" or "false".
+ *     list_commands       Every command, one per line.
+ *     boardsize <n>       Start over on an n*n board.
+ *     clear_board         Start over on the same board.
+ *     play <color> <loc>  Play a move (or pass) for a color.
+ *     genmove <color>     The computer player moves for the
+ *                         color; answers the location,
+ *                         "pass" or "resign".
+ *     legal_moves <color> Every legal location for a color.
+ *     showboard           The board as display() draws it.
+ *     undo                Take back the last move or pass.
+ *     final_score         "X+n", "O+n" or "0" for a tie.
+ *     set_position <cells> <color>
+ *                         Start over from a position string
+ *                         (see position.rs).
+ *     get_position        The position string for the board
+ *                         and the color to move.
+ *     quit                Stop reading commands.
+***********************************************************/
+
+use std::io::{self, BufRead, Write};
+use crate::player::{ComputerPlayer, Move, Player};
+use crate::position::{parse_position, position_string};
+use crate::{Board, NumLet, board_text, coordinates, count_pieces, create_board,
+            get_coordinates, legal_moves, place, valid_size};
+
+// Every command the protocol understands
+static COMMANDS: [&str; 16] = ["protocol_version", "name", "version", "known_command",
+                               "list_commands", "boardsize", "clear_board", "play",
+                               "genmove", "legal_moves", "showboard", "undo",
+                               "final_score", "set_position", "get_position", "quit"];
+
+/***********************************************************
+ * GTP_GAME
+ ***********************************************************
+ * The game being driven by the commands: the board, whose
+ * turn it is, and every earlier position so moves can be
+ * taken back.
+***********************************************************/
+struct GtpGame {
+    board: Board,                   // The current board
+    is_player_one: bool,            // True if "X" is to move
+    history: Vec<(Board, bool)>     // The board and player to move before each move
+}
+
+impl GtpGame {
+    // A new game on a size*size board
+    fn new(size: usize) -> GtpGame {
+        let mut board = Board::new(size);
+        create_board(&mut board);
+        GtpGame { board, is_player_one: true, history: Vec::new() }
+    }
+}
+
+/***********************************************************
+ * RUN
+ ***********************************************************
+ * Answer commands until "quit" or the end of stdin.
+***********************************************************/
+pub fn run(size: usize) {
+    let mut game = GtpGame::new(size);
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    for line in stdin.lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        if let Some((response, quit)) = respond(&mut game, &line) {
+            let _ = write!(out, "{}", response);
+            let _ = out.flush();
+            if quit {
+                break;
+            }
+        }
+    }
+}
+
+/***********************************************************
+ * RESPOND
+ ***********************************************************
+ * Answer one line: the whole response, with its number and
+ * the empty line after it, and true if the command was
+ * "quit". Returns None for lines with no command on them.
+***********************************************************/
+fn respond(game: &mut GtpGame, line: &str) -> Option<(String, bool)> {
+    let line = line.split('#').next().unwrap_or("").trim();    // Drop comments
+    if line.is_empty() {
+        return None;
+    }
+    let mut words: Vec<&str> = line.split_whitespace().collect();
+    let mut id = "";                        // The command's number, if it has one
+    if words[0].chars().all(|c| c.is_ascii_digit()) {
+        id = words.remove(0);
+    }
+    let name = words.first().copied().unwrap_or("");
+    let result = if name.is_empty() {
+        Err("missing command".to_string())  // Only a number
+    }
+    else {
+        command(game, name, &words[1..])
+    };
+    let (sign, text) = match result {
+        Ok(result) => ("=", result),
+        Err(message) => ("?", message),
+    };
+    Some((format!("{}{} {}\n\n", sign, id, text), name == "quit"))
+}
+
+/***********************************************************
+ * COMMAND
+ ***********************************************************
+ * Carry out one command. Returns the result on success, or
+ * the message explaining why the command failed.
+***********************************************************/
+fn command(game: &mut GtpGame, name: &str, args: &[&str]) -> Result<String, String> {
+    match name {
+        "protocol_version" => Ok("2".to_string()),
+        "name" => Ok("Othello".to_string()),
+        "version" => Ok(env!("CARGO_PKG_VERSION").to_string()),
+        "known_command" => Ok(COMMANDS.contains(&arg(args, 0)?).to_string()),
+        "list_commands" => Ok(COMMANDS.join("\n")),
+        "boardsize" => match arg(args, 0)?.parse::<usize>() {
+            Ok(size) if valid_size(size) => {
+                *game = GtpGame::new(size);
+                Ok(String::new())
+            }
+            _ => Err("unacceptable size".to_string()),
+        },
+        "clear_board" => {
+            *game = GtpGame::new(game.board.size());
+            Ok(String::new())
+        }
+        "play" => {
+            let is_player_one = color(arg(args, 0)?)?;
+            let location = arg(args, 1)?;
+            let legal = legal_moves(&game.board, is_player_one);
+            if location.eq_ignore_ascii_case("pass") {
+                if !legal.is_empty() {
+                    return Err("illegal move".to_string());     // Passing is only allowed without a move
+                }
+                game.history.push((game.board.clone(), game.is_player_one));
+                game.is_player_one = !is_player_one;
+                return Ok(String::new());
+            }
+            let mut num: usize = 0;             // The column number index
+            let mut letter: usize = 0;          // The row letter index
+            if !get_coordinates(&mut num, &mut letter, location.to_string())
+                || num >= game.board.size() || letter >= game.board.size() {
+                return Err("invalid coordinate".to_string());
+            }
+            let nl = NumLet { n: num, l: letter };
+            if !legal.contains(&nl) {
+                return Err("illegal move".to_string());
+            }
+            play(game, nl, is_player_one);
+            Ok(String::new())
+        }
+        "genmove" => {
+            let is_player_one = color(arg(args, 0)?)?;
+            let legal = legal_moves(&game.board, is_player_one);
+            if legal.is_empty() {
+                game.history.push((game.board.clone(), game.is_player_one));
+                game.is_player_one = !is_player_one;
+                return Ok("pass".to_string());
+            }
+            match ComputerPlayer.choose_move(&game.board, is_player_one, &legal) {
+                Move::Place(nl) => {
+                    play(game, nl, is_player_one);
+                    Ok(coordinates(nl))
+                }
+                _ => Ok("resign".to_string()),
+            }
+        }
+        "legal_moves" => {
+            let is_player_one = color(arg(args, 0)?)?;
+            let legal: Vec<String> = legal_moves(&game.board, is_player_one)
+                .into_iter()
+                .map(coordinates)
+                .collect();
+            Ok(legal.join(" "))
+        }
+        "showboard" => Ok(format!("\n{}", board_text(&game.board).trim_end())),
+        "undo" => match game.history.pop() {
+            Some((board, is_player_one)) => {
+                game.board = board;
+                game.is_player_one = is_player_one;
+                Ok(String::new())
+            }
+            None => Err("cannot undo".to_string()),
+        },
+        "final_score" => {
+            let (x_count, o_count) = count_pieces(&game.board);
+            if x_count > o_count {
+                Ok(format!("X+{}", x_count - o_count))
+            }
+            else if o_count > x_count {
+                Ok(format!("O+{}", o_count - x_count))
+            }
+            else {
+                Ok("0".to_string())
+            }
+        }
+        "set_position" => match parse_position(&args.join(" ")) {
+            Ok((board, is_player_one)) => {
+                *game = GtpGame { board, is_player_one, history: Vec::new() };
+                Ok(String::new())
+            }
+            Err(message) => Err(message),
+        },
+        "get_position" => Ok(position_string(&game.board, game.is_player_one)),
+        "quit" => Ok(String::new()),
+        _ => Err("unknown command".to_string()),
+    }
+}
+
+/***********************************************************
+ * PLAY
+ ***********************************************************
+ * Place a piece for a color, remembering the position
+ * before it for undo. The location must be legal.
+***********************************************************/
+fn play(game: &mut GtpGame, nl: NumLet, is_player_one: bool) {
+    game.history.push((game.board.clone(), game.is_player_one));
+    place(&mut game.board, nl, is_player_one);
+    game.is_player_one = !is_player_one;
+}
+
+/***********************************************************
+ * ARG
+ ***********************************************************
+ * The command argument at an index, or a failure if the
+ * command was given too few arguments.
+***********************************************************/
+fn arg<'a>(args: &[&'a str], index: usize) -> Result<&'a str, String> {
+    args.get(index).copied().ok_or_else(|| "missing argument".to_string())
+}
+
+/***********************************************************
+ * COLOR
+ ***********************************************************
+ * Read a color, returning true for the first player ("X").
+***********************************************************/
+fn color(text: &str) -> Result<bool, String> {
+    match text.to_lowercase().as_str() {
+        "x" | "b" | "black" => Ok(true),
+        "o" | "w" | "white" => Ok(false),
+        _ => Err("invalid color".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The response to each line, in order
+    fn session(lines: &[&str]) -> Vec<String> {
+        let mut game = GtpGame::new(4);
+        lines.iter().filter_map(|line| respond(&mut game, line)).map(|(response, _)| response).collect()
+    }
+
+    #[test]
+    fn responses_repeat_the_command_number() {
+        assert_eq!(session(&["protocol_version", "7 name", "12 version", "# a comment", "",
+                             "3 known_command genmove", "known_command fly", "99 fly"]),
+                   ["= 2\n\n", "=7 Othello\n\n", format!("=12 {}\n\n", env!("CARGO_PKG_VERSION")).as_str(),
+                    "=3 true\n\n", "= false\n\n", "?99 unknown command\n\n"]);
+    }
+
+    #[test]
+    fn lines_with_only_a_number_are_errors() {
+        assert_eq!(session(&["12", "  5  # comment"]), ["?12 missing command\n\n", "?5 missing command\n\n"]);
+    }
+
+    #[test]
+    fn quit_is_answered_and_stops() {
+        let mut game = GtpGame::new(4);
+        assert_eq!(respond(&mut game, "4 quit"), Some(("=4 \n\n".to_string(), true)));
+        assert_eq!(respond(&mut game, "name"), Some(("= Othello\n\n".to_string(), false)));
+    }
+
+    #[test]
+    fn commands_are_listed() {
+        let list = session(&["list_commands"]).remove(0);
+        assert_eq!(list, format!("= {}\n\n", COMMANDS.join("\n")));
+        assert!(session(&["known_command"])[0].starts_with("? missing argument"));
+    }
+
+    #[test]
+    fn moves_are_played_and_checked() {
+        assert_eq!(session(&["1 play x C4", "2 play o C4", "3 play o E1", "4 play o A1", "5 play green D4",
+                             "6 play o", "7 get_position"]),
+                   ["=1 \n\n", "?2 illegal move\n\n", "?3 invalid coordinate\n\n", "?4 illegal move\n\n",
+                    "?5 invalid color\n\n", "?6 missing argument\n\n", "=7 -----OX--XXX---- O\n\n"]);
+    }
+
+    #[test]
+    fn passes_are_refused_while_there_is_a_move() {
+        assert_eq!(session(&["play b pass"]), ["? illegal move\n\n"]);
+        assert_eq!(session(&["set_position XXXXXXXXXXXXXXX- O", "play w pass", "get_position"]),
+                   ["= \n\n", "= \n\n", "= XXXXXXXXXXXXXXX- X\n\n"]);
+    }
+
+    #[test]
+    fn genmove_plays_for_the_computer() {
+        let responses = session(&["genmove x", "legal_moves x", "genmove o"]);
+        assert!(["= B1\n\n", "= A2\n\n", "= D3\n\n", "= C4\n\n"].contains(&responses[0].as_str()),
+                "{}", responses[0]);
+        assert_eq!(session(&["set_position XXXXXXXXXXXXXXX- O", "5 genmove o"])[1], "=5 pass\n\n");
+    }
+
+    #[test]
+    fn legal_moves_are_listed() {
+        assert_eq!(session(&["legal_moves x", "legal_moves o"]),
+                   ["= A2 B1 C4 D3\n\n", "= A3 B4 C1 D2\n\n"]);
+    }
+
+    #[test]
+    fn undo_takes_back_moves_until_there_are_none() {
+        assert_eq!(session(&["undo", "play x C4", "undo", "get_position", "undo"]),
+                   ["? cannot undo\n\n", "= \n\n", "= \n\n", "= -----OX--XO----- X\n\n",
+                    "? cannot undo\n\n"]);
+    }
+
+    #[test]
+    fn final_score_counts_the_pieces() {
+        assert_eq!(session(&["final_score", "play x C4", "final_score", "set_position XXXXXXXXOOO----- X",
+                             "final_score"]),
+                   ["= 0\n\n", "= \n\n", "= X+3\n\n", "= \n\n", "= X+5\n\n"]);
+        assert_eq!(session(&["set_position OOOOOOOOOOXXXXX- X", "3 final_score"])[1], "=3 O+5\n\n");
+    }
+
+    #[test]
+    fn the_board_can_be_resized_cleared_and_shown() {
+        let start = format!("= {}OX----XO{} X\n\n", "-".repeat(14), "-".repeat(14));
+        assert_eq!(session(&["boardsize 6", "get_position", "boardsize 5", "play x B3", "clear_board",
+                             "get_position"]),
+                   ["= \n\n", start.as_str(), "? unacceptable size\n\n", "= \n\n", "= \n\n", start.as_str()]);
+        let board = session(&["showboard"]).remove(0);
+        assert_eq!(board, format!("= \n{}\n\n", board_text(&GtpGame::new(4).board).trim_end()));
+    }
+}
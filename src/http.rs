@@ -0,0 +1,680 @@
+/***********************************************************
+ * HTTP API
+ ***********************************************************
+ * With "--http PORT" the program serves games over HTTP
+ * with JSON bodies, for dashboards and bots, when built
+ * with the "json" feature. It only listens on this
+ * machine (127.0.0.1) unless an address is given. The
+ * endpoints are:
+ *     GET  /games                 Every game's state.
+ *     POST /games                 Create a game. The body
+ *                                 is optional:
+ *                                 {"size": 8} or
+ *                                 {"position": "<position>"}
+ *                                 (see position.rs).
+ *     GET  /games/<id>            A game's state.
+ *     POST /games/<id>/moves      Play a move for the side
+ *                                 to move: {"move": "D3"} or
+ *                                 {"move": "resign"}.
+ *     POST /games/<id>/computer   Have the computer player
+ *                                 choose and play the move.
+ *     GET  /games/<id>/record     The game's JSON record
+ *                                 (see json.rs).
+ *     GET  /games/<id>/socket     A WebSocket (see below).
+ * A game's state looks like this:
+ *     {
+ *       "id": 1, "size": 4,
+ *       "board": [" X  ", " XX ", " XO ", "    "],
+ *       "position": "-X---XX--XO----- O",
+ *       "to_move": "O", "legal_moves": ["A1", "A3", "C1"],
+ *       "score": { "X": 4, "O": 1 },
+ *       "moves": ["A2"], "last_move": "A2",
+ *       "clock": { "X": 3.2, "O": 0.0 },
+ *       "over": false, "winner": null
+ *     }
+ * "board" has a string per row from A down, with "X",
+ * "O" or a space for each location. Passes are played
+ * for a side with no legal move, so "to_move" is always
+ * a side that can move, or null once the game is over;
+ * "winner" is then "X", "O" or "draw". Moves and created
+ * games are answered with the new state, and the computer
+ * also with the move it chose as "played". Anything wrong
+ * is answered with a 4xx status and {"error": "<message>"}.
+ * "clock" is the seconds each side has spent thinking,
+ * counted from the move before.
+ *
+ * A browser can follow a game on a WebSocket instead of
+ * asking for its state. Every socket on a game is sent
+ * these JSON messages, whoever moved (over HTTP or on a
+ * socket):
+ *     {"event": "state", <the state>}
+ *                         When the socket opens, and after
+ *                         everything that follows.
+ *     {"event": "move", "player": "X", "move": "D3",
+ *      "time": 3.2}       A move (or "resign") was played.
+ *     {"event": "pass", "player": "O"}
+ *                         A side couldn't move and passed.
+ *     {"event": "clock", "X": 3.2, "O": 0.0, "running": "O"}
+ *                         Both clocks after a move, and
+ *                         whose is running now.
+ *     {"event": "over", "winner": "X", "score": {...}}
+ *                         The game has ended.
+ * A socket opened with "?side=X" (or O) plays that side:
+ * it can send {"move": "D3"}, {"move": "resign"} or
+ * {"computer": true} on that side's turn. Moves it can't
+ * make are answered on that socket only with
+ * {"event": "error", "error": "<message>"}.
+***********************************************************/
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use serde_json::{json, Value};
+use crate::game::{Game, move_text, parse_move};
+use crate::player::{ComputerPlayer, Move, Player};
+use crate::position::{parse_position, position_string};
+use crate::websocket::{self, Message};
+use crate::{Board, MAX_SIZE, _X, _O, count_pieces, legal_moves, valid_size};
+
+// Requests bigger than this aren't read
+const MAX_BODY: usize = 64 * 1024;
+
+/***********************************************************
+ * RESPONSE
+ ***********************************************************
+ * An HTTP status and the JSON to answer with.
+***********************************************************/
+type Response = (u16, Value);
+
+/***********************************************************
+ * ROOM
+ ***********************************************************
+ * A game being served and the sockets following it.
+***********************************************************/
+struct Room {
+    game: Game,
+    since: Instant,                     // When the side to move started thinking
+    sockets: Vec<(usize, TcpStream)>,   // Each socket's number and where to write to it
+    next_socket: usize                  // The number of the next socket opened
+}
+
+impl Room {
+    // Write messages to every socket, forgetting the
+    // sockets that have closed
+    fn broadcast(&mut self, messages: &[Value]) {
+        self.sockets.retain_mut(|(_, stream)| {
+            messages.iter().all(|message| websocket::send_text(stream, &message.to_string()).is_ok())
+        });
+    }
+
+    // Write a message to one socket
+    fn tell(&mut self, socket: usize, message: &Value) {
+        if let Some((_, stream)) = self.sockets.iter_mut().find(|(n, _)| *n == socket) {
+            let _ = websocket::send_text(stream, &message.to_string());
+        }
+    }
+}
+
+// Every game served: game <id> is games[id - 1]
+type Games = Arc<Mutex<Vec<Room>>>;
+
+/***********************************************************
+ * ADDRESS
+ ***********************************************************
+ * The address to listen on for "--http": a port number on
+ * its own means this machine only.
+***********************************************************/
+pub fn address(text: &str) -> String {
+    if text.chars().all(|c| c.is_ascii_digit()) {
+        format!("127.0.0.1:{}", text)
+    }
+    else {
+        text.to_string()
+    }
+}
+
+/***********************************************************
+ * RUN
+ ***********************************************************
+ * Listen on the address and answer each connection on its
+ * own thread, keeping every game created until the program
+ * stops.
+***********************************************************/
+pub fn run(address: &str) -> Result<(), String> {
+    let listener = TcpListener::bind(address)
+        .map_err(|e| format!("Couldn't listen on {}: {}", address, e))?;
+    println!("Serving games on http://{}/games", address);
+    let games = Games::default();
+    for stream in listener.incoming().flatten() {
+        let games = Arc::clone(&games);
+        thread::spawn(move || {
+            if let Err(message) = serve(stream, &games) {
+                println!("A request failed: {}", message);
+            }
+        });
+    }
+    Ok(())
+}
+
+/***********************************************************
+ * SERVE
+ ***********************************************************
+ * Read one request from the connection and answer it, or
+ * open a WebSocket if it asks for one.
+***********************************************************/
+fn serve(stream: TcpStream, games: &Games) -> Result<(), String> {
+    stream.set_read_timeout(Some(Duration::from_secs(5))).map_err(|e| e.to_string())?;
+    let mut reader = BufReader::new(stream.try_clone().map_err(|e| e.to_string())?);
+
+    // The request line, then headers up to an empty line
+    let mut line = String::new();
+    reader.read_line(&mut line).map_err(|e| e.to_string())?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or("").to_string();
+    let path = parts.next().unwrap_or("").to_string();
+    let mut length = 0;                         // Content-Length
+    let mut key = None;                         // Sec-WebSocket-Key, for a WebSocket
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).map_err(|e| e.to_string())? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            match name.trim().to_lowercase().as_str() {
+                "content-length" => length = value.trim().parse().unwrap_or(0),
+                "sec-websocket-key" => key = Some(value.trim().to_string()),
+                _ => {}
+            }
+        }
+    }
+    if let (Some(key), true) = (key, method == "GET") {
+        return socket(stream, reader, &path, &key, games);
+    }
+
+    let response = if length > MAX_BODY {
+        error(413, "The request is too big")
+    }
+    else {
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).map_err(|e| e.to_string())?;
+        route(&method, &path, &String::from_utf8_lossy(&body), &mut games.lock().unwrap())
+    };
+    respond(stream, response)
+}
+
+/***********************************************************
+ * RESPOND
+ ***********************************************************
+ * Write the response and close the connection.
+***********************************************************/
+fn respond(mut stream: TcpStream, (status, body): Response) -> Result<(), String> {
+    let reason = match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        413 => "Payload Too Large",
+        _ => "Error",
+    };
+    let body = body.to_string();
+    write!(stream, "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+                    Access-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n{}",
+           status, reason, body.len(), body).map_err(|e| e.to_string())?;
+    stream.flush().map_err(|e| e.to_string())
+}
+
+/***********************************************************
+ * ROUTE
+ ***********************************************************
+ * Answer a request by its method and path.
+***********************************************************/
+fn route(method: &str, path: &str, body: &str, games: &mut Vec<Room>) -> Response {
+    let path = path.split('?').next().unwrap_or("");   // Queries aren't used
+    let parts: Vec<&str> = path.trim_matches('/').split('/').collect();
+    if parts[0] != "games" {
+        return error(404, "Games are at /games");
+    }
+    if parts.len() == 1 {
+        return match method {
+            "GET" => (200, Value::Array(games.iter().enumerate().map(|(i, r)| state(i + 1, &r.game)).collect())),
+            "POST" => create(body, games),
+            _ => error(405, "Use GET or POST"),
+        };
+    }
+    let id = match parts[1].parse::<usize>() {
+        Ok(id) if (1..=games.len()).contains(&id) => id,
+        _ => return error(404, &format!("There is no game {}", parts[1])),
+    };
+    let room = &mut games[id - 1];
+    match (method, &parts[2..]) {
+        ("GET", []) => (200, state(id, &room.game)),
+        ("GET", ["record"]) => match serde_json::from_str(&crate::json::to_json(&room.game)) {
+            Ok(record) => (200, record),
+            Err(e) => error(400, &e.to_string()),
+        },
+        ("POST", ["moves"]) => match parse_body(body) {
+            Ok(request) => submit(id, room, &request),
+            Err(response) => response,
+        },
+        ("POST", ["computer"]) => computer(id, room),
+        (_, []) | (_, ["record"]) | (_, ["socket"]) => error(405, "Use GET"),
+        (_, ["moves"]) | (_, ["computer"]) => error(405, "Use POST"),
+        _ => error(404, "Nothing is at that path"),
+    }
+}
+
+/***********************************************************
+ * CREATE
+ ***********************************************************
+ * Start a game with the size or from the position the
+ * body asks for.
+***********************************************************/
+fn create(body: &str, games: &mut Vec<Room>) -> Response {
+    let request = match parse_body(body) {
+        Ok(request) => request,
+        Err(response) => return response,
+    };
+    let players = ["http".to_string(), "http".to_string()];
+    let mut game = match (request.get("position").and_then(Value::as_str), request.get("size")) {
+        (Some(text), _) => match parse_position(text) {
+            Ok((board, is_player_one)) => Game::from_position(board, is_player_one, players),
+            Err(message) => return error(400, &message),
+        },
+        (None, Some(size)) => match size.as_u64().map(|s| s as usize) {
+            Some(size) if valid_size(size) => Game::new(size, players),
+            _ => return error(400, "The size must be even and from 4 to 8"),
+        },
+        (None, None) => Game::new(MAX_SIZE, players),   // The usual 8x8 game
+    };
+    pass_if_stuck(&mut game);
+    games.push(Room { game, since: Instant::now(), sockets: Vec::new(), next_socket: 0 });
+    (201, state(games.len(), &games[games.len() - 1].game))
+}
+
+/***********************************************************
+ * SUBMIT
+ ***********************************************************
+ * Play the move asked for by the side to move.
+***********************************************************/
+fn submit(id: usize, room: &mut Room, request: &Value) -> Response {
+    let text = match request.get("move").and_then(Value::as_str) {
+        Some(text) => text,
+        None => return error(400, "Send {\"move\": \"D3\"} or {\"move\": \"resign\"}"),
+    };
+    match parse_move(text) {
+        Some(Move::Pass) => error(400, "Passes are played automatically"),
+        Some(mv) => play(id, room, mv).unwrap_or_else(|response| response),
+        None => error(400, &format!("\"{}\" isn't a move", text)),
+    }
+}
+
+/***********************************************************
+ * COMPUTER
+ ***********************************************************
+ * Have the computer player choose the move for the side
+ * to move, and play it.
+***********************************************************/
+fn computer(id: usize, room: &mut Room) -> Response {
+    let (board, is_player_one) = match to_move(&room.game) {
+        Some(position) => position,
+        None => return error(409, "The game is over"),
+    };
+    let legal = legal_moves(&board, is_player_one);
+    let mv = ComputerPlayer.choose_move(&board, is_player_one, &legal);
+    match play(id, room, mv.clone()) {
+        Ok((status, mut state)) => {
+            state["played"] = json!(move_text(&mv));
+            (status, state)
+        }
+        Err(response) => response,
+    }
+}
+
+/***********************************************************
+ * PLAY
+ ***********************************************************
+ * Play a move if it is legal, and any passes after it,
+ * telling every socket on the game what happened and
+ * returning the new state.
+***********************************************************/
+fn play(id: usize, room: &mut Room, mv: Move) -> Result<Response, Response> {
+    let (board, is_player_one) = to_move(&room.game).ok_or_else(|| error(409, "The game is over"))?;
+    if let Move::Place(nl) = mv {
+        if !legal_moves(&board, is_player_one).contains(&nl) {
+            return Err(error(400, &format!("{} is not a legal move", move_text(&mv))));
+        }
+    }
+    let time = room.since.elapsed();
+    room.game.play(mv.clone(), is_player_one, time);
+    room.since = Instant::now();
+    let mut events = vec![json!({ "event": "move", "player": piece(is_player_one),
+                                  "move": move_text(&mv), "time": time.as_secs_f64() })];
+    if pass_if_stuck(&mut room.game) {
+        events.push(json!({ "event": "pass", "player": piece(!is_player_one) }));
+    }
+
+    let state = state(id, &room.game);
+    events.push(json!({ "event": "clock", "X": state["clock"]["X"], "O": state["clock"]["O"],
+                        "running": state["to_move"] }));
+    if state["over"] == true {
+        events.push(json!({ "event": "over", "winner": state["winner"], "score": state["score"] }));
+    }
+    events.push(event("state", &state));
+    room.broadcast(&events);
+    Ok((200, state))
+}
+
+/***********************************************************
+ * TO_MOVE
+ ***********************************************************
+ * The board and the side to move, or None if the game is
+ * over.
+***********************************************************/
+fn to_move(game: &Game) -> Option<(Board, bool)> {
+    if game.moves.last().map(|t| &t.mv) == Some(&Move::Resign) {
+        return None;
+    }
+    let (board, is_player_one) = game.position().ok()?;
+    if legal_moves(&board, is_player_one).is_empty() {
+        return None;                            // Passes are already played, so neither can move
+    }
+    Some((board, is_player_one))
+}
+
+/***********************************************************
+ * PASS_IF_STUCK
+ ***********************************************************
+ * Play a pass for the side to move if it can't place but
+ * the other side can, returning true if it did.
+***********************************************************/
+fn pass_if_stuck(game: &mut Game) -> bool {
+    if game.moves.last().map(|t| &t.mv) == Some(&Move::Resign) {
+        return false;
+    }
+    if let Ok((board, is_player_one)) = game.position() {
+        if legal_moves(&board, is_player_one).is_empty() && !legal_moves(&board, !is_player_one).is_empty() {
+            game.play(Move::Pass, is_player_one, Duration::from_secs(0));
+            return true;
+        }
+    }
+    false
+}
+
+/***********************************************************
+ * STATE
+ ***********************************************************
+ * A game's state as JSON, as described at the top.
+***********************************************************/
+fn state(id: usize, game: &Game) -> Value {
+    let (board, is_player_one) = game.position().unwrap_or_else(|_| game.start_position());
+    let rows: Vec<String> = board.iter().map(|row| row.concat()).collect();
+    let (x_count, o_count) = count_pieces(&board);
+    let moves: Vec<String> = game.moves.iter().map(|t| move_text(&t.mv)).collect();
+    let over = to_move(game).is_none();
+    let winner = match game.moves.last().map(|t| &t.mv) {
+        _ if !over => None,
+        Some(Move::Resign) => Some(piece(is_player_one)),   // The side that didn't resign
+        _ if x_count > o_count => Some(_X),
+        _ if o_count > x_count => Some(_O),
+        _ => Some("draw"),
+    };
+    json!({
+        "id": id,
+        "size": game.size,
+        "board": rows,
+        "position": position_string(&board, is_player_one),
+        "to_move": if over { None } else { Some(piece(is_player_one)) },
+        "legal_moves": if over { Vec::new() } else {
+            legal_moves(&board, is_player_one).into_iter().map(crate::coordinates).collect()
+        },
+        "score": { "X": x_count, "O": o_count },
+        "moves": moves,
+        "last_move": moves.last(),
+        "clock": { "X": game.clock[0].as_secs_f64(), "O": game.clock[1].as_secs_f64() },
+        "over": over,
+        "winner": winner
+    })
+}
+
+/***********************************************************
+ * SOCKET
+ ***********************************************************
+ * Answer a WebSocket handshake for a game, send the game's
+ * state and then everything that happens to it, and play
+ * the moves sent for the socket's side, until the browser
+ * closes it.
+***********************************************************/
+fn socket(mut stream: TcpStream, mut reader: BufReader<TcpStream>, path: &str, key: &str,
+          games: &Games) -> Result<(), String> {
+    let (path, query) = path.split_once('?').unwrap_or((path, ""));
+    let parts: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let id = match (parts.as_slice(), games.lock().unwrap().len()) {
+        (["games", id, "socket"], count) => id.parse::<usize>().ok().filter(|id| (1..=count).contains(id)),
+        _ => None,
+    };
+    let side = query.split('&').find_map(|pair| match pair.split_once('=') {
+        Some(("side", "X")) | Some(("side", "x")) => Some(true),
+        Some(("side", "O")) | Some(("side", "o")) => Some(false),
+        _ => None,
+    });
+    let id = match id {
+        Some(id) => id,
+        None => return respond(stream, error(404, "Sockets are at /games/<id>/socket")),
+    };
+
+    write!(stream, "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+                    Sec-WebSocket-Accept: {}\r\n\r\n", websocket::accept_key(key))
+        .map_err(|e| e.to_string())?;
+    stream.set_read_timeout(None).map_err(|e| e.to_string())?;     // Sockets wait as long as they like
+    stream.set_write_timeout(Some(Duration::from_secs(5))).map_err(|e| e.to_string())?;
+
+    // Follow the game
+    let number = {
+        let mut games = games.lock().unwrap();
+        let room = &mut games[id - 1];
+        let number = room.next_socket;
+        room.next_socket += 1;
+        let mut writer = stream.try_clone().map_err(|e| e.to_string())?;
+        websocket::send_text(&mut writer, &event("state", &state(id, &room.game)).to_string())
+            .map_err(|e| e.to_string())?;
+        room.sockets.push((number, writer));
+        number
+    };
+
+    while let Some(message) = websocket::read_message(&mut reader) {
+        let mut games = games.lock().unwrap();
+        let room = &mut games[id - 1];
+        let request = match message {
+            Message::Text(text) => text,
+            Message::Ping(payload) => {
+                let _ = websocket::send_pong(&mut stream, &payload);
+                continue;
+            }
+            Message::Close => {
+                let _ = websocket::send_close(&mut stream);
+                break;
+            }
+        };
+        let answer = match (serde_json::from_str::<Value>(&request), side) {
+            (_, None) => error(403, "Open the socket with ?side=X or ?side=O to play"),
+            (Ok(request), Some(is_player_one)) if request.is_object() => {
+                match to_move(&room.game) {
+                    Some((_, to_move)) if to_move != is_player_one => error(409, "It's not your turn"),
+                    _ if request["computer"] == true => computer(id, room),
+                    _ => submit(id, room, &request),
+                }
+            }
+            _ => error(400, "Send {\"move\": \"D3\"}, {\"move\": \"resign\"} or {\"computer\": true}"),
+        };
+        if answer.0 >= 400 {                    // Everyone has already been told of a move
+            room.tell(number, &event("error", &answer.1));
+        }
+    }
+
+    // Stop following the game
+    let mut games = games.lock().unwrap();
+    games[id - 1].sockets.retain(|(n, _)| *n != number);
+    Ok(())
+}
+
+/***********************************************************
+ * EVENT / PIECE
+ ***********************************************************
+ * A socket message: the event's name added to an object;
+ * and a side's piece.
+***********************************************************/
+fn event(name: &str, value: &Value) -> Value {
+    let mut message = json!({ "event": name });
+    if let (Some(message), Some(fields)) = (message.as_object_mut(), value.as_object()) {
+        message.extend(fields.clone());
+    }
+    message
+}
+
+fn piece(is_player_one: bool) -> &'static str {
+    if is_player_one { _X } else { _O }
+}
+
+/***********************************************************
+ * PARSE_BODY / ERROR
+ ***********************************************************
+ * Read a request's JSON object (an empty body is an empty
+ * object), and answer with an error.
+***********************************************************/
+fn parse_body(body: &str) -> Result<Value, Response> {
+    if body.trim().is_empty() {
+        return Ok(json!({}));
+    }
+    match serde_json::from_str::<Value>(body) {
+        Ok(value) if value.is_object() => Ok(value),
+        Ok(_) => Err(error(400, "The body must be a JSON object")),
+        Err(e) => Err(error(400, &format!("The body isn't JSON: {}", e))),
+    }
+}
+
+fn error(status: u16, message: &str) -> Response {
+    (status, json!({ "error": message }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A request on a fresh connection to serve(), and the
+    // whole response
+    fn request(games: &Games, text: &str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        let games = Arc::clone(games);
+        let served = thread::spawn(move || serve(stream, &games));
+        client.write_all(text.as_bytes()).unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        served.join().unwrap().unwrap();
+        response
+    }
+
+    #[test]
+    fn games_are_created_with_a_size_or_a_position() {
+        let mut games = Vec::new();
+        let (status, state) = route("POST", "/games", "", &mut games);
+        assert_eq!((status, state["id"].clone(), state["size"].clone()), (201, json!(1), json!(8)));
+        let (status, state) = route("POST", "/games", r#"{"size": 4}"#, &mut games);
+        assert_eq!((status, state["id"].clone(), state["size"].clone()), (201, json!(2), json!(4)));
+        assert_eq!(state["to_move"], "X");
+        let position = state["position"].as_str().unwrap().to_string();
+        let (status, state) = route("POST", "/games", &json!({ "position": position }).to_string(), &mut games);
+        assert_eq!((status, state["position"].as_str()), (201, Some(position.as_str())));
+        assert_eq!(route("POST", "/games", r#"{"size": 5}"#, &mut games).0, 400);
+        assert_eq!(route("POST", "/games", "[4]", &mut games).0, 400);
+        assert_eq!(games.len(), 3);
+    }
+
+    #[test]
+    fn games_answer_with_their_state() {
+        let mut games = Vec::new();
+        route("POST", "/games", r#"{"size": 4}"#, &mut games);
+        let (status, state) = route("GET", "/games/1", "", &mut games);
+        assert_eq!(status, 200);
+        assert_eq!(state["board"], json!(["    ", " OX ", " XO ", "    "]));
+        assert_eq!(state["score"], json!({ "X": 2, "O": 2 }));
+        assert_eq!((state["over"].clone(), state["winner"].clone()), (json!(false), Value::Null));
+        let (status, all) = route("GET", "/games?any=thing", "", &mut games);
+        assert_eq!((status, all.as_array().map(Vec::len)), (200, Some(1)));
+    }
+
+    #[test]
+    fn moves_are_played_if_legal() {
+        let mut games = Vec::new();
+        route("POST", "/games", r#"{"size": 4}"#, &mut games);
+        let (status, state) = route("POST", "/games/1/moves", r#"{"move": "C4"}"#, &mut games);
+        assert_eq!((status, state["moves"].clone(), state["to_move"].clone()), (200, json!(["C4"]), json!("O")));
+        assert_eq!(route("POST", "/games/1/moves", r#"{"move": "A1"}"#, &mut games).0, 400);
+        assert_eq!(route("POST", "/games/1/moves", r#"{"move": "pass"}"#, &mut games).0, 400);
+        assert_eq!(route("POST", "/games/1/moves", r#"{"go": "A1"}"#, &mut games).0, 400);
+        assert_eq!(route("POST", "/games/1/moves", "not json", &mut games).0, 400);
+    }
+
+    #[test]
+    fn the_computer_plays_for_the_side_to_move() {
+        let mut games = Vec::new();
+        route("POST", "/games", r#"{"size": 4}"#, &mut games);
+        let (status, state) = route("POST", "/games/1/computer", "", &mut games);
+        assert_eq!(status, 200);
+        assert_eq!(state["moves"], json!([state["played"].clone()]));
+        assert_eq!(state["to_move"], "O");
+    }
+
+    #[test]
+    fn finished_games_refuse_moves() {
+        let mut games = Vec::new();
+        route("POST", "/games", r#"{"size": 4}"#, &mut games);
+        let (status, state) = route("POST", "/games/1/moves", r#"{"move": "resign"}"#, &mut games);
+        assert_eq!((status, state["over"].clone(), state["winner"].clone()), (200, json!(true), json!("O")));
+        assert_eq!(route("POST", "/games/1/moves", r#"{"move": "C4"}"#, &mut games).0, 409);
+        assert_eq!(route("POST", "/games/1/computer", "", &mut games).0, 409);
+    }
+
+    #[test]
+    fn unknown_paths_and_methods_are_refused() {
+        let mut games = Vec::new();
+        route("POST", "/games", "", &mut games);
+        for (method, path, status) in [("GET", "/", 404), ("GET", "/players", 404), ("GET", "/games/2", 404),
+                                       ("GET", "/games/zero", 404), ("GET", "/games/1/chat", 404),
+                                       ("DELETE", "/games", 405), ("PUT", "/games/1", 405),
+                                       ("POST", "/games/1/record", 405), ("GET", "/games/1/moves", 405),
+                                       ("GET", "/games/1/computer", 405)].iter() {
+            let (answered, body) = route(method, path, "", &mut games);
+            assert_eq!(answered, *status, "{} {}", method, path);
+            assert!(body["error"].is_string());
+        }
+    }
+
+    #[test]
+    fn requests_are_answered_over_http() {
+        let games = Games::default();
+        let body = r#"{"size": 6}"#;
+        let response = request(&games, &format!("POST /games HTTP/1.1\r\nHost: localhost\r\n\
+                                                  Content-Length: {}\r\n\r\n{}", body.len(), body));
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        assert!(head.starts_with("HTTP/1.1 201 Created\r\n"));
+        assert!(head.contains("Content-Type: application/json"));
+        assert_eq!(serde_json::from_str::<Value>(body).unwrap()["size"], 6);
+        let response = request(&games, "GET /games/1/record HTTP/1.1\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    }
+
+    #[test]
+    fn big_requests_are_refused_unread() {
+        let games = Games::default();
+        let response = request(&games, &format!("POST /games HTTP/1.1\r\nContent-Length: {}\r\n\r\n", MAX_BODY + 1));
+        assert!(response.starts_with("HTTP/1.1 413 Payload Too Large\r\n"));
+        assert!(games.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,281 @@
+/***********************************************************
+ * JSON GAME RECORDS
+ ***********************************************************
+ * Games can be saved as JSON for other tools (dashboards,
+ * notebooks) to read, when the program is built with the
+ * "json" feature (cargo build --features json). A record
+ * looks like this:
+ *     {
+ *       "format": "othello-game",
+ *       "version": 1,
+ *       "size": 8,
+ *       "start": "---------------------------OX------XO--------------------------- X",
+ *       "players": {
+ *         "X": { "kind": "human", "name": "Marc Tastet", "rating": 2250.5 },
+ *         "O": { "kind": "computer", "name": null, "rating": null }
+ *       },
+ *       "place": "Paris Open", "date": "2024.05.04", "time_control": "15:00",
+ *       "moves": [
+ *         { "number": 1, "player": "X", "move": "D3", "time": 2.1, "eval": null },
+ *         { "number": 2, "player": "O", "move": "pass", "time": 0.0, "eval": null }
+ *       ],
+ *       "clock": { "X": 12.345, "O": 3.21 },
+ *       "result": { "finished": true, "winner": "X", "resigned": null,
+ *                   "discs": { "X": 38, "O": 26 }, "text": "+12" }
+ *     }
+ * "start" is the position string (see position.rs) the
+ * game starts from, always written even for the usual
+ * start. Moves are in order with every pass included, as
+ * "D3" style locations (row letter, column number),
+ * "pass" or "resign"; times are in seconds and "eval" is
+ * the evaluation a game from another program came with.
+ * "result" is worked out from the moves when saving:
+ * whether the game is over, who won ("X", "O", "draw",
+ * or null before the end), who resigned, the discs on the
+ * final board and the result as the source wrote it.
+ * When loading, "format", "version", "size" and "moves"
+ * are needed and everything else is optional; "result"
+ * and "number" are ignored since they follow from the
+ * moves, which are replayed through the rules.
+***********************************************************/
+
+use std::time::Duration;
+use serde_json::{json, Map, Value};
+use crate::game::{move_text, parse_move, Game, Turn};
+use crate::player::Move;
+use crate::position::{parse_position, position_string};
+use crate::{_X, _O, count_pieces, legal_moves, valid_size};
+
+const FORMAT: &str = "othello-game";    // The "format" every record has
+const VERSION: u64 = 1;                 // The "version" written by to_json()
+
+/***********************************************************
+ * TO_JSON
+ ***********************************************************
+ * Write a game as a JSON record.
+***********************************************************/
+pub fn to_json(game: &Game) -> String {
+    let (start, first_player_one) = game.start_position();
+    let mut is_player_one = first_player_one;
+    let mut moves = Vec::new();
+    for (i, turn) in game.moves.iter().enumerate() {
+        moves.push(json!({
+            "number": i + 1,
+            "player": if is_player_one { _X } else { _O },
+            "move": move_text(&turn.mv),
+            "time": turn.time.as_secs_f64(),
+            "eval": turn.eval
+        }));
+        is_player_one = !is_player_one;
+    }
+    let player = |i: usize| json!({
+        "kind": game.players[i],
+        "name": game.info.names[i],
+        "rating": game.info.ratings[i]
+    });
+    let record = json!({
+        "format": FORMAT,
+        "version": VERSION,
+        "size": game.size,
+        "start": position_string(&start, first_player_one),
+        "players": { "X": player(0), "O": player(1) },
+        "place": game.info.place,
+        "date": game.info.date,
+        "time_control": game.info.time_control,
+        "moves": moves,
+        "clock": { "X": game.clock[0].as_secs_f64(), "O": game.clock[1].as_secs_f64() },
+        "result": result(game)
+    });
+    serde_json::to_string_pretty(&record).unwrap_or_default() + "\n"
+}
+
+/***********************************************************
+ * RESULT
+ ***********************************************************
+ * The "result" object for a game, worked out by replaying
+ * its moves. A game that can't be replayed has a null
+ * result.
+***********************************************************/
+fn result(game: &Game) -> Value {
+    let (board, is_player_one) = match game.position() {
+        Ok(position) => position,
+        Err(_) => return Value::Null,
+    };
+    let (x_count, o_count) = count_pieces(&board);
+    // The player before the one to move is the one who resigned
+    let resigned = if game.moves.last().map(|t| &t.mv) == Some(&Move::Resign) {
+        Some(if is_player_one { _O } else { _X })
+    }
+    else {
+        None
+    };
+    let finished = resigned.is_some()
+        || (legal_moves(&board, true).is_empty() && legal_moves(&board, false).is_empty());
+    let winner = match resigned {
+        Some(piece) => Some(if piece == _X { _O } else { _X }),
+        None if !finished => None,
+        None if x_count > o_count => Some(_X),
+        None if o_count > x_count => Some(_O),
+        None => Some("draw"),
+    };
+    json!({
+        "finished": finished,
+        "winner": winner,
+        "resigned": resigned,
+        "discs": { "X": x_count, "O": o_count },
+        "text": game.info.result
+    })
+}
+
+/***********************************************************
+ * FROM_JSON
+ ***********************************************************
+ * Read a game from a JSON record. Returns a message saying
+ * what is wrong if the record can't be used.
+***********************************************************/
+pub fn from_json(text: &str) -> Result<Game, String> {
+    let record: Value = serde_json::from_str(text).map_err(|e| format!("Bad JSON: {}", e))?;
+    if record["format"] != FORMAT {
+        return Err(format!("The JSON isn't an \"{}\" record", FORMAT));
+    }
+    if record["version"].as_u64().is_none_or(|v| v > VERSION) {
+        return Err("The JSON record was written by a newer version of the program".to_string());
+    }
+    let size = match record["size"].as_u64() {
+        Some(size) if valid_size(size as usize) => size as usize,
+        _ => return Err("The JSON record has a bad \"size\"".to_string()),
+    };
+    let mut game = Game::new(size, ["human".to_string(), "human".to_string()]);
+    if let Some(start) = record["start"].as_str() {
+        let (board, is_player_one) = parse_position(start)?;
+        if board.size() != size {
+            return Err("The JSON record's \"start\" doesn't match its \"size\"".to_string());
+        }
+        if (board.clone(), is_player_one) != game.start_position() {
+            game.start = Some((board, is_player_one));
+        }
+    }
+    for (i, piece) in [_X, _O].iter().enumerate() {
+        let player = &record["players"][piece];
+        if let Some(kind) = player["kind"].as_str() {
+            game.players[i] = kind.to_string();
+        }
+        game.info.names[i] = string(&player["name"]);
+        game.info.ratings[i] = player["rating"].as_f64();
+    }
+    game.info.place = string(&record["place"]);
+    game.info.date = string(&record["date"]);
+    game.info.time_control = string(&record["time_control"]);
+    game.info.result = string(&record["result"]["text"]);
+
+    let moves = match record["moves"].as_array() {
+        Some(moves) => moves,
+        None => return Err("The JSON record has no \"moves\"".to_string()),
+    };
+    for (i, mv) in moves.iter().enumerate() {
+        let bad = || format!("Move {} of the JSON record is bad", i + 1);
+        let turn = mv.as_object().ok_or_else(bad)?;
+        game.moves.push(Turn {
+            mv: turn.get("move").and_then(Value::as_str).and_then(parse_move).ok_or_else(bad)?,
+            time: seconds(turn).ok_or_else(bad)?,
+            eval: turn.get("eval").and_then(Value::as_f64)
+        });
+    }
+    let positions = game.positions()?;     // Check the moves against the rules
+    for (i, mv) in moves.iter().enumerate() {
+        let piece = if positions[i].1 { _X } else { _O };
+        if mv["player"].as_str().is_some_and(|p| p != piece) {
+            return Err(format!("Move {} of the JSON record is played out of turn", i + 1));
+        }
+    }
+    if record["clock"].is_object() {
+        for (i, piece) in [_X, _O].iter().enumerate() {
+            let secs = record["clock"][piece].as_f64().unwrap_or(0.0);
+            game.clock[i] = Duration::try_from_secs_f64(secs)   // Not negative or too long to hold
+                .map_err(|_| "The JSON record has a bad \"clock\"".to_string())?;
+        }
+    }
+    else {
+        // Without a clock, charge each move to the player who made it
+        for (i, turn) in game.moves.iter().enumerate() {
+            let clock = &mut game.clock[if positions[i].1 { 0 } else { 1 }];
+            *clock = clock.checked_add(turn.time)
+                .ok_or_else(|| format!("Move {} of the JSON record runs the clock past what can be held", i + 1))?;
+        }
+    }
+    Ok(game)
+}
+
+/***********************************************************
+ * STRING
+ ***********************************************************
+ * A JSON string as an Option, None for null or a missing
+ * value.
+***********************************************************/
+fn string(value: &Value) -> Option<String> {
+    value.as_str().map(|s| s.to_string())
+}
+
+/***********************************************************
+ * SECONDS
+ ***********************************************************
+ * The "time" of a move, 0 if it has none.
+***********************************************************/
+fn seconds(turn: &Map<String, Value>) -> Option<Duration> {
+    match turn.get("time") {
+        None | Some(Value::Null) => Some(Duration::from_secs(0)),
+        Some(time) => time.as_f64().and_then(|secs| Duration::try_from_secs_f64(secs).ok()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::tests::{resigned, sample};
+
+    #[test]
+    fn records_round_trip() {
+        let mut game = sample();
+        game.info.names[1] = Some("Edax".to_string());
+        game.info.ratings[1] = Some(2400.5);
+        game.info.result = Some("+2".to_string());
+        game.moves[3].eval = Some(0.25);
+        for game in [game, resigned()] {
+            assert_eq!(from_json(&to_json(&game)), Ok(game));
+        }
+    }
+
+    #[test]
+    fn results_are_worked_out() {
+        let record: Value = serde_json::from_str(&to_json(&resigned())).unwrap();
+        assert_eq!(record["result"]["resigned"], "X");
+        assert_eq!(record["result"]["winner"], "O");
+        let record: Value = serde_json::from_str(&to_json(&sample())).unwrap();
+        assert_eq!(record["result"]["finished"], true);
+        assert_eq!(record["moves"][10]["move"], "pass");
+    }
+
+    #[test]
+    fn bad_records_are_refused() {
+        let text = to_json(&sample());
+        let bad = [text.replace("othello-game", "chess-game"),
+                   text.replace("\"size\": 4", "\"size\": 5"),
+                   text.replace("\"time\": 0.25", "\"time\": 1e300"),
+                   text.replace("\"time\": 0.25", "\"time\": -1"),
+                   text.replace("\"move\": \"C4\"", "\"move\": \"A1\""),
+                   text.replace("\"O\": 14.0", "\"O\": 1e300")];
+        for text in bad {
+            assert!(from_json(&text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn clocks_too_long_are_refused() {
+        let mut record: Value = serde_json::from_str(&to_json(&sample())).unwrap();
+        record.as_object_mut().unwrap().remove("clock");
+        record["moves"][0]["time"] = 1e19.into();
+        record["moves"][2]["time"] = 1e19.into();
+        assert_eq!(from_json(&record.to_string()).map(|_| ()),
+                   Err("Move 3 of the JSON record runs the clock past what can be held".to_string()));
+    }
+}
@@ -0,0 +1,482 @@
+/***********************************************************
+ * MY OTHELLO GAME
+ ***********************************************************
+ * This is an Othello game program. Othello is based on
+ * Reversi and a full explaination of the rules can be
+ * found here: https://en.wikipedia.org/wiki/Reversi
+ * 
+ * Basic explanation of the rules:
+ * The game is played on an 8*8 board with black and white
+ * game pieces (represented in this program as "X" and "O"
+ * resectively) that can be flipped from one color to
+ * the other. Two of each color is placed in the middle at
+ * diagonals with black ("X") traditionally at the northeast
+ * and southwest corners. Pieces are flipped when they are
+ * captured by placing one of your pieces on a horizontal,
+ * veritical, and/or diagonal line from one of your own
+ * pieces with only the oppenents pieces between the two.
+ * Black ("X") goes first. Each player must place to
+ * capture at least one of the other players pieces
+ * each turn, and their turn is skipped if no location
+ * allows them to do so. The game ends once neither
+ * player can place down, even if the game board is
+ * not full. The player with the most pieces on the
+ * board wins.
+***********************************************************/
+
+pub mod player;         // The Player trait and the human and computer players
+pub mod engine;         // Players that are separate programs
+pub mod nboard;         // Running as an engine for NBoard and other GUIs
+pub mod gtp;            // A line-oriented command protocol for scripts and tests
+pub mod game;           // Game records and saving them to transcript files
+pub mod position;       // One-line position strings
+pub mod wthor;          // Reading the WTHOR tournament game archive
+pub mod ggf;            // Reading and writing GGF game records
+pub mod replay;         // Stepping through a saved game
+pub mod diagram;        // Drawing positions as images
+pub mod png;            // Writing PNG images
+pub mod search;         // Finding positions in collections of games
+pub mod network;        // Playing over the network
+pub mod server;         // Many games at once over the network
+pub mod ratings;        // The server's ladder of Elo ratings
+#[cfg(feature = "json")]
+pub mod json;           // Reading and writing JSON game records
+#[cfg(feature = "json")]
+pub mod http;           // Serving games over HTTP as JSON
+pub mod websocket;      // WebSocket connections for the HTTP API
+pub mod digest;         // SHA-1, base64 and the random tokens shared across the crate
+pub mod tui;            // The full-screen board with a cursor
+pub mod color;          // Drawing the board in color at a terminal
+pub mod theme;          // The characters and colors the board is drawn with
+pub mod accessible;     // Describing the board in words for screen readers
+
+use std::ops::{Index, IndexMut};
+use std::time::{Duration, Instant};
+use color::Highlights;
+use theme::Theme;
+pub static _X: &str = "X";  // Used to represent player one's pieces
+pub static _O: &str = "O";  // Used to represent player two's pieces
+pub const SIZE: usize = 4;  // The default size of the game board
+pub const MAX_SIZE: usize = 8; // The largest board display() and get_coordinates() can handle
+
+/***********************************************************
+ * NUMLET
+ ***********************************************************
+ * This is a structure used to store the number and letter
+ * inputs for the coordinates on the game board. Used by
+ * the Vector in the check() function.
+***********************************************************/
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NumLet {
+    pub n: usize,   // Number index
+    pub l: usize    // Letter index
+}
+
+/***********************************************************
+ * BOARD
+ ***********************************************************
+ * The game board. It holds size * size locations, each
+ * " " (empty), "X" or "O", and is indexed like a grid:
+ * board[letter][num]. The size is chosen when the board is
+ * made so the same program can play small boards and the
+ * standard 8*8 game.
+***********************************************************/
+#[derive(Clone, Debug, PartialEq)]
+pub struct Board {
+    size: usize,                // Number of rows and columns
+    cells: Vec<&'static str>    // Every location, row by row
+}
+
+impl Board {
+    // An empty board of the given size
+    pub fn new(size: usize) -> Board {
+        Board { size, cells: vec![" "; size * size] }
+    }
+
+    // The number of rows (and columns) on the board
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    // Each row of the board, starting with row "A"
+    pub fn iter(&self) -> std::slice::Chunks<'_, &'static str> {
+        self.cells.chunks(self.size)
+    }
+}
+
+impl Index<usize> for Board {
+    type Output = [&'static str];
+
+    // The row at the letter index
+    fn index(&self, letter: usize) -> &[&'static str] {
+        &self.cells[letter * self.size..(letter + 1) * self.size]
+    }
+}
+
+impl IndexMut<usize> for Board {
+    fn index_mut(&mut self, letter: usize) -> &mut [&'static str] {
+        &mut self.cells[letter * self.size..(letter + 1) * self.size]
+    }
+}
+
+/***********************************************************
+ * VALID_SIZE
+ ***********************************************************
+ * Boards need an even size so the starting pieces sit in
+ * the middle, at least 4 so there is room to play, and no
+ * more than MAX_SIZE.
+***********************************************************/
+pub fn valid_size(size: usize) -> bool {
+    size.is_multiple_of(2) && (4..=MAX_SIZE).contains(&size)
+}
+
+/***********************************************************
+ * PARSE_SECONDS
+ ***********************************************************
+ * Read a number of seconds given on the command line, such
+ * as "2.5". Negative numbers, "inf" and times too long for
+ * a timer to count down are refused.
+***********************************************************/
+pub fn parse_seconds(text: &str) -> Option<Duration> {
+    let secs = Duration::try_from_secs_f64(text.parse().ok()?).ok()?;
+    Instant::now().checked_add(secs).map(|_| secs)     // Deadlines are the time now plus this
+}
+
+/***********************************************************
+ * COUNT_PIECES
+ ***********************************************************
+ * Count the "X" and "O" pieces on the board, returned in
+ * that order.
+***********************************************************/
+pub fn count_pieces(board: &Board) -> (usize, usize) {
+    let mut x_count = 0; // Counts "X" pieces
+    let mut o_count = 0; // Counts "O" pieces
+
+    // Iterate through each row, and each column in each row
+    for row in board.iter() {
+        for column in row.iter() {
+            if *column == _X {          // If location is equivalent to "X"...
+                x_count += 1;           // Increment x_count
+            }
+            else if *column == _O {     // If location is equivalent to "O"...
+                o_count += 1;           // Increment o_count
+            }
+        }
+    }
+    (x_count, o_count)
+}
+
+/***********************************************************
+ * CREATE_BOARD
+ ***********************************************************
+ * Create the starting board for games
+***********************************************************/
+pub fn create_board(board: &mut Board) {
+    let m = board.size() / 2;           // Find the middle
+    board[m - 1][m - 1] = _O;   // Northwest "O"
+    board[m - 1][  m  ] = _X;   // Northeast "X"
+    board[  m  ][m - 1] = _X;   // Southwest "X"
+    board[  m  ][  m  ] = _O;   // Southeast "O"
+}
+
+/***********************************************************
+ * DISPLAY
+ ***********************************************************
+ * Display the game board to the user/users, unless the
+ * full-screen board (see tui.rs) is being drawn in place.
+ * It is drawn with the theme chosen at startup (see
+ * theme.rs), in color at a terminal (see color.rs), and
+ * display_highlighted() also picks out the legal moves,
+ * the last move and what it flipped. With "--accessible"
+ * it is described in words instead (see accessible.rs).
+***********************************************************/
+pub fn display(board: &Board) {
+    display_highlighted(board, &Highlights::default());
+}
+
+pub fn display_highlighted(board: &Board, highlights: &Highlights) {
+    if tui::active() {
+        return;                         // The full-screen board is drawn in place instead
+    }
+    if accessible::active() {
+        print!("{}", accessible::describe(board, highlights));
+        return;
+    }
+    let theme = theme::current();
+    if color::enabled(theme) {
+        print!("{}", color::board_text(board, highlights, None, theme));
+    }
+    else {
+        print!("{}", board_text_at(board, None, theme));
+    }
+    if let Some(key) = theme.key() {
+        println!("{}", key);            // Which piece is which
+    }
+}
+
+/***********************************************************
+ * BOARD_TEXT
+ ***********************************************************
+ * Draw the game board as a grid of text, with the column
+ * numbers across the top and the row letters down the side.
+ * board_text_at() draws it with a theme's characters
+ * instead of "X", "O" and " ", and puts the location under
+ * a cursor in brackets.
+***********************************************************/
+pub fn board_text(board: &Board) -> String {
+    board_text_at(board, None, &Theme::default())
+}
+
+pub fn board_text_at(board: &Board, cursor: Option<NumLet>, theme: &Theme) -> String {
+    let mut text = "  ".to_string();    // Proper spacing for column numbers
+    let mut line = "  -".to_string();   // used for horizontal grid lines
+    for i in 0..board.size() {          // For each column add the column number
+        text += &format!("  {} ", i + 1);   // Account for off-by-one (0...size to 1...size)
+        line += "----";                 // Add length to the horizontal grid lines for each column
+    }
+    text += "\n";                       // End column number line
+    text += &format!("{}\n", line);     // Top of grid
+    for (i1, row) in board.iter().enumerate() {             // For each row and column draw the grid
+        text += &format!("{} |", (i1 + 'A' as usize) as u8 as char);  // The row letter starting at "A"
+        for (i2, column) in row.iter().enumerate() {
+            let column = theme.glyph(column);
+            if cursor == Some(NumLet { n: i2, l: i1 }) {
+                text += &format!("[{}]|", column);  // The location under the cursor
+            }
+            else {
+                text += &format!(" {} |", column);  // The board value and the veritical grid line
+            }
+        }
+        text += "\n";                   // Finsh the row
+        text += &format!("{}\n", line); // The next horizontal grid line
+    }
+    text
+}
+
+/***********************************************************
+ * LEGAL_MOVES
+ ***********************************************************
+ * Find every location the current player could place a
+ * piece. Each empty location is tried on a copy of the
+ * board so that nothing is captured on the real one.
+***********************************************************/
+pub fn legal_moves(board: &Board, is_player_one: bool) -> Vec<NumLet> {
+    let mut v = Vec::new();                         // Holds the legal locations
+    for letter in 0..board.size() {                 // For each row...
+        for num in 0..board.size() {                // ...and each column in that row
+            let mut test_board = board.clone();     // Copy the board to test the location on
+            if try_capture(num, letter, &mut test_board, is_player_one) {
+                v.push(NumLet { n: num, l: letter });   // Capturing means it's a legal location
+            }
+        }
+    }
+    v
+}
+
+/***********************************************************
+ * PLACE
+ ***********************************************************
+ * Place the current player's piece at a location and
+ * capture the opponent's pieces. Returns false (and leaves
+ * the board alone) if the location isn't a legal move.
+***********************************************************/
+pub fn place(board: &mut Board, nl: NumLet, is_player_one: bool) -> bool {
+    if nl.n >= board.size() || nl.l >= board.size() {    // Locations off the board can't be played
+        return false;
+    }
+    if try_capture(nl.n, nl.l, board, is_player_one) {  // Try to capture the opponent's pieces
+        if is_player_one {  // Place the current player's piece at the location
+            board[nl.l][nl.n] = _X;
+        }
+        else {
+            board[nl.l][nl.n] = _O;
+        }
+        return true;        // It was a valid move
+    }
+    false
+}
+
+/***********************************************************
+ * COORDINATES
+ ***********************************************************
+ * Turn a location back into the row letter and column
+ * number shown by display(), for example "B3".
+***********************************************************/
+pub fn coordinates(nl: NumLet) -> String {
+    format!("{}{}", (nl.l as u8 + b'A') as char, nl.n + 1)
+}
+
+/***********************************************************
+ * GET_COORDINATES
+ ***********************************************************
+ * Translate the user input into location indexes, if you
+ * can. Return true if it works, otherwise false.
+***********************************************************/
+pub fn get_coordinates(num: &mut usize, letter: &mut usize, pos: String) -> bool {
+    let mut is_valid = false;       // Bool returned from the function, true if valid location
+    if pos.chars().count() == 2 {   // The user should only have input two characters (a letter and a number)
+
+        // If the first character is a number and the second is a letter...
+        if pos.chars().next().unwrap().is_numeric() && pos.chars().nth(1).unwrap().is_alphabetic() {
+            // Set the number index from the first char
+            *num = (pos.chars().next().unwrap() as usize).wrapping_sub(49);
+            // Set the letter index from the second char
+            *letter = pos.chars().nth(1).unwrap().to_ascii_uppercase() as usize - 'A' as usize;
+            is_valid = true; // Is a valid input
+        }
+        // Else if the first character is a letter and the second is a number...
+        else if pos.chars().nth(1).unwrap().is_numeric() && pos.chars().next().unwrap().is_alphabetic() {
+            // Set the number index from the second char
+            *num = (pos.chars().nth(1).unwrap() as usize).wrapping_sub(49);
+            // Set the letter index from the first char
+            *letter = pos.chars().next().unwrap().to_ascii_uppercase() as usize - 'A' as usize;
+            is_valid = true; // Is a valid input
+        }
+    }
+    is_valid // Return whether the input was valid or not
+}
+
+/***********************************************************
+ * TRY_CAPTURE
+ ***********************************************************
+ * Try to capture the opponent's piece[s]. If it does, then
+ * return true, otherwise return false.
+***********************************************************/
+fn try_capture(num: usize, letter: usize, board: &mut Board, is_player_one: bool) -> bool {
+    let mut is_valid = false;       // If the location on the board is a valid capture location it will become true
+    let p_piece: &str;              // Holds the current player's piece
+    let o_piece: &str;              // Holds the current opponent's piece
+    let size = board.size();        // Number of rows and columns on the board
+    if board[letter][num] != " " {  // If the location isn't empty, return to the previous function
+        return is_valid;            // is_valid is false here
+    }
+    if is_player_one {              // if current player is player one...
+        p_piece = _X;               // Player piece is "X"
+        o_piece = _O;               // Opponent piece is "O"
+    }
+    else {                          // Else if current player isn't player one...
+        p_piece = _O;               // Player piece is "O"
+        o_piece = _X                // Opponent piece is "X"
+    }
+
+    if num > 0 {                                    // Check all situations where the number index > 0
+        if check(num, letter, 0, 0, -1, 0,
+                 board, p_piece, o_piece) {         // Check left (if letter is != 0)
+            is_valid = true;                        // Capture successful
+        }
+        if check(num, letter, 0, size - 1, -1, 0,
+                 board, p_piece, o_piece) {         // Check left again (in case letter is == 0)
+            is_valid = true;                        // Capture successful
+        }
+        if letter > 0 {                             // If letter > 0...
+            if check(num, letter, 0, 0, -1, -1,
+                     board, p_piece, o_piece) {     // Check left-up diagonal
+                is_valid = true;                    // Capture successful
+            }
+        }
+        if letter < size - 1 {                      // If letter < size - 1...
+            if check(num, letter, 0, size - 1, -1, 1,
+                     board, p_piece, o_piece) {     // Check left-down diagonal
+                is_valid = true;                    // Capture successful
+            }
+        }
+    }
+    if num < size - 1 {                             // Check all situations where the number index < size - 1
+        if check(num, letter, size - 1, 0, 1, 0,
+                 board, p_piece, o_piece) {         // Check right (if letter != 0)
+            is_valid = true;                        // Capture successful
+        }
+        if check(num, letter, size - 1, size - 1, 1, 0,
+                 board, p_piece, o_piece) {         // Check right again (in case letter == 0)
+            is_valid = true;                        // Capture successful
+        }
+        if letter > 0 {                             // If letter > 0...
+            if check(num, letter, size - 1, 0, 1, -1,
+                     board, p_piece, o_piece) {     // Check right-up diagonal
+                is_valid = true;                    // Capture successful
+            }
+        }
+        if letter < size - 1 {                      // If letter < size -1...
+            if check(num, letter, size - 1, size - 1, 1, 1,
+                     board, p_piece, o_piece) {     // Check right-down diagonal
+                is_valid = true;                    // Capture successful
+            }
+        }
+    }                                               // Only checks still needed are up and down (with no left or right)
+    if letter > 0 {                                 // If letter > 0...
+        if check(num, letter, 0, 0, 0, -1,
+                 board, p_piece, o_piece) {         // Check up (if number != 0)
+            is_valid = true;                        // Capture successful
+        }
+        if check(num, letter, size - 1, 0, 0, -1,
+                 board, p_piece, o_piece) {         // Check up again (in case number == 0)
+            is_valid = true;                        // Capture successful
+        }
+    }
+    if letter < size -1 {                           // If letter < size - 1
+        if check(num, letter, 0, size - 1, 0, 1,
+                 board, p_piece, o_piece) {         // Check down (if number != 0)
+            is_valid = true;                        // Capture successful
+        }
+        if check(num, letter, size - 1, size - 1, 0, 1,
+                 board, p_piece, o_piece) {         // Check down again (in case number == 0)
+            is_valid = true;                        // Capture successful
+        }
+    }
+    is_valid                                        // Return true if capture was successful
+}
+
+/***********************************************************
+ * CHECK
+ ***********************************************************
+ * Checks if a capture can take place, and if it can it
+ * captures all the relevant pieces. This is done by taking
+ * in both indexes and their iteration value (-1, 0, or 1)
+ * and looping through the line until the board edge (limit)
+ * is reached, an empty space is reached, or one of the
+ * current player's pieces are reached. If any of the
+ * opponent's pieces were found they are captured and
+ * true is returned. In all other cases false is returned.
+***********************************************************/
+#[allow(clippy::too_many_arguments)]
+fn check(num: usize, letter: usize,
+         limit_n: usize, limit_l: usize,
+         it_n: i8, it_l: i8,
+         board: &mut Board,
+         p_piece: &str, o_piece: &str)
+         -> bool {
+    
+    let mut n = num;                                // Copy number index for iteration
+    let mut l = letter;                             // Copy letter index for iteration
+    let mut v = Vec::new();                         // Create a Vector to hold potential capturable pieces
+    while n != limit_n && l != limit_l {            // Until the edge of the board is reached...
+        n = ((n as i8) + it_n) as usize;            // Iterate the number index (usize can't iterate without casting)
+        l = ((l as i8) + it_l) as usize;            // Iterate the letter index (usize can't iterate without casting)
+        if board[l][n] == o_piece {                 // If an opponent's piece is found...
+            let nl = NumLet {                       // Create a NumLet struct to hold the location and...
+                n,      // Number index
+                l       // Letter index
+            };
+            v.push(nl);                             // Store the struct in the Vector
+        }
+        else if board[l][n] == p_piece {            // If one of the current player's pieces are found...
+            if !v.is_empty() {                      // Check if the Vector holds any locations. If it does...
+                while !v.is_empty() {               // Loop through the Vector until it is empty
+                    let pos = v.remove(0);          // Remove and store the location from the Vector
+                    if p_piece == _X {              // If the current player is "X"...
+                        board[pos.l][pos.n] = _X;   // Change that location to "X"
+                    }
+                    else {                          // Otherwise the player is "O", so...
+                        board[pos.l][pos.n] = _O;   // Change that location to "O"
+                    }
+                }
+                return true;                        // Once the Vetor is empty return true (you did capture)
+            }
+            return false;                           // If the Vector was empty then return false (you didn't capture)
+        }
+        else {                                      // If it's not the current player's piece or the opponent's piece...
+            return false;                           // It's an empty location, so return false
+        }
+    }
+    false                                           // If you reach the edge of the board return false
+}
\ No newline at end of file
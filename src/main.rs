@@ -1,44 +1,85 @@
 /***********************************************************
  * MY OTHELLO GAME
  ***********************************************************
- * This is an Othello game program. Othello is based on
- * Reversi and a full explaination of the rules can be
- * found here: https://en.wikipedia.org/wiki/Reversi
- * 
- * Basic explanation of the rules:
- * The game is played on an 8*8 board with black and white
- * game pieces (represented in this program as "X" and "O"
- * resectively) that can be flipped from one color to
- * the other. Two of each color is placed in the middle at
- * diagonals with black ("X") traditionally at the northeast
- * and southwest corners. Pieces are flipped when they are
- * captured by placing one of your pieces on a horizontal,
- * veritical, and/or diagonal line from one of your own
- * pieces with only the oppenents pieces between the two.
- * Black ("X") goes first. Each player must place to
- * capture at least one of the other players pieces
- * each turn, and their turn is skipped if no location
- * allows them to do so. The game ends once neither
- * player can place down, even if the game board is
- * not full. The player with the most pieces on the
- * board wins.
+ * The terminal program: it reads the command line, then
+ * plays games at the terminal or runs one of the other
+ * modes. The rules and everything the modes are built
+ * from are in the library (lib.rs), which the server
+ * program (bin/othello-server.rs) shares.
 ***********************************************************/
 
 use text_io::read;      // For the read!() function to accept user input
-static _X: &str = "X";  // Used to represent player one's pieces
-static _O: &str = "O";  // Used to represent player two's pieces
-const SIZE: usize = 4;  // The size of the game board
+use std::time::{Duration, Instant};
+use othello::{Board, _X, _O, SIZE, coordinates, count_pieces, display_highlighted, legal_moves,
+              parse_seconds, place};
+use othello::color::Highlights;
+use othello::{diagram, gtp, nboard, network, replay, search, wthor};
+use othello::game::Game;
+use othello::position::parse_position;
+use othello::player::{Player, Move, HumanPlayer, ComputerPlayer};
+use othello::engine::EnginePlayer;
+use othello::network::RemotePlayer;
+use othello::tui::{self, TuiPlayer};
+use othello::{accessible, theme};
 
 /***********************************************************
- * NUMLET
+ * OPTIONS
  ***********************************************************
- * This is a structure used to store the number and letter
- * inputs for the coordinates on the game board. Used by
- * the Vector in the check() function.
+ * What the command line asked for: who plays each side,
+ * how long engines get per move, a game to resume or a
+ * position to start from, a file to keep saving to, how
+ * the board should look, and what the program should do.
 ***********************************************************/
-struct NumLet {
-    n: usize,   // Number index
-    l: usize    // Letter index
+struct Options {
+    kinds: [Option<String>; 2], // Kind of player X and O, if given
+    timeout: Duration,          // Time engines get per move
+    grace: Duration,            // Time remote players get to come back
+    load: Option<String>,       // Transcript of a game to resume
+    start: Option<(Board, bool)>,   // Position to start new games from, if not the usual one
+    save: Option<String>,       // Transcript to save the game to after every move
+    export: Option<String>,     // File to write the loaded game to
+    replay: Option<String>,     // Saved game to step through
+    diagram: Option<String>,    // Image file to draw a position to
+    at: Option<usize>,          // Number of moves into the game to draw
+    marks: diagram::Marks,      // What to mark on the drawing
+    strip: Option<usize>,       // Draw every this many moves of the game instead
+    cell: usize,                // Pixels across each location of the drawing
+    search: Option<(Board, bool)>,  // Position to look for in the games
+    games: Vec<String>,         // Saved games to search
+    join: Option<String>,       // Address of a hosted game or server to join
+    name: Option<String>,       // Name to use on a server
+    resume: Option<String>,     // Token to go back to a dropped game with
+    http: Option<String>,       // Address to serve games over HTTP on
+    tui: bool,                  // True if people play on the full-screen board
+    accessible: bool,           // True if boards are described in words
+    config: Option<String>,     // Settings file for how the board looks
+    looks: Vec<(String, String)>,   // Settings for how it looks from the command line
+    wthor: Vec<String>,         // WTHOR game files (.wtb) to list games from
+    jou: Option<String>,        // WTHOR player names file
+    trn: Option<String>,        // WTHOR tournament names file
+    filter: wthor::Filter,      // Which WTHOR games to list
+    mode: Mode                  // What the program should do
+}
+
+/***********************************************************
+ * MODE
+ ***********************************************************
+ * Whether the program plays games at the terminal or
+ * answers one of the protocols on stdin/stdout instead.
+***********************************************************/
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    Play,       // Play games at the terminal
+    NBoard,     // Run as an NBoard engine ("--nboard")
+    Gtp,        // Answer text protocol commands ("--gtp")
+    Wthor,      // List games from WTHOR files ("--wthor")
+    Export,     // Write a loaded game in another format ("--export")
+    Replay,     // Step through a saved game ("--replay")
+    Diagram,    // Draw a position as an image ("--diagram")
+    Search,     // Find a position in stored games ("--search")
+    Join,       // Play in a game someone else hosts ("--join")
+    Connect,    // Play on a multi-game server ("--connect")
+    Http        // Serve games over HTTP ("--http")
 }
 
 /***********************************************************
@@ -46,20 +87,158 @@ struct NumLet {
  ***********************************************************
  * This function is used to create the game board and start
  * the game. Once the game is finished it will prompt the
- * user if they want to play again.
+ * user if they want to play again. Either side can be made
+ * a computer player or an engine program from the command
+ * line, for example:
+ *     othello --x human --o computer
+ *     othello --x "engine:./my_bot --fast" --timeout 5
+ *     othello --load lunch.txt --save lunch.txt
+ *     othello --position "-XXO-OX--OXX-O-- O"
+ *     othello --host 5000
+ *     othello --join 192.168.1.20:5000
+ *     othello --connect 192.168.1.20:5000 --name Dylan
+ *     othello --tui --o computer
+ *     othello --theme unicode --palette colorblind
+ *     othello --accessible --o computer
+ * With "--nboard" the program doesn't play a game itself
+ * but acts as an engine for NBoard (see nboard.rs), and
+ * with "--gtp" it answers text commands (see gtp.rs).
+ * "--http" serves games to other programs over HTTP, as
+ * JSON, when built with the "json" feature (see http.rs).
+ * "--wthor" lists games from the WTHOR archive instead,
+ * for example every 1998 game of a player:
+ *     othello --wthor WTH_1998.wtb --player Tastet
+ * "--search" finds the games, saved or in the archive,
+ * that reached a position (see search.rs).
+ * "--export" writes a saved game in the format its file
+ * name asks for (GGF for ".ggf", JSON for ".json" when
+ * built with the "json" feature, a transcript otherwise):
+ *     othello --load lunch.txt --export lunch.ggf
+ * and "--replay" steps through a saved game move by move
+ * (see replay.rs). "--diagram" draws a position of a
+ * saved game, or a position string, as an image:
+ *     othello --load lunch.txt --at 20 --diagram move20.svg
+ *     othello --load lunch.txt --strip 10 --diagram lunch.png
 ***********************************************************/
 fn main() {
-    // The game board; " " is used as the value for empty
-    // spaces so as to make displaying the board simpler.
-    let mut board = [[" "; SIZE]; SIZE];
-    
-    create_board(&mut board);   // Used to place the beginning pieces on the board
+    let options = match parse_args(std::env::args().skip(1).collect()) {
+        Ok(options) => options,
+        Err(message) => usage(&message),
+    };
+    if let Err(message) = theme::start(options.config.as_deref(), &options.looks) {
+        usage(&message);
+    }
+    if options.accessible {
+        accessible::start();
+    }
+    match options.mode {
+        Mode::NBoard => return nboard::run(),        // Act as an engine instead of playing
+        Mode::Gtp => return gtp::run(SIZE),  // Answer commands instead of playing
+        Mode::Wthor => {                             // List archive games instead of playing
+            if let Err(message) = wthor::run(&options.wthor, options.jou.as_deref(),
+                                             options.trn.as_deref(), &options.filter) {
+                eprintln!("{}", message);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Mode::Export => {                            // Convert a game instead of playing
+            let result = Game::load(options.load.as_deref().unwrap_or_default())
+                .and_then(|game| game.save(options.export.as_deref().unwrap_or_default()));
+            if let Err(message) = result {
+                eprintln!("{}", message);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Mode::Replay => {                            // Step through a game instead of playing
+            if let Err(message) = replay::run(options.replay.as_deref().unwrap_or_default()) {
+                eprintln!("{}", message);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Mode::Diagram => {                           // Draw a position instead of playing
+            if let Err(message) = draw(&options) {
+                eprintln!("{}", message);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Mode::Search => {                            // Search games instead of playing
+            let position = options.search.as_ref().expect("search mode needs a position");
+            if let Err(message) = search::run(position, &options.games, &options.wthor,
+                                              options.jou.as_deref(), options.trn.as_deref()) {
+                eprintln!("{}", message);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Mode::Join => {                              // The host runs the game
+            if let Err(message) = network::join(options.join.as_deref().unwrap_or_default(),
+                                                options.resume.as_deref()) {
+                eprintln!("{}", message);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Mode::Connect => {                           // The server runs the games
+            if let Err(message) = network::connect(options.join.as_deref().unwrap_or_default(),
+                                                   options.name.as_deref(), options.resume.as_deref()) {
+                eprintln!("{}", message);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Mode::Http => {                              // Answer requests instead of playing
+            if let Err(message) = serve_http(options.http.as_deref().unwrap_or_default()) {
+                eprintln!("{}", message);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Mode::Play => {}
+    }
+
+    // The game to play: the one being resumed, or a new one.
+    // A resumed game keeps its players unless the command
+    // line names different ones.
+    let mut game = match &options.load {
+        Some(path) => match Game::load(path) {
+            Ok(game) => game,
+            Err(message) => usage(&message),
+        },
+        None => match &options.start {
+            Some((board, is_player_one)) => Game::from_position(board.clone(), *is_player_one,
+                                                                ["human".to_string(), "human".to_string()]),
+            None => Game::new(SIZE, ["human".to_string(), "human".to_string()]),
+        },
+    };
+    for (i, kind) in options.kinds.iter().enumerate() {
+        if let Some(kind) = kind {
+            game.players[i] = kind.clone();
+        }
+    }
     let mut play = true;        // True if the user wants to play the game
+    if options.tui {
+        if let Err(message) = tui::start() {
+            usage(&message);
+        }
+    }
+
+    // Player X and player O, humans unless the command line says otherwise
+    let mut players = match create_players(&game.players, options.timeout, options.grace, options.tui) {
+        Ok(players) => players,
+        Err(message) => usage(&message),
+    };
 
     // While the user wants to keep playing games keep
     // running the game
     while play {
-        othello(board); // Play Othello
+        othello(&mut game, &mut players, options.save.as_deref()); // Play Othello
+        let start = game.start.take();                              // The next game starts fresh
+        game = Game::new(game.size, game.players.clone());          // from the same position
+        game.start = start;
 
         // Used to determine if the user inputs an appropriate
         // response, either a "y"/"Y" or "n"/"N"
@@ -76,426 +255,619 @@ fn main() {
             }
             else if option.to_lowercase() == "y" { // User wants to play again
                 good_in = true; // User input was good
+                play = players.iter_mut().all(|p| p.rematch());    // Anyone over the network has to agree
             }
         }
     }
 }
 
 /***********************************************************
- * OTHELLO
+ * SERVE_HTTP
  ***********************************************************
- * Play the game of Othello. Starts by diplaying the game
- * board and then prompting player one ("X") to take their
- * turn. Turns alternate unless a players turn is skipped,
- * and the game ends once neither player can play. It then
- * displays the winner (or that it's a tie) and the player's
- * scores and returns back the the main() function.
+ * Serve games over HTTP, or explain that the program was
+ * built without JSON.
 ***********************************************************/
-fn othello(mut board: [[&str; SIZE]; SIZE]) {
-    let mut playing = true;         // True if still playing
-    let mut was_skipped = false;    // True if the last player's turn was skipped
-    let mut is_player_one = true;   // True if the current player is player one ("X")
-    
-    // While the game isn't over keep playing
-    while playing {
-        if !was_skipped {   // Only display the board once if the last player was skipped
-            display(board); // Display the game board
-        }
-
-        // Create a copy of the game board so that you can test if the current
-        // player has any locations they can place.
-        let mut test_turn_board = board;
-
-        // If the current player can take their turn then they do so
-        if can_take_turn(&mut test_turn_board, is_player_one) {
-            if was_skipped { // If the last player was skipped inform the users
-                println!("Next Player's turn was Skipped!\n\n");
-                was_skipped = false; // The current player can take their turn, so reset to false
-            }
-            if is_player_one { // Inform the users who's turn it is
-                println!("Player X's turn.");
-            }
-            else {
-                println!("Player O's turn.");
-            }
-            take_turn(&mut board, is_player_one); // Current player takes their turn
-        }
-        else { // If the current player can't take their turn then...
-            if was_skipped { // If the last player was skipped than neither can play (game is over)
-                let mut x_count = 0; // Counts "X" pieces
-                let mut o_count = 0; // Counts "O" pieces
-
-                // Iterate through each row, and each column in each row
-                for (i1, _row) in board.iter().enumerate() {
-                    for (i2, _column) in _row.iter().enumerate() {
-                        if board[i1][i2] == _X {        // If location is equivalent to "X"...
-                            x_count = x_count + 1;      // Increment x_count
-                        }
-                        else if board[i1][i2] == _O {   // If location is equivalent to "O"...
-                            o_count = o_count + 1;      // Increment o_count
-                        }
-                    }
-                }
+#[cfg(feature = "json")]
+fn serve_http(address: &str) -> Result<(), String> {
+    othello::http::run(&othello::http::address(address))
+}
 
-                // Determine who won, or if there is a tie
-                if x_count > o_count { // "X" wins!
-                    println!("--------------");
-                    println!("Player X wins!");
-                    println!("--------------\n");
-                    println!("Player X score: {}", x_count); // Display "X" count
-                    println!("Player O score: {}", o_count); // Display "O" count
-                }
-                else if x_count < o_count { // "O" wins!
-                    println!("--------------");
-                    println!("Player O wins!");
-                    println!("--------------\n");
-                    println!("Player O score: {}", o_count); // Display "O" count
-                    println!("Player X score: {}", x_count); // Display "X" count
-                }
-                else if x_count == o_count { // It's a tie! ...maybe play again?
-                    println!("--------------");
-                    println!("It's a tie!");
-                    println!("--------------\n");
-                    println!("Both Player's scored: {}", x_count); // Display the shared count
-                }
-                playing = false; // Game is over
-            }
-            was_skipped = true; // Current player is skipped
-        }
-        is_player_one = !is_player_one; // Switch who's turn it is
-    }
+#[cfg(not(feature = "json"))]
+fn serve_http(_address: &str) -> Result<(), String> {
+    Err("The HTTP server needs the program built with \"--features json\"".to_string())
 }
 
 /***********************************************************
- * CREATE_BOARD
+ * USAGE
  ***********************************************************
- * Create the starting board for games
+ * Explain what went wrong with the command line and how to
+ * use it, then exit.
 ***********************************************************/
-fn create_board(board: &mut [[&str; SIZE]; SIZE]) {
-    let m = SIZE / 2;           // Find the middle
-    board[m - 1][m - 1] = _O;   // Northwest "O"
-    board[m - 1][  m  ] = _X;   // Northeast "X"
-    board[  m  ][m - 1] = _X;   // Southwest "X"
-    board[  m  ][  m  ] = _O;   // Southeast "O"
+fn usage(message: &str) -> ! {
+    eprintln!("{}", message);
+    eprintln!("Usage: othello [--x PLAYER] [--o PLAYER] [--timeout SECONDS]");
+    eprintln!("               [--load FILE | --position POSITION] [--save FILE] [--host PORT]");
+    eprintln!("               [--grace SECONDS] [--tui] [--config FILE] [--theme ascii|unicode]");
+    eprintln!("               [--pieces XO[E]] [--palette classic|colorblind|mono|none] [--accessible]");
+    eprintln!("       othello --join HOST:PORT [--resume TOKEN]");
+    eprintln!("       othello --connect HOST:PORT [--name NAME] [--resume TOKEN]");
+    eprintln!("       othello --load FILE --export FILE");
+    eprintln!("       othello --replay FILE");
+    eprintln!("       othello (--load FILE | --position POSITION) --diagram FILE.svg|FILE.png");
+    eprintln!("               [--at MOVES | --strip MOVES] [--marks last,legal,numbers|none]");
+    eprintln!("               [--cell PIXELS]");
+    eprintln!("       othello --http PORT|ADDRESS:PORT");
+    eprintln!("       othello --nboard");
+    eprintln!("       othello --gtp");
+    eprintln!("       othello --wthor FILE.wtb... [--jou FILE] [--trn FILE]");
+    eprintln!("               [--player NAME] [--year YEAR] [--tournament NAME]");
+    eprintln!("       othello --search POSITION [--games FILE]... [--wthor FILE.wtb]...");
+    eprintln!("               [--jou FILE] [--trn FILE]");
+    eprintln!("PLAYER is human, computer, engine:COMMAND or remote:PORT");
+    std::process::exit(2);
 }
 
 /***********************************************************
- * DISPLAY
+ * PARSE_ARGS
  ***********************************************************
- * Display the game board to the user/users
+ * Read the command line arguments into Options. Both
+ * players are human unless "--x" or "--o" names another
+ * kind of player. An engine is given as "engine:" followed
+ * by the command that starts it, and "--timeout" sets how
+ * many seconds engines get per move. "--host" (the same as
+ * "--o remote:PORT") waits for a player to join over the
+ * network with "--join", and "--connect" plays on a
+ * multi-game server (bin/othello-server.rs) as "--name".
+ * "--grace" sets how many seconds a remote player whose
+ * connection drops has to come back, which "--resume"
+ * does with the token they were given. "--load" resumes a
+ * saved game, "--position" starts games from a position
+ * string instead of the usual start, and "--save" keeps a
+ * transcript of the game up to date, in GGF or JSON if its
+ * name ends in ".ggf" or ".json". "--export" writes the
+ * "--load" game to another file and stops, and "--replay"
+ * steps through a saved game. "--diagram" draws the
+ * "--load" game after "--at" moves (or the "--position")
+ * with the "--marks" asked for, as SVG or PNG; "--strip"
+ * draws every so many moves side by side instead, and
+ * "--cell" sets the size in pixels of each location.
+ * "--wthor" (which can be given more than once) lists
+ * games from WTHOR files, and "--jou", "--trn",
+ * "--player", "--year" and "--tournament" say where the
+ * names are and which games to list.
+ * "--search" looks for a position string in the "--games"
+ * files and any "--wthor" files instead of listing them.
+ * "--http" serves games on the port (on this machine) or
+ * address. "--tui" has the human players choose their
+ * moves with a cursor on a full-screen board (see tui.rs).
+ * "--config" reads how the board should look from a file
+ * instead of ~/.othello.conf, and "--theme", "--pieces"
+ * and "--palette" change it (see theme.rs).
+ * "--accessible" describes the board in words for screen
+ * readers instead of drawing it (see accessible.rs).
+ * Returns a message describing the problem if the
+ * arguments can't be used.
 ***********************************************************/
-fn display(board: [[&str; SIZE]; SIZE]) {
-    let mut line = "  -".to_string();   // used for horizontal grid lines
-    print!("  ");                       // Proper spacing for column numbers
-    for i in 0..SIZE {                  // For each column print the column number
-        print!("  {} ", i + 1);         // Account for off-by-one (0...SIZE to 1...SIZES)
-        line += "----";                 // Add length to the horizontal grid lines for each column
+fn parse_args(args: Vec<String>) -> Result<Options, String> {
+    let mut options = Options {
+        kinds: [None, None],
+        timeout: Duration::from_secs(10),
+        grace: Duration::from_secs(60),
+        load: None,
+        start: None,
+        save: None,
+        export: None,
+        replay: None,
+        diagram: None,
+        at: None,
+        marks: diagram::Marks::default(),
+        strip: None,
+        cell: 48,
+        search: None,
+        games: Vec::new(),
+        join: None,
+        name: None,
+        resume: None,
+        http: None,
+        tui: false,
+        accessible: false,
+        config: None,
+        looks: Vec::new(),
+        wthor: Vec::new(),
+        jou: None,
+        trn: None,
+        filter: wthor::Filter::default(),
+        mode: Mode::Play
+    };
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {                // Options without a value
+            "--nboard" => { options.mode = Mode::NBoard; continue; }
+            "--tui" => { options.tui = true; continue; }
+            "--accessible" => { options.accessible = true; continue; }
+            "--gtp" => { options.mode = Mode::Gtp; continue; }
+            _ => {}
+        }
+        let value = match args.next() {     // Every other option takes a value
+            Some(value) => value,
+            None => return Err(format!("Missing value after \"{}\"", arg)),
+        };
+        match arg.as_str() {
+            "--x" => options.kinds[0] = Some(value),
+            "--o" => options.kinds[1] = Some(value),
+            "--load" => options.load = Some(value),
+            "--position" => options.start = Some(parse_position(&value)?),
+            "--wthor" => {
+                options.wthor.push(value);
+                options.mode = Mode::Wthor;
+            }
+            "--search" => options.search = Some(parse_position(&value)?),
+            "--games" => options.games.push(value),
+            "--host" => options.kinds[1] = Some(format!("remote:{}", value)),
+            "--join" => {
+                options.join = Some(value);
+                options.mode = Mode::Join;
+            }
+            "--connect" => {
+                options.join = Some(value);
+                options.mode = Mode::Connect;
+            }
+            "--name" => options.name = Some(value),
+            "--config" => options.config = Some(value),
+            "--theme" | "--pieces" | "--palette" => options.looks.push((arg[2..].to_string(), value)),
+            "--http" => {
+                options.http = Some(value);
+                options.mode = Mode::Http;
+            }
+            "--resume" => options.resume = Some(value),
+            "--jou" => options.jou = Some(value),
+            "--trn" => options.trn = Some(value),
+            "--player" => options.filter.player = Some(value),
+            "--tournament" => options.filter.tournament = Some(value),
+            "--year" => match value.parse::<u16>() {
+                Ok(year) => options.filter.year = Some(year),
+                _ => return Err(format!("Bad year \"{}\"", value)),
+            },
+            "--save" => options.save = Some(value),
+            "--replay" => {
+                options.replay = Some(value);
+                options.mode = Mode::Replay;
+            }
+            "--diagram" => {
+                options.diagram = Some(value);
+                options.mode = Mode::Diagram;
+            }
+            "--at" => match value.parse::<usize>() {
+                Ok(at) => options.at = Some(at),
+                _ => return Err(format!("Bad move number \"{}\"", value)),
+            },
+            "--marks" => options.marks = diagram::parse_marks(&value)?,
+            "--strip" => match value.parse::<usize>() {
+                Ok(moves) if moves > 0 => options.strip = Some(moves),
+                _ => return Err(format!("Bad number of moves \"{}\"", value)),
+            },
+            "--cell" => match value.parse::<usize>() {
+                Ok(cell) if (diagram::MIN_CELL..=diagram::MAX_CELL).contains(&cell) => options.cell = cell,
+                _ => return Err(format!("Bad cell size \"{}\" (use {} to {} pixels)", value,
+                                        diagram::MIN_CELL, diagram::MAX_CELL)),
+            },
+            "--export" => {
+                options.export = Some(value);
+                options.mode = Mode::Export;
+            }
+            "--timeout" => match parse_seconds(&value) {
+                Some(secs) if !secs.is_zero() => options.timeout = secs,
+                _ => return Err(format!("Bad timeout \"{}\"", value)),
+            },
+            "--grace" => match parse_seconds(&value) {
+                Some(secs) => options.grace = secs,
+                _ => return Err(format!("Bad grace period \"{}\"", value)),
+            },
+            _ => return Err(format!("Unknown option \"{}\"", arg)),
+        }
     }
-    println!();                         // End column number line
-    println!("{}", line);               // Top of grid
-    for (i1, row) in board.iter().enumerate() {             // For each row and column print the grid
-        print!("{} |", (i1 + 'A' as usize) as u8 as char);  // Print the row letter starting at "A"
-        for (_i2, column) in row.iter().enumerate() {
-            print!(" {} |", column);    // Print the board value and the veritical grid line
+    if options.search.is_some() {
+        options.mode = Mode::Search;        // The "--wthor" files are searched, not listed
+        if options.games.is_empty() && options.wthor.is_empty() {
+            return Err("\"--search\" needs \"--games\" or \"--wthor\" files to search".to_string());
         }
-        println!();                     // Finsh the row
-        println!("{}", line);           // Print the next horizontal grid line
     }
+    if options.tui && options.accessible {
+        return Err("Use either \"--tui\" or \"--accessible\", not both".to_string());
+    }
+    if options.load.is_some() && options.start.is_some() {
+        return Err("Use either \"--load\" or \"--position\", not both".to_string());
+    }
+    if options.mode == Mode::Diagram && options.load.is_none() && options.start.is_none() {
+        return Err("\"--diagram\" needs a game to \"--load\" or a \"--position\"".to_string());
+    }
+    if options.mode == Mode::Export && options.load.is_none() {
+        return Err("\"--export\" needs a game to \"--load\"".to_string());
+    }
+    Ok(options)
 }
 
 /***********************************************************
- * TAKE_TURN
+ * DRAW
  ***********************************************************
- * The player takes their turn by entering a location on the
- * board. If it's a valid location capture the opponent's
- * pieces and end the player's turn.
+ * Draw the position the command line asked for: the
+ * "--load" game after "--at" moves (all of them if "--at"
+ * isn't given), or the "--position". With "--strip" the
+ * positions every that many moves are drawn, from the
+ * start to the end of the game.
 ***********************************************************/
-fn take_turn(board: &mut [[&str; SIZE]; SIZE], is_player_one: bool) {
-    let mut valid_move = false;     // The player needs to enter a valid location
-    let mut num: usize = 0;         // The column number index
-    let mut letter: usize = 0;      // The row letter index (index is translated from a char)
-    while !valid_move {             // Until the player enters a valid location...
-        let pos: String = read!();  // Get the input from the user
-        if get_coordinates(&mut num, &mut letter, pos) {        // Try to translate the user input into indexes
-            if try_capture(num, letter, board, is_player_one) { // Try to capture the opponent's pieces
-                valid_move = true;  // If the input was valid and could capture then it was a valid move
-                if is_player_one {  // Place the current player's piece at the location
-                    board[letter][num] = _X;
-                }
-                else {
-                    board[letter][num] = _O;
-                }
-            }
-        }
-    }
+fn draw(options: &Options) -> Result<(), String> {
+    let game = match (&options.load, &options.start) {
+        (Some(path), _) => Game::load(path)?,
+        (None, Some((board, is_player_one))) => Game::from_position(board.clone(), *is_player_one,
+                                                                   ["human".to_string(), "human".to_string()]),
+        (None, None) => return Err("There is no position to draw".to_string()),
+    };
+    let last = game.moves.len();
+    let at: Vec<usize> = match options.strip {
+        Some(every) => (0..last).step_by(every).chain(std::iter::once(last)).collect(),
+        None => vec![options.at.unwrap_or(last)],
+    };
+    diagram::save(&game, &at, options.marks, options.cell, options.diagram.as_deref().unwrap_or_default())
 }
 
 /***********************************************************
- * GET_COORDINATES
+ * CREATE_PLAYERS
  ***********************************************************
- * Translate the user input into location indexes, if you
- * can. Return true if it works, otherwise false.
+ * Build player X and player O from their kinds, starting
+ * any engines with the time they get per move and waiting
+ * for any remote players to join, who can come back
+ * within the grace period if their connection drops.
+ * Returns a message describing the problem if a player
+ * can't be made.
 ***********************************************************/
-fn get_coordinates(num: &mut usize, letter: &mut usize, pos: String) -> bool {
-    let mut is_valid = false;       // Bool returned from the function, true if valid location
-    if pos.chars().count() == 2 {   // The user should only have input two characters (a letter and a number)
-
-        // If the first character is a number and the second is a letter...
-        if pos.chars().nth(0).unwrap().is_numeric() && pos.chars().nth(1).unwrap().is_alphabetic() {
-            // Set the number index from the first char
-            *num = pos.chars().nth(0).unwrap() as usize - 49;
-            // Set the letter index from the second char
-            *letter = pos.chars().nth(1).unwrap().to_ascii_uppercase() as usize - 'A' as usize;
-            is_valid = true; // Is a valid input
+fn create_players(kinds: &[String; 2], timeout: Duration, grace: Duration, tui: bool)
+                  -> Result<[Box<dyn Player>; 2], String> {
+    let mut players: Vec<Box<dyn Player>> = Vec::new();
+    for kind in kinds.iter() {
+        if let Some(command) = kind.strip_prefix("engine:") {
+            players.push(Box::new(EnginePlayer::new(command, timeout)?));
+            continue;
+        }
+        if let Some(address) = kind.strip_prefix("remote:") {
+            players.push(Box::new(RemotePlayer::listen(&network::address(address), grace)?));
+            continue;
         }
-        // Else if the first character is a letter and the second is a number...
-        else if pos.chars().nth(1).unwrap().is_numeric() && pos.chars().nth(0).unwrap().is_alphabetic() {
-            // Set the number index from the second char
-            *num = pos.chars().nth(1).unwrap() as usize - 49;
-            // Set the letter index from the first char
-            *letter = pos.chars().nth(0).unwrap().to_ascii_uppercase() as usize - 'A' as usize;
-            is_valid = true; // Is a valid input
+        match kind.to_lowercase().as_str() {
+            "human" if tui => players.push(Box::new(TuiPlayer::new())),
+            "human" => players.push(Box::new(HumanPlayer::new())),
+            "computer" => players.push(Box::new(ComputerPlayer)),
+            _ => return Err(format!("Unknown player kind \"{}\"", kind)),
         }
     }
-    return is_valid; // Return whether the input was valid or not
+    let o = players.pop().unwrap();     // Exactly two players were made above
+    let x = players.pop().unwrap();
+    Ok([x, o])
 }
 
 /***********************************************************
- * TRY_CAPTURE
+ * OTHELLO
  ***********************************************************
- * Try to capture the opponent's piece[s]. If it does, then
- * return true, otherwise return false.
+ * Play the game of Othello. Starts by diplaying the game
+ * board and then asking player one ("X") for their move.
+ * Turns alternate unless a players turn is skipped, and the
+ * game ends once neither player can play, a player
+ * resigns or the players agree to a draw over the
+ * network. It then displays the winner (or that it's a tie)
+ * and the player's scores, tells both players the game is
+ * over and returns back the the main() function.
+ *
+ * Every move is added to the game record, which may
+ * already hold moves when a saved game is being resumed.
+ * If save names a file the record is written there after
+ * each move.
 ***********************************************************/
-fn try_capture(num: usize, letter: usize, board: &mut [[&str; SIZE]; SIZE], is_player_one: bool) -> bool {
-    let mut is_valid = false;       // If the location on the board is a valid capture location it will become true
-    let p_piece: &str;              // Holds the current player's piece
-    let o_piece: &str;              // Holds the current opponent's piece
-    if board[letter][num] != " " {  // If the location isn't empty, return to the previous function
-        return is_valid;            // is_valid is false here
-    }
-    if is_player_one {              // if current player is player one...
-        p_piece = _X;               // Player piece is "X"
-        o_piece = _O;               // Opponent piece is "O"
-    }
-    else {                          // Else if current player isn't player one...
-        p_piece = _O;               // Player piece is "O"
-        o_piece = _X                // Opponent piece is "X"
-    }
+fn othello(game: &mut Game, players: &mut [Box<dyn Player>; 2], save: Option<&str>) {
+    // Where the game stands: the board, who is to move,
+    // whether the last player was skipped and who resigned
+    let (mut board, mut is_player_one, mut was_skipped, mut resigned) = resume(game, players);
+    let mut playing = resigned.is_none();   // True if still playing
+    let mut drawn = false;                  // True if the players agreed to a draw
+    let mut shown = board.clone();          // The board last displayed, to highlight what changed
 
-    if num > 0 {                                    // Check all situations where the number index > 0
-        if check(num, letter, 0, 0, -1, 0,
-                 board, p_piece, o_piece) {         // Check left (if letter is != 0)
-            is_valid = true;                        // Capture successful
-        }
-        if check(num, letter, 0, SIZE - 1, -1, 0,
-                 board, p_piece, o_piece) {         // Check left again (in case letter is == 0)
-            is_valid = true;                        // Capture successful
-        }
-        if letter > 0 {                             // If letter > 0...
-            if check(num, letter, 0, 0, -1, -1,
-                     board, p_piece, o_piece) {     // Check left-up diagonal
-                is_valid = true;                    // Capture successful
+    // While the game isn't over keep playing
+    while playing {
+        let current = if is_player_one { 0 } else { 1 };   // Index of the current player
+        let opponent = 1 - current;                         // Index of the other player
+        let piece = if is_player_one { _X } else { _O };    // The current player's piece
+
+        // Find every location the current player can place
+        let legal = legal_moves(&board, is_player_one);
+
+        if !was_skipped {   // Only display the board once if the last player was skipped
+            display_highlighted(&board, &Highlights::between(&shown, &board, &legal));
+            shown = board.clone();
+            for said in players.iter().flat_map(|p| p.said()) {    // The latest chat, under the board
+                println!("  {}", said);
             }
         }
-        if letter < SIZE - 1 {                      // If letter < SIZE - 1...
-            if check(num, letter, 0, SIZE - 1, -1, 1,
-                     board, p_piece, o_piece) {     // Check left-down diagonal
-                is_valid = true;                    // Capture successful
+
+        // If the current player can take their turn then they do so
+        if !legal.is_empty() {
+            if was_skipped { // If the last player was skipped inform the users
+                println!("Next Player's turn was Skipped!\n\n");
+                was_skipped = false; // The current player can take their turn, so reset to false
             }
-        }
-    }
-    if num < SIZE - 1 {                             // Check all situations where the number index < SIZE - 1
-        if check(num, letter, SIZE - 1, 0, 1, 0,
-                 board, p_piece, o_piece) {         // Check right (if letter != 0)
-            is_valid = true;                        // Capture successful
-        }
-        if check(num, letter, SIZE - 1, SIZE - 1, 1, 0,
-                 board, p_piece, o_piece) {         // Check right again (in case letter == 0)
-            is_valid = true;                        // Capture successful
-        }
-        if letter > 0 {                             // If letter > 0...
-            if check(num, letter, SIZE - 1, 0, 1, -1,
-                     board, p_piece, o_piece) {     // Check right-up diagonal
-                is_valid = true;                    // Capture successful
+            println!("Player {}'s turn.", piece); // Inform the users who's turn it is
+
+            // Ask the current player for their move until they give a legal one
+            let started = Instant::now();   // Time the move for the player's clock
+            let mv = loop {
+                match players[current].choose_move(&board, is_player_one, &legal) {
+                    Move::Place(nl) if !legal.contains(&nl) => {
+                        println!("{} is not a legal move.", coordinates(nl));
+                    }
+                    Move::Pass => println!("You can't pass while you can place."),
+                    Move::Save(path) => match game.save(&path) {
+                        Ok(()) => println!("Game saved to {}.", path),
+                        Err(message) => println!("{}", message),
+                    },
+                    Move::Diagram(path) => match diagram::save(game, &[game.moves.len()],
+                                                               diagram::Marks::default(), 48, &path) {
+                        Ok(()) => println!("Board drawn to {}.", path),
+                        Err(message) => println!("{}", message),
+                    },
+                    Move::Chat(line) => {
+                        if !players[opponent].chat(&line) {
+                            println!("Chat is only for games against someone over the network.");
+                        }
+                        drawn = players.iter_mut().any(|p| p.agreed_draw());
+                        if drawn {
+                            break Move::Resign;             // Not played: the game is drawn
+                        }
+                    }
+                    mv => break mv,
+                }
+            };
+            drawn = drawn || players.iter_mut().any(|p| p.agreed_draw());
+            if drawn {                                      // Whatever the move, the game is drawn
+                break;
             }
-        }
-        if letter < SIZE - 1 {                      // If letter < SIZE -1...
-            if check(num, letter, SIZE - 1, SIZE - 1, 1, 1,
-                     board, p_piece, o_piece) {     // Check right-down diagonal
-                is_valid = true;                    // Capture successful
+            match mv {
+                Move::Place(nl) => {
+                    place(&mut board, nl, is_player_one);   // Current player takes their turn
+                    println!("Player {} plays {}.", piece, coordinates(nl));
+                }
+                Move::Load(path) => {
+                    match Game::load(&path) {   // Carry on from the loaded game instead
+                        Ok(loaded) => {
+                            *game = loaded;
+                            println!("Game loaded from {}.", path);
+                            let state = resume(game, players);
+                            board = state.0;
+                            is_player_one = state.1;
+                            was_skipped = state.2;
+                            resigned = state.3;
+                            playing = resigned.is_none();
+                            shown = board.clone();
+                        }
+                        Err(message) => println!("{}", message),
+                    }
+                    continue;                               // Nobody has moved yet
+                }
+                _ => {                                      // The current player resigned
+                    println!("Player {} resigns.", piece);
+                    resigned = Some(piece);
+                    playing = false;                        // Game is over
+                }
             }
+            let time = started.elapsed().saturating_sub(players[current].time_away());
+            game.play(mv.clone(), is_player_one, time);
+            players[opponent].opponent_moved(&board, mv);   // Tell the opponent what happened
         }
-    }                                               // Only checks still needed are up and down (with no left or right)
-    if letter > 0 {                                 // If letter > 0...
-        if check(num, letter, 0, 0, 0, -1,
-                 board, p_piece, o_piece) {         // Check up (if number != 0)
-            is_valid = true;                        // Capture successful
+        else { // If the current player can't take their turn then...
+            if was_skipped { // If the last player was skipped than neither can play (game is over)
+                playing = false; // Game is over
+            }
+            else {
+                game.play(Move::Pass, is_player_one, Duration::from_secs(0));
+                players[opponent].opponent_moved(&board, Move::Pass); // Tell the opponent about the pass
+            }
+            was_skipped = true; // Current player is skipped
         }
-        if check(num, letter, SIZE - 1, 0, 0, -1,
-                 board, p_piece, o_piece) {         // Check up again (in case number == 0)
-            is_valid = true;                        // Capture successful
+        if let Some(path) = save {  // Keep the saved game up to date
+            if let Err(message) = game.save(path) {
+                println!("{}", message);
+            }
         }
+        is_player_one = !is_player_one; // Switch who's turn it is
     }
-    if letter < SIZE -1 {                           // If letter < SIZE - 1
-        if check(num, letter, 0, SIZE - 1, 0, 1,
-                 board, p_piece, o_piece) {         // Check down (if number != 0)
-            is_valid = true;                        // Capture successful
-        }
-        if check(num, letter, SIZE - 1, SIZE - 1, 0, 1,
-                 board, p_piece, o_piece) {         // Check down again (in case number == 0)
-            is_valid = true;                        // Capture successful
+
+    let (x_count, o_count) = count_pieces(&board);  // Final count of each player's pieces
+    if drawn {                                      // An agreed draw decides the game regardless of the count
+        game.info.result = Some("draw agreed".to_string());
+        if let Some(path) = save {
+            if let Err(message) = game.save(path) {
+                println!("{}", message);
+            }
         }
+        println!("--------------");
+        println!("It's a draw!");
+        println!("--------------\n");
+    }
+    else if let Some(piece) = resigned {                 // A resignation decides the game regardless of the count
+        let winner = if piece == _X { _O } else { _X };
+        println!("--------------");
+        println!("Player {} wins!", winner);
+        println!("--------------\n");
     }
-    return is_valid;                                // Return true if capture was successful
+    // Determine who won, or if there is a tie
+    else if x_count > o_count { // "X" wins!
+        println!("--------------");
+        println!("Player X wins!");
+        println!("--------------\n");
+        println!("Player X score: {}", x_count); // Display "X" count
+        println!("Player O score: {}", o_count); // Display "O" count
+    }
+    else if x_count < o_count { // "O" wins!
+        println!("--------------");
+        println!("Player O wins!");
+        println!("--------------\n");
+        println!("Player O score: {}", o_count); // Display "O" count
+        println!("Player X score: {}", x_count); // Display "X" count
+    }
+    else { // It's a tie! ...maybe play again?
+        println!("--------------");
+        println!("It's a tie!");
+        println!("--------------\n");
+        println!("Both Player's scored: {}", x_count); // Display the shared count
+    }
+    players[0].game_over(&board, resigned);  // Tell both players the game has ended
+    players[1].game_over(&board, resigned);
 }
 
 /***********************************************************
- * CAN_TAKE_TURN
+ * RESUME
  ***********************************************************
- * Checks every location on the board for empty (" ")
- * locations. When a location is found it check if it's
- * possible to capture any of the current opponent's
- * pieces. 
+ * Get ready to play on from the end of a game record.
+ * Tells both players a game is starting and returns the
+ * board, whether "X" is to move, whether the last player
+ * was skipped, and the piece of a player who resigned.
 ***********************************************************/
-fn can_take_turn(board: &mut [[&str; SIZE]; SIZE], is_player_one: bool) -> bool {
-    let p_piece: &str;      // Holds the current player's piece
-    let o_piece: &str;      // Holds the current opponent's piece
-    let it_board = *board;  // Create a copy of the board for iteration (mutable/immutable conflict)
-    if is_player_one {      // If current player is player one...
-        p_piece = _X;       // Player piece is "X"
-        o_piece = _O;       // Opponent piece is "O"
+fn resume(game: &Game, players: &mut [Box<dyn Player>; 2]) -> (Board, bool, bool, Option<&'static str>) {
+    let (start, _) = game.start_position(); // The board the game starts from
+    players[0].new_game(&start, true);      // Player X plays "X"
+    players[1].new_game(&start, false);     // Player O plays "O"
+
+    // Records are checked when they are loaded, so replaying can't fail
+    let (board, is_player_one) = game.position().unwrap();
+    let was_skipped = game.moves.last().map(|t| &t.mv) == Some(&Move::Pass);
+    let mut resigned = None;
+    if game.moves.last().map(|t| &t.mv) == Some(&Move::Resign) {
+        resigned = Some(if is_player_one { _O } else { _X });  // The player before the one to move
     }
-    else {                  // Otherwise...
-        p_piece = _O;       // Player piece is "O"
-        o_piece = _X;       // Opponent piece is "X"
+    (board, is_player_one, was_skipped, resigned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use othello::{NumLet, get_coordinates};
+
+    // Everything a player was told, in order
+    type Log = Rc<RefCell<Vec<String>>>;
+
+    // A player that makes the moves it is given, then the
+    // first legal move, and writes down what it is told. It
+    // agrees to any draw offered.
+    struct Scripted {
+        moves: Vec<Move>,
+        log: Log,
+        offered: bool
     }
 
-    // Iterate through each row, and each column in each row
-    for (i1, row) in it_board.iter().enumerate() {
-        for (i2, column) in row.iter().enumerate() {
-            if column as &str == " " {                      // If location is empty (" ")...
-                let letter = i1;                            // Set the letter index to the row iterator
-                let num = i2;                               // Set the number index to the column iterator
-                if num > 0 {                                // Check all situations where number index > 0
-                    if check(num, letter, 0, 0, -1, 0,
-                             board, p_piece, o_piece) {     // Check left (if letter != 0)
-                        return true;                        // Player can take their turn
-                    }
-                    if check(num, letter, 0, SIZE - 1, -1, 0,
-                             board, p_piece, o_piece) {     // Check left (in case letter == 0)
-                        return true;                        // Player can take their turn
-                    }
-                    if letter > 0 {                         // If letter > 0...
-                        if check(num, letter, 0, 0, -1, -1,
-                                 board, p_piece, o_piece) { // Check left-up
-                            return true;                    // Player can take their turn
-                        }
-                    }
-                    if letter < SIZE - 1 {                  // If letter < SIZE -1...
-                        if check(num, letter, 0, SIZE - 1, -1, 1,
-                                 board, p_piece, o_piece) { // Check left-down
-                            return true;                    // Player can take their turn
-                        }
-                    }
-                }
-                if num < SIZE - 1 {                         // Check all situations where the number index < SIZE -1
-                    if check(num, letter, SIZE - 1, 0, 1, 0,
-                             board, p_piece, o_piece) {     // Check right (if letter != 0)
-                        return true;                        // Player can take their turn
-                    }
-                    if check(num, letter, SIZE - 1, SIZE - 1, 1, 0,
-                             board, p_piece, o_piece) {     // Check right (in case letter == 0)
-                        return true;                        // Player can take their turn
-                    }
-                    if letter > 0 {                         // If letter > 0...
-                        if check(num, letter, SIZE - 1, 0, 1, -1,
-                                 board, p_piece, o_piece) { // Check right-up
-                            return true;                    // Player can take their turn
-                        }
-                    }
-                    if letter < SIZE - 1 {                  // If letter < SIZE - 1...
-                        if check(num, letter, SIZE - 1, SIZE - 1, 1, 1,
-                                 board, p_piece, o_piece) { // Check right-down
-                            return true;                    // Player can take their turn
-                        }
-                    }
-                }                                           // Only checks still needed are up and down (no left or right)
-                if letter > 0 {                             // If letter > 0...
-                    if check(num, letter, 0, 0, 0, -1,
-                             board, p_piece, o_piece) {     // Check up (if number != 0)
-                        return true;                        // Player can take their turn
-                    }
-                    if check(num, letter, SIZE - 1, 0, 0, -1,
-                             board, p_piece, o_piece) {     // Check up (in case number == 0)
-                        return true;                        // Player can take their turn
-                    }
-                }
-                if letter < SIZE -1 {                       // If letter < SIZE - 1...
-                    if check(num, letter, 0, SIZE - 1, 0, 1,
-                             board, p_piece, o_piece) {     // Check down (if number != 0)
-                        return true;                        // Player can take their turn
-                    }
-                    if check(num, letter, SIZE - 1, SIZE - 1, 0, 1,
-                             board, p_piece, o_piece) {     // Check down (in case number == 0)
-                        return true;                        // Player can take their turn
-                    }
-                }
+    // A scripted player and its log
+    fn scripted(moves: &[&str]) -> (Box<dyn Player>, Log) {
+        let log = Log::default();
+        let moves = moves.iter().map(|text| match *text {
+            "resign" => Move::Resign,
+            "pass" => Move::Pass,
+            "draw" => Move::Chat("offer draw".to_string()),
+            _ => {
+                let (mut num, mut letter) = (0, 0);
+                assert!(get_coordinates(&mut num, &mut letter, text.to_string()));
+                Move::Place(NumLet { n: num, l: letter })
             }
-        }
+        }).collect();
+        (Box::new(Scripted { moves, log: Rc::clone(&log), offered: false }), log)
     }
-    return false;                                           // Failed all checks, the player can't take their turn
-}
 
-/***********************************************************
- * CHECK
- ***********************************************************
- * Checks if a capture can take place, and if it can it
- * captures all the relevant pieces. This is done by taking
- * in both indexes and their iteration value (-1, 0, or 1)
- * and looping through the line until the board edge (limit)
- * is reached, an empty space is reached, or one of the
- * current player's pieces are reached. If any of the
- * opponent's pieces were found they are captured and
- * true is returned. In all other cases false is returned.
-***********************************************************/
-fn check(num: usize, letter: usize,
-         limit_n: usize, limit_l: usize,
-         it_n: i8, it_l: i8,
-         board: &mut [[&str; SIZE]; SIZE],
-         p_piece: &str, o_piece: &str)
-         -> bool {
-    
-    let mut n = num;                                // Copy number index for iteration
-    let mut l = letter;                             // Copy letter index for iteration
-    let mut v = Vec::new();                         // Create a Vector to hold potential capturable pieces
-    while n != limit_n && l != limit_l {            // Until the edge of the board is reached...
-        n = ((n as i8) + it_n) as usize;            // Iterate the number index (usize can't iterate without casting)
-        l = ((l as i8) + it_l) as usize;            // Iterate the letter index (usize can't iterate without casting)
-        if board[l][n] == o_piece {                 // If an opponent's piece is found...
-            let nl = NumLet {                       // Create a NumLet struct to hold the location and...
-                n:n,    // Number index
-                l:l     // Letter index
+    impl Player for Scripted {
+        fn new_game(&mut self, _board: &Board, is_player_one: bool) {
+            self.log.borrow_mut().push(format!("new {}", if is_player_one { _X } else { _O }));
+        }
+
+        fn choose_move(&mut self, _board: &Board,
+                       _is_player_one: bool, legal: &[NumLet]) -> Move {
+            if self.moves.is_empty() { Move::Place(legal[0]) } else { self.moves.remove(0) }
+        }
+
+        fn opponent_moved(&mut self, _board: &Board, mv: Move) {
+            let text = match mv {
+                Move::Place(nl) => coordinates(nl),
+                Move::Pass => "pass".to_string(),
+                Move::Resign => "resign".to_string(),
+                Move::Save(_) | Move::Load(_) | Move::Diagram(_) | Move::Chat(_) => unreachable!("not a move"),
             };
-            v.push(nl);                             // Store the struct in the Vector
+            self.log.borrow_mut().push(text);
         }
-        else if board[l][n] == p_piece {            // If one of the current player's pieces are found...
-            if v.len() != 0 {                       // Check if the Vector holds any locations. If it does...
-                while v.len() != 0 {                // Loop through the Vector until it is empty
-                    let pos = v.remove(0);          // Remove and store the location from the Vector
-                    if p_piece == _X {              // If the current player is "X"...
-                        board[pos.l][pos.n] = _X;   // Change that location to "X"
-                    }
-                    else {                          // Otherwise the player is "O", so...
-                        board[pos.l][pos.n] = _O;   // Change that location to "O"
-                    }
-                }
-                return true;                        // Once the Vetor is empty return true (you did capture)
-            }
-            return false;                           // If the Vector was empty then return false (you didn't capture)
+
+        fn game_over(&mut self, board: &Board, resigned: Option<&'static str>) {
+            let (x_count, o_count) = count_pieces(board);
+            self.log.borrow_mut().push(format!("over {}-{} {:?}", x_count, o_count, resigned));
+        }
+
+        fn chat(&mut self, line: &str) -> bool {
+            self.log.borrow_mut().push(line.to_string());
+            self.offered |= line == "offer draw";
+            true
         }
-        else {                                      // If it's not the current player's piece or the opponent's piece...
-            return false;                           // It's an empty location, so return false
+
+        fn agreed_draw(&mut self) -> bool {
+            std::mem::take(&mut self.offered)
         }
     }
-    return false;                                   // If you reach the edge of the board return false
-}
\ No newline at end of file
+
+    // Play a game from the usual start
+    fn play(players: [Box<dyn Player>; 2]) {
+        let mut game = Game::new(4, ["test".to_string(), "test".to_string()]);
+        othello(&mut game, &mut { players }, None);
+    }
+
+    #[test]
+    fn players_are_told_every_move_and_pass() {
+        let (x, x_log) = scripted(&["C4", "D3", "A3", "D1", "B1"]);
+        let (o, o_log) = scripted(&["D4", "B4", "D2", "C1", "A4", "A1", "A2"]);
+        play([x, o]);
+        assert_eq!(*x_log.borrow(), ["new X", "D4", "B4", "D2", "C1", "A4", "A1", "A2", "over 3-13 None"]);
+        assert_eq!(*o_log.borrow(), ["new O", "C4", "D3", "A3", "D1", "B1", "pass", "pass", "pass",
+                                     "over 3-13 None"]);
+    }
+
+    #[test]
+    fn illegal_moves_and_passes_are_asked_again() {
+        let (x, _) = scripted(&["A1", "pass", "D4", "C4", "resign"]);
+        let (o, o_log) = scripted(&[]);
+        play([x, o]);
+        assert_eq!(o_log.borrow()[1], "C4");
+        assert_eq!(o_log.borrow()[2..], ["resign", "over 3-3 Some(\"X\")"]);
+    }
+
+    #[test]
+    fn resigning_ends_the_game() {
+        let (x, x_log) = scripted(&["resign"]);
+        let (o, o_log) = scripted(&[]);
+        play([x, o]);
+        assert_eq!(*x_log.borrow(), ["new X", "over 2-2 Some(\"X\")"]);
+        assert_eq!(*o_log.borrow(), ["new O", "resign", "over 2-2 Some(\"X\")"]);
+    }
+
+    #[test]
+    fn agreed_draws_end_the_game() {
+        let (x, x_log) = scripted(&["C4", "draw", "resign"]);
+        let (o, o_log) = scripted(&["B4"]);
+        let mut game = Game::new(4, ["test".to_string(), "test".to_string()]);
+        othello(&mut game, &mut [x, o], None);
+        assert_eq!(game.moves.len(), 2);
+        assert_eq!(game.info.result.as_deref(), Some("draw agreed"));
+        assert_eq!(*x_log.borrow(), ["new X", "B4", "over 3-3 None"]);
+        assert_eq!(*o_log.borrow(), ["new O", "C4", "offer draw", "over 3-3 None"]);
+    }
+
+    #[test]
+    fn computers_play_to_the_end() {
+        let (o, o_log) = scripted(&[]);
+        play([Box::new(ComputerPlayer), o]);
+        let log = o_log.borrow();
+        let over = log.last().unwrap();
+        assert!(over.starts_with("over ") && over.ends_with(" None"), "{}", over);
+        assert!(log[1..log.len() - 1].iter().all(|mv| mv == "pass" || mv.len() == 2));
+    }
+}
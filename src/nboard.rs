@@ -0,0 +1,238 @@
+/***********************************************************
+ * NBOARD ENGINE
+ ***********************************************************
+ * With "--nboard" the program runs as an engine for NBoard
+ * and other Othello GUIs that speak the NBoard protocol.
+ * The GUI writes commands to stdin and reads the answers
+ * from stdout, one line each:
+ *     nboard <version>    Answered with "set myname Othello".
+ *     set game <ggf>      The game so far, as a GGF record.
+ *     set depth <n>       Accepted and ignored, as is
+ *     set contempt <n>    "set contempt".
+ *     move <square>[/..]  A move was played in the game.
+ *     go                  Answered with "=== <square>", the
+ *                         computer player's move.
+ *     hint <n>            Answered with a "search <square>
+ *                         <eval> 0 1" line for each of the
+ *                         n best moves.
+ *     ping <n>            Answered with "pong <n>".
+ *     learn               Answered with "learned".
+ * Squares are the column letter then the row number (for
+ * example "F5"), and "PA" is a pass. Problems with a
+ * command are reported with a "status" line.
+***********************************************************/
+
+use std::io::{self, BufRead, Write};
+use crate::player::{ComputerPlayer, Move, Player};
+use crate::ggf::{parse_game, parse_square, square_name};
+use crate::{Board, NumLet, create_board, legal_moves, place};
+
+/***********************************************************
+ * NBOARD_GAME
+ ***********************************************************
+ * The position the GUI has set up: the board and whose
+ * turn it is.
+***********************************************************/
+struct NBoardGame {
+    board: Board,           // The current board
+    is_player_one: bool     // True if "X" (black) is to move
+}
+
+/***********************************************************
+ * RUN
+ ***********************************************************
+ * Answer the GUI's commands until it closes stdin.
+***********************************************************/
+pub fn run() {
+    let mut board = Board::new(8);          // NBoard starts with the standard game
+    create_board(&mut board);
+    let mut game = NBoardGame { board, is_player_one: true };
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    for line in stdin.lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        for answer in command(&mut game, line.trim()) {
+            let _ = writeln!(out, "{}", answer);
+        }
+        let _ = out.flush();                // The GUI waits for each answer
+    }
+}
+
+/***********************************************************
+ * COMMAND
+ ***********************************************************
+ * Carry out one command and return the lines to answer it
+ * with. Unknown commands are ignored, as the protocol asks.
+***********************************************************/
+fn command(game: &mut NBoardGame, line: &str) -> Vec<String> {
+    let mut parts = line.splitn(2, ' ');
+    let name = parts.next().unwrap_or("");
+    let rest = parts.next().unwrap_or("").trim();
+    match name {
+        "nboard" => vec!["set myname Othello".to_string()],
+        "set" => {
+            let mut parts = rest.splitn(2, ' ');
+            match (parts.next(), parts.next()) {
+                (Some("game"), Some(ggf)) => match parse_game(ggf).and_then(|g| g.position()) {
+                    Ok((board, is_player_one)) => {
+                        *game = NBoardGame { board, is_player_one };
+                        Vec::new()
+                    }
+                    Err(message) => vec![format!("status {}", message)],
+                },
+                _ => Vec::new(),            // Depth and contempt don't change anything
+            }
+        }
+        "move" => {
+            let square = rest.split('/').next().unwrap_or("");  // Drop the eval and time
+            match play(game, square) {
+                Ok(()) => Vec::new(),
+                Err(message) => vec![format!("status {}", message)],
+            }
+        }
+        "go" => {
+            let legal = legal_moves(&game.board, game.is_player_one);
+            if legal.is_empty() {
+                return vec!["=== PA".to_string()];
+            }
+            match ComputerPlayer.choose_move(&game.board, game.is_player_one, &legal) {
+                Move::Place(nl) => vec![format!("=== {}", square_name(nl))],
+                _ => vec!["=== PA".to_string()],
+            }
+        }
+        "hint" => {
+            let count = rest.parse::<usize>().unwrap_or(1);
+            let mut scored: Vec<(NumLet, i32)> = legal_moves(&game.board, game.is_player_one)
+                .into_iter()
+                .map(|nl| (nl, ComputerPlayer::score(&game.board, nl, game.is_player_one)))
+                .collect();
+            scored.sort_by_key(|s| std::cmp::Reverse(s.1));   // Best moves first
+            scored.iter()
+                .take(count)
+                .map(|(nl, score)| format!("search {} {} 0 1", square_name(*nl), score))
+                .collect()
+        }
+        "ping" => vec![format!("pong {}", rest)],
+        "learn" => vec!["learned".to_string()],
+        _ => Vec::new(),
+    }
+}
+
+/***********************************************************
+ * PLAY
+ ***********************************************************
+ * Play a move in the game. If the player to move can't
+ * place, the move is taken to be the other player's after
+ * an unannounced pass.
+***********************************************************/
+fn play(game: &mut NBoardGame, square: &str) -> Result<(), String> {
+    if square.eq_ignore_ascii_case("pa") || square.eq_ignore_ascii_case("pass") {
+        game.is_player_one = !game.is_player_one;
+        return Ok(());
+    }
+    let nl = match parse_square(square, game.board.size()) {
+        Some(nl) => nl,
+        None => return Err(format!("Bad square \"{}\"", square)),
+    };
+    if legal_moves(&game.board, game.is_player_one).is_empty() {
+        game.is_player_one = !game.is_player_one;   // The player to move had to pass
+    }
+    if !place(&mut game.board, nl, game.is_player_one) {
+        return Err(format!("Illegal move \"{}\"", square));
+    }
+    game.is_player_one = !game.is_player_one;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The standard game, "X" (black) to move
+    fn start() -> NBoardGame {
+        let mut board = Board::new(8);
+        create_board(&mut board);
+        NBoardGame { board, is_player_one: true }
+    }
+
+    // A GGF record of the standard game after the moves
+    fn ggf(moves: &str) -> String {
+        let cells = format!("{}O*{}*O{}", "-".repeat(27), "-".repeat(6), "-".repeat(27));
+        format!("(;GM[Othello]TY[8]BO[8 {} *]{};)", cells, moves)
+    }
+
+    #[test]
+    fn the_engine_introduces_itself_and_answers_pings() {
+        let mut game = start();
+        assert_eq!(command(&mut game, "nboard 2"), ["set myname Othello"]);
+        assert_eq!(command(&mut game, "ping 7"), ["pong 7"]);
+        assert_eq!(command(&mut game, "learn"), ["learned"]);
+        assert!(command(&mut game, "set depth 12").is_empty());
+        assert!(command(&mut game, "set contempt 0").is_empty());
+        assert!(command(&mut game, "fly away").is_empty());
+    }
+
+    #[test]
+    fn set_game_replaces_the_position() {
+        let mut game = start();
+        assert!(command(&mut game, &format!("set game {}", ggf("B[f5]W[f6]"))).is_empty());
+        let mut expected = start();
+        play(&mut expected, "F5").unwrap();
+        play(&mut expected, "F6").unwrap();
+        assert_eq!(game.board, expected.board);
+        assert!(game.is_player_one);
+
+        let answer = command(&mut game, &format!("set game {}", ggf("B[a1]")));
+        assert_eq!(answer.len(), 1);
+        assert!(answer[0].starts_with("status "), "{}", answer[0]);
+        assert_eq!(game.board, expected.board);     // Bad games change nothing
+    }
+
+    #[test]
+    fn moves_are_played_or_reported() {
+        let mut game = start();
+        assert!(command(&mut game, "move F5/0.5/2.1").is_empty());
+        assert_eq!(game.board[4][5], "X");
+        assert!(!game.is_player_one);
+        assert_eq!(command(&mut game, "move A1"), ["status Illegal move \"A1\""]);
+        assert_eq!(command(&mut game, "move Z9"), ["status Bad square \"Z9\""]);
+        assert!(command(&mut game, "move PA").is_empty());
+        assert!(game.is_player_one);
+    }
+
+    #[test]
+    fn go_answers_a_legal_move() {
+        let mut game = start();
+        command(&mut game, "move F5");
+        let answer = command(&mut game, "go");
+        let legal: Vec<String> = legal_moves(&game.board, false).into_iter()
+            .map(|nl| format!("=== {}", square_name(nl))).collect();
+        assert_eq!(answer.len(), 1);
+        assert!(legal.contains(&answer[0]), "{}", answer[0]);
+    }
+
+    #[test]
+    fn go_passes_when_there_is_no_move() {
+        let mut game = NBoardGame { board: Board::new(8), is_player_one: true };
+        assert_eq!(command(&mut game, "go"), ["=== PA"]);
+    }
+
+    #[test]
+    fn hints_are_the_best_moves_first() {
+        let mut game = start();
+        let hints = command(&mut game, "hint 2");
+        assert_eq!(hints.len(), 2);
+        let scores: Vec<i32> = hints.iter().map(|line| {
+            let parts: Vec<&str> = line.split(' ').collect();
+            assert_eq!((parts[0], &parts[3..]), ("search", &["0", "1"][..]));
+            assert!(parse_square(parts[1], 8).is_some());
+            parts[2].parse().unwrap()
+        }).collect();
+        assert!(scores[0] >= scores[1]);
+        assert_eq!(command(&mut game, "hint 10").len(), 4);    // Only four moves at the start
+    }
+}
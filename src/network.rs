@@ -0,0 +1,1583 @@
+/***********************************************************
+ * NETWORK PLAY
+ ***********************************************************
+ * Two people can play from different machines: one runs
+ * the game with "--host PORT" and waits, the other runs
+ * "--join HOST:PORT". The host's program referees: it
+ * keeps the game record, checks every move against the
+ * rules and plays the joining player through a
+ * RemotePlayer, exactly like any other Player. Each side
+ * sees the board on their own terminal and only types
+ * their own moves.
+ *
+ * The connection carries lines of text. The host sends
+ * the engine protocol (see engine.rs) plus two lines:
+ *     new <size> <X|O>    A new game; the joining player
+ *                         plays the given piece. Answered
+ *                         with "ready".
+ *     position <cells> <X|O>
+ *                         The board, whenever it changes.
+ *     go <milliseconds>   The joining player's turn (the
+ *                         time is 0, there is no limit).
+ *                         Answered with "move <location>"
+ *                         or "move resign".
+ *     illegal <location>  The move answered wasn't legal;
+ *                         the position and "go" follow
+ *                         again.
+ *     opponent <location|pass|resign>
+ *                         What the host's player did.
+ *     over <message>      The game has ended, and how.
+ *     quit                The host is finished.
+ *     lobby               Back to the server's lobby.
+ *     token <token> <seconds>
+ *                         How to come back if the
+ *                         connection drops, and how long
+ *                         the game waits.
+ *     resumed <size> <X|O>
+ *     history <X|O>:<location|pass|resign>...
+ *                         Sent to a player who has come
+ *                         back, followed by the position;
+ *                         a move they owed is asked for
+ *                         again.
+ * A player whose connection drops has the grace period
+ * ("--grace", 60 seconds unless set, 0 to turn it off) to
+ * connect again and send "resume <token>" as their first
+ * line, answered with "ok" or "error <message>". The game
+ * waits for them, without the time counting on their
+ * clock; if they don't come back in time they forfeit.
+ * The joining program reconnects by itself, or can be
+ * started again with "--resume TOKEN".
+ *
+ * Either player can chat with the other during the game,
+ * on the same connection, and offer a draw or a rematch
+ * (see CHAT); the host's player types "say" and a message,
+ * "draw", "accept" or "decline" at the move prompt, and
+ * offers a rematch by playing again.
+ *
+ * The same protocol is used between the multi-game server
+ * (see server.rs) and the players connected to it with
+ * "--connect", with "lobby" sent once a game is over to
+ * go back to choosing a game.
+***********************************************************/
+
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::player::{Move, Player};
+use crate::position::{parse_position, position_string};
+use crate::accessible;
+use crate::digest::new_token;
+use crate::color::Highlights;
+use crate::{Board, NumLet, _X, _O, coordinates, count_pieces, display_highlighted, get_coordinates,
+            legal_moves, place};
+use std::io;
+
+/***********************************************************
+ * ADDRESS
+ ***********************************************************
+ * The address to listen on for "--host": a port number on
+ * its own means every network interface of the machine.
+***********************************************************/
+pub fn address(text: &str) -> String {
+    if text.chars().all(|c| c.is_ascii_digit()) {
+        format!("0.0.0.0:{}", text)
+    }
+    else {
+        text.to_string()
+    }
+}
+
+/***********************************************************
+ * CONNECTION
+ ***********************************************************
+ * A connection to another program that lines of text are
+ * sent and read over. A thread reads the lines as they
+ * arrive, so chat (see CHAT) can be passed on at any time,
+ * even while nobody is waiting for a line.
+***********************************************************/
+pub struct Connection {
+    stream: TcpStream,                  // The socket, to close or ask about
+    writer: Writer,                     // Where lines are written
+    lines: Receiver<String>,            // The lines read, in order
+    early: VecDeque<String>,            // Lines taken early by closed()
+    talk: Tap                           // Where chat goes instead, while a game listens
+}
+
+// Where a connection's chat lines are sent while a game is
+// listening for them
+type Tap = Arc<Mutex<Option<Sender<String>>>>;
+
+// Where a connection's lines are written, shared with the
+// chat so that lines written from two threads can't mix
+type Writer = Arc<Mutex<TcpStream>>;
+
+// What waiting for a line found
+pub enum Received {
+    Line(String),                       // A whole line, without its end
+    Nothing,                            // No line arrived in time
+    Closed                              // The connection has closed
+}
+
+impl Connection {
+    // Use an open stream, starting the thread that reads it
+    pub fn new(stream: TcpStream) -> io::Result<Connection> {
+        let reader = BufReader::new(stream.try_clone()?);
+        let (sender, lines) = channel();
+        let talk = Tap::default();
+        let tap = Arc::clone(&talk);
+        thread::spawn(move || {
+            for line in reader.lines() {
+                let line = match line {
+                    Ok(line) => line.trim_end().to_string(),
+                    Err(_) => break,
+                };
+                let word = line.split_whitespace().next().unwrap_or("");
+                let listener = if CHAT_WORDS.contains(&word) { tap.lock().unwrap().clone() } else { None };
+                let line = match listener {
+                    Some(listener) => match listener.send(line) {
+                        Ok(()) => continue,
+                        Err(e) => e.0,          // The game has stopped listening
+                    },
+                    None => line,
+                };
+                if sender.send(line).is_err() {
+                    break;                      // Nobody will read it
+                }
+            }
+        });
+        let writer = Arc::new(Mutex::new(stream.try_clone()?));
+        Ok(Connection { stream, writer, lines, early: VecDeque::new(), talk })
+    }
+
+    // Write a line
+    pub fn send(&mut self, line: &str) -> io::Result<()> {
+        write_line(&self.writer, line)
+    }
+
+    // Wait for a line, for no longer than wait if given
+    pub fn poll_line(&mut self, wait: Option<Duration>) -> Received {
+        if let Some(line) = self.early.pop_front() {
+            return Received::Line(line);
+        }
+        let line = match wait {
+            Some(wait) => self.lines.recv_timeout(wait).map_err(|e| e == RecvTimeoutError::Disconnected),
+            None => self.lines.recv().map_err(|_| true),
+        };
+        match line {
+            Ok(line) => Received::Line(line),
+            Err(true) => Received::Closed,
+            Err(false) => Received::Nothing,
+        }
+    }
+
+    // Read a line without its end; None once the
+    // connection has closed
+    pub fn read_line(&mut self) -> Option<String> {
+        match self.poll_line(None) {
+            Received::Line(line) => Some(line),
+            _ => None,
+        }
+    }
+
+    // True if the other end has closed the connection,
+    // found without waiting for a line
+    pub fn closed(&mut self) -> bool {
+        loop {
+            match self.lines.try_recv() {
+                Ok(line) => self.early.push_back(line),     // Kept for poll_line()
+                Err(TryRecvError::Empty) => return false,
+                Err(TryRecvError::Disconnected) => return true,
+            }
+        }
+    }
+
+    // Where the other end is
+    pub fn peer(&self) -> String {
+        self.stream.peer_addr().map(|a| a.to_string()).unwrap_or_else(|_| "?".to_string())
+    }
+
+    // Send the chat lines that arrive to listener, or stop
+    // with None
+    fn tap(&self, listener: Option<Sender<String>>) {
+        *self.talk.lock().unwrap() = listener;
+    }
+}
+
+// Write a line all at once, holding the writer so chat
+// from another thread waits for it to finish
+fn write_line(writer: &Writer, line: &str) -> io::Result<()> {
+    writer.lock().unwrap().write_all(format!("{}\n", line).as_bytes())
+}
+
+impl Drop for Connection {
+    // Close the connection, which ends the reading thread
+    fn drop(&mut self) {
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
+
+/***********************************************************
+ * SESSIONS
+ ***********************************************************
+ * The games waiting for players to come back, by token:
+ * where to send a player's new connection.
+***********************************************************/
+pub type Sessions = Arc<Mutex<HashMap<String, Sender<Connection>>>>;
+
+/***********************************************************
+ * RESUME
+ ***********************************************************
+ * Hand a connection that sent "resume <token>" to the game
+ * waiting for it. Gives the connection back if no game is
+ * waiting for the token.
+***********************************************************/
+pub fn resume(sessions: &Sessions, token: &str, mut connection: Connection) -> Result<(), Connection> {
+    let waiting = sessions.lock().unwrap().get(token).cloned();
+    match waiting {
+        Some(game) => {
+            let _ = connection.send("ok Welcome back.");
+            game.send(connection).map_err(|e| e.0)
+        }
+        None => Err(connection),
+    }
+}
+
+/***********************************************************
+ * SESSION
+ ***********************************************************
+ * How a RemotePlayer whose connection drops can come back.
+***********************************************************/
+struct Session {
+    token: String,                      // What they come back with
+    sessions: Sessions,                 // Where the token is kept
+    returning: Receiver<Connection>,    // Where their new connection arrives
+    grace: Duration                     // How long to wait for them
+}
+
+/***********************************************************
+ * CHAT
+ ***********************************************************
+ * What the two players of a game say to each other, passed
+ * on as soon as it arrives rather than when the referee
+ * next reads (see CONNECTION), and the offers they make.
+ * Each side is a connection, or the host's own player at
+ * the terminal, who is shown what arrives (the latest chat
+ * again under each board) and types at the move prompt
+ * (see RemotePlayer's chat()).
+ * A player sends:
+ *     chat <text>         To the other player.
+ *     shout <text>        To the other player and anyone
+ *                         watching (on a server).
+ *     offer <draw|rematch>
+ *     accept <draw|rematch>
+ *     decline <draw|rematch>
+ *                         Offer the other player a draw
+ *                         during the game or a rematch
+ *                         after it, or answer their offer.
+ * and is sent:
+ *     chat <name> <text>  What someone said.
+ *     offer <name> <draw|rematch>
+ *     declined <name> <draw|rematch>
+ *                         The other player's offer, or
+ *                         their answer to one.
+ *     agreed <draw|rematch>
+ *                         Both players want it.
+ *     rematch <seconds>   The game is over, and a rematch
+ *                         can be agreed for this long.
+ *     notice <text>       News from the referee, such as
+ *                         new ratings.
+ *     error <message>     What they sent was refused:
+ *                         a message longer than MAX_CHAT
+ *                         characters, or an offer that
+ *                         can't be made.
+***********************************************************/
+pub struct Chat {
+    sides: [Option<Writer>; 2],         // Where each player is written to
+    names: [String; 2],                 // What each player is called
+    spectators: Option<Spectators>,     // Where shouts go, if anyone can watch
+    offers: [Option<String>; 2],        // What each player has offered, until answered
+    agreed: Vec<String>,                // What both have agreed to, until acted on
+    declined: bool,                     // True once a rematch has been turned down
+    playing: bool,                      // True while a game is being played
+    terminal: Option<usize>,            // The side played at the terminal, if one is
+    muted: bool,                        // True if the terminal hides chat
+    said: VecDeque<String>              // The latest chat shown at the terminal
+}
+
+// Passes a line on to a game's spectators
+pub type Spectators = Box<dyn Fn(String) + Send>;
+
+// The longest message allowed, in characters
+pub const MAX_CHAT: usize = 200;
+
+// The lines a connection sends that are for the chat
+static CHAT_WORDS: [&str; 5] = ["chat", "shout", "offer", "accept", "decline"];
+
+// How long a connection has to answer "new" with "ready",
+// and a returning one to send "resume <token>"
+const READY: Duration = Duration::from_secs(30);
+const RESUME: Duration = Duration::from_secs(30);
+
+// How long the other player has to agree to a rematch the
+// host's player wants
+const REMATCH: Duration = Duration::from_secs(15);
+
+impl Chat {
+    /*******************************************************
+     * NEW / LISTEN
+     *******************************************************
+     * Chat between the named players, one of whom may be
+     * at the terminal (the host's own player), whose
+     * referee acts on the offers agreed to. Shouts go to
+     * spectators too, where there are any. Each connection is
+     * listened to for its side, again if it comes back.
+    *******************************************************/
+    pub fn new(names: [String; 2], terminal: Option<usize>, spectators: Option<Spectators>) -> Arc<Mutex<Chat>> {
+        Arc::new(Mutex::new(Chat { sides: [None, None], names, spectators, offers: [None, None],
+                                   agreed: Vec::new(), declined: false, playing: false, terminal,
+                                   muted: false, said: VecDeque::new() }))
+    }
+
+    pub fn listen(chat: &Arc<Mutex<Chat>>, side: usize, connection: &Connection) {
+        chat.lock().unwrap().sides[side] = Some(Arc::clone(&connection.writer));
+        let (listener, heard) = channel();
+        connection.tap(Some(listener));
+        let chat = Arc::clone(chat);
+        thread::spawn(move || {                 // Until the connection stops being listened to
+            for line in heard {
+                chat.lock().unwrap().heard(side, &line);
+            }
+        });
+    }
+
+    /*******************************************************
+     * HEARD
+     *******************************************************
+     * Pass on a line from one side, or refuse it.
+    *******************************************************/
+    pub fn heard(&mut self, side: usize, line: &str) {
+        let other = 1 - side;
+        let name = self.names[side].clone();
+        let mut parts = line.trim().splitn(2, ' ');
+        let word = parts.next().unwrap_or("");
+        let rest = parts.next().unwrap_or("").trim().to_string();
+        match word {
+            "chat" | "shout" if rest.chars().count() > MAX_CHAT => {
+                self.tell(side, &format!("error Messages can be at most {} characters.", MAX_CHAT));
+            }
+            "chat" | "shout" if !rest.is_empty() => {
+                let said = format!("chat {} {}", name, rest);
+                if word == "shout" {
+                    if let Some(spectators) = &self.spectators {
+                        spectators(said.clone());
+                    }
+                }
+                self.tell(other, &said);
+            }
+            "offer" | "accept" | "decline" if rest != "draw" && rest != "rematch" => {
+                self.tell(side, "error Only a draw or a rematch can be offered.");
+            }
+            "offer" | "accept" if rest == "draw" && !self.playing => {
+                self.tell(side, "error The game is already over.");
+            }
+            "offer" | "accept" if self.offers[other].as_ref() == Some(&rest) => self.agree(&rest),
+            "offer" => {
+                self.offers[side] = Some(rest.clone());
+                self.tell(other, &format!("offer {} {}", name, rest));
+            }
+            "accept" => self.tell(side, &format!("error Nobody has offered a {}.", rest)),
+            "decline" => {
+                if self.offers[other].as_ref() == Some(&rest) {
+                    self.offers[other] = None;
+                    self.tell(other, &format!("declined {} {}", name, rest));
+                }
+                self.declined |= rest == "rematch";
+            }
+            _ => {}
+        }
+    }
+
+    /*******************************************************
+     * AGREE / AGREED / START / STOP
+     *******************************************************
+     * Both players want the same thing: tell them, and keep
+     * it for the referee, who takes it with agreed(). A
+     * game starting or ending changes what can be offered.
+    *******************************************************/
+    fn agree(&mut self, what: &str) {
+        self.offers = [None, None];
+        self.agreed.push(what.to_string());
+        for side in 0..2 {
+            self.tell(side, &format!("agreed {}", what));
+        }
+    }
+
+    pub fn agreed(&mut self, what: &str) -> bool {
+        let found = self.agreed.iter().any(|a| a == what);
+        self.agreed.retain(|a| a != what);
+        found
+    }
+
+    pub fn start(&mut self) {
+        self.playing = true;
+        self.declined = false;
+        self.agreed.clear();
+    }
+
+    pub fn stop(&mut self) {
+        self.playing = false;
+        self.agreed.retain(|a| a != "draw");
+        for offer in self.offers.iter_mut() {
+            if offer.as_deref() == Some("draw") {
+                *offer = None;
+            }
+        }
+    }
+
+    /*******************************************************
+     * NOTICE / INVITE / CLOSE
+     *******************************************************
+     * Tell both players some news; tell them how long they
+     * have to agree to a rematch; and stop writing to
+     * anyone once the games are over.
+    *******************************************************/
+    pub fn notice(&mut self, text: &str) {
+        for side in 0..2 {
+            self.tell(side, &format!("notice {}", text));
+        }
+    }
+
+    pub fn invite(&mut self, wait: Duration) {
+        for side in 0..2 {
+            self.tell(side, &format!("rematch {}", wait.as_secs()));
+        }
+    }
+
+    pub fn close(&mut self) {
+        self.sides = [None, None];
+        self.spectators = None;
+    }
+
+    /*******************************************************
+     * WAIT_REMATCH
+     *******************************************************
+     * After a game, wait up to the given time for both
+     * players to agree to another. Returns false as soon
+     * as either turns it down.
+    *******************************************************/
+    pub fn wait_rematch(chat: &Mutex<Chat>, wait: Duration) -> bool {
+        let give_up = Instant::now() + wait;
+        while Instant::now() < give_up {
+            {
+                let mut chat = chat.lock().unwrap();
+                if chat.agreed("rematch") {
+                    return true;
+                }
+                if chat.declined {
+                    return false;
+                }
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        false
+    }
+
+    /*******************************************************
+     * TELL
+     *******************************************************
+     * Send a line to one side, or show it on the terminal.
+    *******************************************************/
+    fn tell(&mut self, side: usize, line: &str) {
+        if self.terminal == Some(side) {
+            let mut parts = line.splitn(3, ' ');
+            match (parts.next(), parts.next(), parts.next()) {
+                (Some("chat"), Some(name), Some(text)) if !self.muted => {
+                    println!("{}: {}", name, text);
+                    self.said.push_back(format!("{}: {}", name, text));
+                    if self.said.len() > CHAT_SHOWN {
+                        self.said.pop_front();
+                    }
+                }
+                (Some("offer"), Some(name), Some("draw")) => {
+                    println!("{} offers a draw. Type accept or decline.", name);
+                }
+                (Some("offer"), Some(name), Some(what)) => println!("{} offers a {}.", name, what),
+                (Some("declined"), Some(name), Some(what)) => println!("{} declines the {}.", name, what),
+                (Some("agreed"), Some(what), _) => println!("Both players agree to a {}.", what),
+                (Some("error"), _, _) => println!("{}", &line[6..]),
+                _ => {}
+            }
+        }
+        else if let Some(writer) = &self.sides[side] {
+            let _ = write_line(writer, line);
+        }
+    }
+}
+
+/***********************************************************
+ * REMOTE_PLAYER
+ ***********************************************************
+ * The referee's side of the connection to a player on
+ * another machine. Once the connection has dropped (and
+ * the player hasn't come back) the player forfeits every
+ * game it is asked to play.
+***********************************************************/
+pub struct RemotePlayer {
+    connection: Option<Connection>,     // The connection, until it is handed back
+    is_player_one: bool,                // True if they play "X"
+    dead: bool,                         // True once the connection has dropped for good
+    session: Option<Session>,           // How they can come back, if they can
+    size: usize,                        // The size of the board being played on
+    history: Vec<(bool, String)>,       // Who did what this game ("X" is true)
+    position: String,                   // The latest position they were sent
+    away: Duration,                     // Time spent waiting for them to come back
+    chat: Option<(Arc<Mutex<Chat>>, usize)>, // The game's chat and which side they are
+    agreed: bool                        // True once the referee has ended the game drawn
+}
+
+impl RemotePlayer {
+    /*******************************************************
+     * LISTEN
+     *******************************************************
+     * Wait on the address for the other player to join.
+     * With a grace period they can come back on the same
+     * address if their connection drops.
+    *******************************************************/
+    pub fn listen(address: &str, grace: Duration) -> Result<RemotePlayer, String> {
+        let listener = TcpListener::bind(address)
+            .map_err(|e| format!("Couldn't listen on {}: {}", address, e))?;
+        println!("Waiting for the other player to join on {}...", address);
+        let (stream, from) = listener.accept().map_err(|e| format!("Nobody could join: {}", e))?;
+        println!("Player joined from {}.", from);
+        let names = ["host".to_string(), "guest".to_string()];
+        let player = RemotePlayer::new(Connection::new(stream).map_err(|e| e.to_string())?)
+            .chatting(&Chat::new(names, Some(0), None), 1);
+        if grace.is_zero() {
+            return Ok(player);
+        }
+        let sessions = Sessions::default();
+        let player = player.resumable(&sessions, grace);
+        thread::spawn(move || welcome_back(listener, sessions));
+        Ok(player)
+    }
+
+    /*******************************************************
+     * NEW / RESUMABLE / CHATTING / INTO_CONNECTION
+     *******************************************************
+     * Play over a connection that is already open; let the
+     * player come back within the grace period with a token
+     * kept in sessions; pass what they say on through the
+     * chat as the given side; and take the connection back
+     * (no longer chatting) once the games are over.
+    *******************************************************/
+    pub fn new(connection: Connection) -> RemotePlayer {
+        RemotePlayer { connection: Some(connection), is_player_one: false, dead: false, session: None,
+                       size: crate::SIZE, history: Vec::new(), position: String::new(),
+                       away: Duration::from_secs(0), chat: None, agreed: false }
+    }
+
+    pub fn resumable(mut self, sessions: &Sessions, grace: Duration) -> RemotePlayer {
+        let token = new_token();
+        let (game, returning) = channel();
+        sessions.lock().unwrap().insert(token.clone(), game);
+        self.session = Some(Session { token, sessions: Arc::clone(sessions), returning, grace });
+        self
+    }
+
+    pub fn chatting(mut self, chat: &Arc<Mutex<Chat>>, side: usize) -> RemotePlayer {
+        if let Some(connection) = &self.connection {
+            Chat::listen(chat, side, connection);
+        }
+        self.chat = Some((Arc::clone(chat), side));
+        self
+    }
+
+    pub fn into_connection(mut self) -> Option<Connection> {
+        let connection = if self.dead { None } else { self.connection.take() };
+        if let Some(connection) = &connection {
+            connection.tap(None);
+        }
+        connection
+    }
+
+    /*******************************************************
+     * SEND
+     *******************************************************
+     * Write a line to the other player, again once they
+     * come back if the connection has dropped.
+    *******************************************************/
+    fn send(&mut self, line: &str) {
+        self.check_returned();
+        if self.dead {
+            return;
+        }
+        let sent = self.connection.as_mut().is_some_and(|c| c.send(line).is_ok());
+        if !sent && self.lost() {
+            self.send(line);
+        }
+    }
+
+    /*******************************************************
+     * EXPECT
+     *******************************************************
+     * Wait for the first line from the other player that
+     * starts with the given word, and return the rest of
+     * it. Returns None if the connection drops, whether or
+     * not the player comes back (they have been caught up
+     * and should be asked again). A player who says nothing
+     * within the time given, if there is one, forfeits.
+    *******************************************************/
+    fn expect(&mut self, word: &str, within: Option<Duration>) -> Option<String> {
+        let deadline = within.map(|within| Instant::now() + within);
+        while !self.dead {
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                self.forfeit();
+                return None;
+            }
+            let received = match self.connection.as_mut() {
+                Some(connection) => connection.poll_line(Some(Duration::from_millis(250))),
+                None => Received::Closed,
+            };
+            match received {
+                Received::Line(line) => {
+                    let mut parts = line.trim().splitn(2, ' ');
+                    if parts.next() == Some(word) {
+                        return Some(parts.next().unwrap_or("").trim().to_string());
+                    }
+                }
+                Received::Nothing => {          // They may have come back before the drop was noticed
+                    if self.check_returned() || self.drawn() {
+                        return None;
+                    }
+                }
+                Received::Closed => {
+                    self.lost();
+                    return None;
+                }
+            }
+        }
+        None
+    }
+
+    /*******************************************************
+     * DRAWN
+     *******************************************************
+     * True if both players have agreed to a draw, which
+     * the referee hasn't acted on yet.
+    *******************************************************/
+    fn drawn(&self) -> bool {
+        self.chat.as_ref().is_some_and(|(chat, _)| chat.lock().unwrap().agreed.iter().any(|a| a == "draw"))
+    }
+
+    /*******************************************************
+     * LOST
+     *******************************************************
+     * The connection has dropped: wait the grace period for
+     * the player to come back. Returns true if they did;
+     * otherwise they forfeit.
+    *******************************************************/
+    fn lost(&mut self) -> bool {
+        let waited = Instant::now();
+        let back = match &self.session {
+            Some(session) if !self.dead => {
+                println!("The other player's connection has dropped. Waiting {} seconds for them...",
+                         session.grace.as_secs());
+                session.returning.recv_timeout(session.grace).ok()
+            }
+            _ => None,
+        };
+        match back {
+            Some(connection) => {
+                self.away += waited.elapsed();  // Their clock stops while they're away
+                self.welcome(connection);
+                true
+            }
+            None => {
+                self.forfeit();
+                false
+            }
+        }
+    }
+
+    /*******************************************************
+     * CHECK_RETURNED
+     *******************************************************
+     * Switch to a new connection if the player has come
+     * back, returning true if they have.
+    *******************************************************/
+    fn check_returned(&mut self) -> bool {
+        match self.session.as_ref().and_then(|s| s.returning.try_recv().ok()) {
+            Some(connection) => {
+                self.welcome(connection);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /*******************************************************
+     * WELCOME
+     *******************************************************
+     * Catch a player who has come back up on the game: the
+     * piece they play, every move so far and the board.
+    *******************************************************/
+    fn welcome(&mut self, mut connection: Connection) {
+        println!("The other player is back.");
+        let piece = if self.is_player_one { _X } else { _O };
+        let history: Vec<String> = self.history.iter()
+            .map(|(player, mv)| format!("{}:{}", if *player { _X } else { _O }, mv)).collect();
+        let _ = connection.send(&format!("resumed {} {}", self.size, piece));
+        let _ = connection.send(&format!("history {}", history.join(" ")));
+        if !self.position.is_empty() {
+            let _ = connection.send(&format!("position {}", self.position));
+        }
+        if let Some((chat, side)) = &self.chat {
+            Chat::listen(chat, *side, &connection);
+        }
+        self.connection = Some(connection);     // Anything that failed shows up next time
+    }
+
+    /*******************************************************
+     * RECORD
+     *******************************************************
+     * Add a move to the history. The player is never told
+     * of their own passes, so one is added whenever the
+     * same side seems to move twice.
+    *******************************************************/
+    fn record(&mut self, is_player_one: bool, mv: String) {
+        if self.history.last().is_some_and(|(player, _)| *player == is_player_one) {
+            self.history.push((!is_player_one, "pass".to_string()));
+        }
+        self.history.push((is_player_one, mv));
+    }
+
+    /*******************************************************
+     * FINISH
+     *******************************************************
+     * Tell the player the game is over: the final board
+     * and how it ended.
+    *******************************************************/
+    pub fn finish(&mut self, board: &Board, message: &str) {
+        if let Some((chat, _)) = &self.chat {
+            chat.lock().unwrap().stop();
+        }
+        self.position = position_string(board, self.is_player_one);
+        let position = format!("position {}", self.position);
+        self.send(&position);
+        self.send(&format!("over {}", message));
+    }
+
+    /*******************************************************
+     * FORFEIT
+     *******************************************************
+     * The connection has dropped for good; say so.
+    *******************************************************/
+    fn forfeit(&mut self) {
+        if !self.dead {
+            println!("The other player has left the game and forfeits.");
+            self.dead = true;
+        }
+    }
+}
+
+impl Player for RemotePlayer {
+    fn new_game(&mut self, board: &Board, is_player_one: bool) {
+        let piece = if is_player_one { _X } else { _O };
+        self.is_player_one = is_player_one;
+        self.size = board.size();
+        self.history.clear();
+        self.position.clear();
+        self.agreed = false;
+        if let Some((chat, _)) = &self.chat {
+            chat.lock().unwrap().start();
+        }
+        self.send(&format!("new {} {}", board.size(), piece));
+        if let Some(session) = &self.session {
+            let line = format!("token {} {}", session.token, session.grace.as_secs());
+            self.send(&line);
+        }
+        self.expect("ready", Some(READY));
+    }
+
+    fn choose_move(&mut self, board: &Board,
+                   is_player_one: bool, legal: &[NumLet]) -> Move {
+        self.position = position_string(board, is_player_one);
+        loop {
+            let position = format!("position {}", self.position);
+            self.send(&position);
+            self.send("go 0");
+            let answer = match self.expect("move", None) {
+                Some(answer) => answer,
+                None if self.dead => return Move::Resign,   // Players who leave forfeit
+                None if self.drawn() => return Move::Resign,    // The referee ends the game drawn
+                None => continue,               // They came back; ask again
+            };
+            if answer.to_lowercase() == "resign" {
+                self.record(is_player_one, "resign".to_string());
+                return Move::Resign;
+            }
+            let mut num: usize = 0;             // The column number index
+            let mut letter: usize = 0;          // The row letter index
+            if get_coordinates(&mut num, &mut letter, answer.clone()) {
+                let nl = NumLet { n: num, l: letter };
+                if legal.contains(&nl) {        // The host checks every move
+                    self.record(is_player_one, coordinates(nl));
+                    return Move::Place(nl);
+                }
+            }
+            self.send(&format!("illegal {}", answer));
+        }
+    }
+
+    fn opponent_moved(&mut self, board: &Board, mv: Move) {
+        let text = match mv {
+            Move::Place(nl) => coordinates(nl),
+            Move::Pass => "pass".to_string(),
+            Move::Resign => "resign".to_string(),
+            _ => return,                        // Saving and loading aren't moves
+        };
+        self.record(!self.is_player_one, text.clone());
+        self.position = position_string(board, self.is_player_one);
+        self.send(&format!("opponent {}", text));
+        let position = format!("position {}", self.position);
+        self.send(&position);
+    }
+
+    fn game_over(&mut self, board: &Board, resigned: Option<&'static str>) {
+        let (x_count, o_count) = count_pieces(board);
+        let message = match resigned {
+            _ if self.agreed => "The game is drawn by agreement.".to_string(),
+            Some(piece) => format!("Player {} resigns. Player {} wins!", piece,
+                                   if piece == _X { _O } else { _X }),
+            None if x_count > o_count => format!("Player X wins {} to {}!", x_count, o_count),
+            None if o_count > x_count => format!("Player O wins {} to {}!", o_count, x_count),
+            None => format!("It's a tie at {} each!", x_count),
+        };
+        self.finish(board, &message);
+    }
+
+    fn time_away(&mut self) -> Duration {
+        std::mem::take(&mut self.away)
+    }
+
+    // The host's player chats as the other side; muting
+    // only changes what the terminal shows
+    fn chat(&mut self, line: &str) -> bool {
+        let (chat, side) = match &self.chat {
+            Some(chat) => chat,
+            None => return false,
+        };
+        let mut chat = chat.lock().unwrap();
+        match line {
+            "mute" | "unmute" => {
+                chat.muted = line == "mute";
+                println!("Chat {}.", if chat.muted { "muted" } else { "unmuted" });
+            }
+            "accept" | "decline" => match chat.offers[*side].clone() {
+                Some(what) => chat.heard(1 - side, &format!("{} {}", line, what)),
+                None => println!("Nobody has offered anything."),
+            },
+            _ => chat.heard(1 - side, line),
+        }
+        true
+    }
+
+    // The referee asks once a move has been chosen, or the
+    // host's player has chatted
+    fn agreed_draw(&mut self) -> bool {
+        let agreed = self.chat.as_ref().is_some_and(|(chat, _)| chat.lock().unwrap().agreed("draw"));
+        self.agreed |= agreed;
+        agreed
+    }
+
+    // The host's player offers a rematch (or accepts one
+    // already offered) and waits for the answer
+    fn rematch(&mut self) -> bool {
+        let (chat, side) = match &self.chat {
+            Some((chat, side)) if !self.dead => (Arc::clone(chat), *side),
+            _ => return false,
+        };
+        let name = {
+            let mut chat = chat.lock().unwrap();
+            chat.heard(1 - side, "offer rematch");
+            chat.names[side].clone()
+        };
+        if chat.lock().unwrap().offers[1 - side].is_some() {
+            println!("Waiting {} seconds for {} to agree to a rematch...", REMATCH.as_secs(), name);
+        }
+        let agreed = Chat::wait_rematch(&chat, REMATCH);
+        if !agreed {
+            println!("There is no rematch.");
+        }
+        agreed
+    }
+
+    fn said(&self) -> Vec<String> {
+        self.chat.as_ref().map_or(Vec::new(), |(chat, _)| chat.lock().unwrap().said.iter().cloned().collect())
+    }
+}
+
+impl Drop for RemotePlayer {
+    // Tell the other player the referee is finished, unless
+    // the connection was handed back, and stop waiting for
+    // them to come back
+    fn drop(&mut self) {
+        if let Some(session) = &self.session {
+            session.sessions.lock().unwrap().remove(&session.token);
+        }
+        if self.connection.is_some() {
+            let _ = self.connection.as_mut().map(|c| c.send("quit"));
+        }
+    }
+}
+
+/***********************************************************
+ * WELCOME_BACK
+ ***********************************************************
+ * Keep listening for players coming back to a hosted game
+ * and hand each to the game waiting for them.
+***********************************************************/
+fn welcome_back(listener: TcpListener, sessions: Sessions) {
+    for stream in listener.incoming().flatten() {
+        let sessions = Arc::clone(&sessions);
+        thread::spawn(move || {
+            let mut connection = match Connection::new(stream) {
+                Ok(connection) => connection,
+                Err(_) => return,
+            };
+            let line = match connection.poll_line(Some(RESUME)) {
+                Received::Line(line) => line,
+                _ => String::new(),             // Too slow, or gone
+            };
+            let refused = match line.strip_prefix("resume ") {
+                Some(token) => resume(&sessions, token.trim(), connection).err(),
+                None => Some(connection),
+            };
+            if let Some(mut connection) = refused {
+                let _ = connection.send("error No game is waiting for you here.");
+            }
+        });
+    }
+}
+
+/***********************************************************
+ * JOIN
+ ***********************************************************
+ * Play as the joining player: connect to the host, show
+ * each board as it arrives and type a move when asked.
+ * With a token, go back to a game the connection to had
+ * dropped instead.
+***********************************************************/
+pub fn join(address: &str, token: Option<&str>) -> Result<(), String> {
+    let stream = TcpStream::connect(address)
+        .map_err(|e| format!("Couldn't join {}: {}", address, e))?;
+    let mut connection = Connection::new(stream).map_err(|e| e.to_string())?;
+    if let Some(token) = token {
+        let _ = connection.send(&format!("resume {}", token));
+        if !answer(&mut connection)? {
+            return Err(format!("Couldn't go back to the game at {}.", address));
+        }
+    }
+    else {
+        println!("Joined the game at {}.", address);
+    }
+    if !play_games(&mut connection, address, &typing()) {
+        println!("The host has closed the game.");
+    }
+    Ok(())
+}
+
+/***********************************************************
+ * CONNECT
+ ***********************************************************
+ * Use a multi-game server: pass on the lobby commands
+ * typed (list, create, join, watch and quit) and show the
+ * server's answers, playing each game created or joined,
+ * or watching one, before going back to the lobby. With a
+ * token, first go back to a game the connection to had
+ * dropped.
+***********************************************************/
+pub fn connect(address: &str, name: Option<&str>, token: Option<&str>) -> Result<(), String> {
+    let stream = TcpStream::connect(address)
+        .map_err(|e| format!("Couldn't connect to {}: {}", address, e))?;
+    let mut connection = Connection::new(stream).map_err(|e| e.to_string())?;
+    let lost = |e: io::Error| format!("Lost the connection: {}", e);
+    let closed = || "The server has closed the connection.".to_string();
+    println!("Connected to the server at {}.", address);
+    let typed = typing();
+    if let Some(name) = name {
+        connection.send(&format!("name {}", name)).map_err(lost)?;
+        answer(&mut connection)?;
+    }
+    if let Some(token) = token {
+        connection.send(&format!("resume {}", token)).map_err(lost)?;
+        if answer(&mut connection)? {
+            if !play_games(&mut connection, address, &typed) {
+                return Err(closed());
+            }
+            println!("Back in the lobby.");
+        }
+    }
+    println!("Commands: list, create [size], join <game>, match [size], watch <game>, ratings,");
+    println!("          name <name> [password], register <password>, quit");
+
+    loop {
+        print!("lobby> ");
+        io::stdout().flush().map_err(|e| e.to_string())?;
+        let command = typed.recv().unwrap_or_else(|_| "quit".to_string());  // Once nothing more will be typed
+        let command = command.trim();
+        if command.is_empty() {
+            continue;
+        }
+        connection.send(command).map_err(lost)?;
+        if command == "quit" {
+            return Ok(());
+        }
+        let accepted = answer(&mut connection)?;
+        let word = command.split_whitespace().next().unwrap_or("");
+        if accepted && ["create", "join", "match", "watch"].contains(&word) {
+            if word == "create" {
+                println!("Waiting for someone to join (type lobby to stop waiting)...");
+            }
+            if !play_games(&mut connection, address, &typed) {
+                return Err(closed());
+            }
+            println!("Back in the lobby.");
+        }
+    }
+}
+
+/***********************************************************
+ * ANSWER
+ ***********************************************************
+ * Show the server's answer to a lobby command, returning
+ * true if it ended with "ok" and false for "error".
+***********************************************************/
+fn answer(connection: &mut Connection) -> Result<bool, String> {
+    while let Some(line) = connection.read_line() {
+        let mut parts = line.splitn(2, ' ');
+        let word = parts.next().unwrap_or("");
+        let rest = parts.next().unwrap_or("");
+        match word {
+            "ok" | "error" => {
+                if !rest.is_empty() {
+                    println!("{}", rest);
+                }
+                return Ok(word == "ok");
+            }
+            _ => println!("{}", line),
+        }
+    }
+    Err("The server has closed the connection.".to_string())
+}
+
+/***********************************************************
+ * RECONNECT
+ ***********************************************************
+ * Keep trying to get back to a game at the address until
+ * the grace period runs out.
+***********************************************************/
+fn reconnect(address: &str, token: &str, grace: Duration) -> Option<Connection> {
+    println!("Lost the connection. Trying to get back to the game for {} seconds...", grace.as_secs());
+    let give_up = Instant::now() + grace;
+    while Instant::now() < give_up {
+        let back = TcpStream::connect(address).ok().and_then(|s| Connection::new(s).ok());
+        if let Some(mut connection) = back {
+            if connection.send(&format!("resume {}", token)).is_ok() {
+                match answer(&mut connection) {
+                    Ok(true) => return Some(connection),
+                    Ok(false) => return None,   // The game is gone
+                    Err(_) => {}
+                }
+            }
+        }
+        thread::sleep(Duration::from_secs(1));
+    }
+    None
+}
+
+/***********************************************************
+ * TYPING
+ ***********************************************************
+ * The lines typed at the terminal, read by a thread of
+ * their own so that what arrives over the network (the
+ * other player's chat, say) is shown while waiting.
+***********************************************************/
+fn typing() -> Receiver<String> {
+    let (sender, typed) = channel();
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            match line {
+                Ok(line) if sender.send(line.trim().to_string()).is_ok() => {}
+                _ => break,                     // Nothing more will be typed, or wanted
+            }
+        }
+    });
+    typed
+}
+
+// How many chat messages are shown under the board
+const CHAT_SHOWN: usize = 3;
+
+// What can be typed during a game
+static GAME_HELP: &str = "Type a location (or resign) when it's your turn. Any time: say <message>, \
+shout <message> (spectators hear it too), mute, unmute, draw, rematch, accept, decline, lobby, \
+or ask about the board: what is on d4, row c, count, moves, describe.";
+
+/***********************************************************
+ * CLIENT
+ ***********************************************************
+ * Where a player or spectator stands in the games played
+ * over a connection.
+***********************************************************/
+struct Client {
+    board: Board,                       // The board as the referee last sent it
+    highlights: Highlights,             // What changed on it, and the legal moves
+    piece: &'static str,                // This player's piece
+    token: Option<(String, Duration)>,  // How to get back to the game
+    started: bool,                      // True once a game has started or is being watched
+    playing: bool,                      // True during a game
+    to_move: bool,                      // True while the referee waits for a move
+    watching: bool,                     // True for a spectator
+    offered: Option<String>,            // What the other player has offered
+    muted: bool,                        // True if chat isn't shown
+    said: VecDeque<String>              // The latest chat, shown under the board
+}
+
+/***********************************************************
+ * PLAY_GAMES
+ ***********************************************************
+ * Play games over a connection until the referee says
+ * "quit" or "lobby" (returning true), or the connection
+ * closes (returning false). If it closes during a game
+ * that can be resumed, connect to the address again and
+ * carry on. Spectators on a server use it too; they are
+ * never asked for a move. Lines are typed at any time:
+ * moves when asked for, chat and offers whenever.
+***********************************************************/
+pub fn play_games(connection: &mut Connection, address: &str, typed: &Receiver<String>) -> bool {
+    let mut client = Client { board: Board::new(crate::SIZE), highlights: Highlights::default(), piece: _O,
+                              token: None, started: false, playing: false, to_move: false, watching: false, offered: None,
+                              muted: false, said: VecDeque::new() };
+
+    // Sending can fail without harm: a dropped connection
+    // shows up on the next read
+    loop {
+        match connection.poll_line(Some(Duration::from_millis(100))) {
+            Received::Line(line) => if client.heard(connection, &line) {
+                return true;
+            },
+            Received::Nothing => {}
+            Received::Closed => {
+                let token = client.token.as_ref().filter(|_| client.playing);
+                match token.and_then(|(t, grace)| reconnect(address, t, *grace)) {
+                    Some(back) => *connection = back,
+                    None => return false,
+                }
+            }
+        }
+        while let Ok(line) = typed.try_recv() {
+            client.typed(connection, &line);
+        }
+    }
+}
+
+impl Client {
+    /*******************************************************
+     * HEARD
+     *******************************************************
+     * Act on a line from the referee. Returns true once it
+     * says "quit" or "lobby".
+    *******************************************************/
+    fn heard(&mut self, connection: &mut Connection, line: &str) -> bool {
+        let mut parts = line.trim().splitn(2, ' ');
+        let word = parts.next().unwrap_or("");
+        let rest = parts.next().unwrap_or("").trim();
+        let mut named = rest.splitn(2, ' ');      // For lines that start with a name
+        let name = named.next().unwrap_or("?");
+        let what = named.next().unwrap_or("");
+        match word {
+            "new" => {
+                self.piece = if rest.ends_with(_X) { _X } else { _O };
+                self.board = Board::new(0);     // Show the first board whatever it is
+                self.started = true;
+                self.playing = true;
+                self.offered = None;
+                println!("A new game is starting. You play {}.", self.piece);
+                println!("{}", GAME_HELP);
+                let _ = connection.send("ready");
+            }
+            "token" => {
+                let mut parts = rest.split_whitespace();
+                let given = parts.next().unwrap_or("").to_string();
+                let grace = Duration::from_secs(parts.next().and_then(|s| s.parse().ok()).unwrap_or(0));
+                if self.token.as_ref().map(|(t, _)| t) != Some(&given) {
+                    println!("If your connection drops, the game waits {} seconds for you to come back.",
+                             grace.as_secs());
+                    println!("Reconnecting is automatic, or start again with --resume {}", given);
+                }
+                self.token = Some((given, grace));
+            }
+            "resumed" => {
+                self.piece = if rest.ends_with(_X) { _X } else { _O };
+                self.board = Board::new(0);
+                self.started = true;
+                self.playing = true;
+                self.to_move = false;           // Asked again if it's their turn
+                println!("Back in the game. You play {}.", self.piece);
+                println!("{}", GAME_HELP);
+            }
+            "history" if !rest.is_empty() => {
+                let moves: Vec<String> = rest.split_whitespace().map(|m| m.replacen(':', " ", 1)).collect();
+                println!("Moves so far: {}", moves.join(", "));
+            }
+            "position" => {
+                match parse_position(rest) {
+                    Ok((new_board, is_player_one)) if new_board != self.board => {
+                        let legal = legal_moves(&new_board, is_player_one);
+                        let highlights = Highlights::between(&self.board, &new_board, &legal);
+                        self.board = new_board;
+                        self.show(highlights);
+                    }
+                    _ => {}                     // Nothing has changed
+                }
+            }
+            "go" => {
+                self.to_move = true;
+                println!("Your turn, player {}.", self.piece);
+            }
+            "watching" => {                     // Spectators only
+                let names: Vec<&str> = rest.split_whitespace().skip(1).collect();
+                self.board = Board::new(0);
+                self.started = true;
+                self.watching = true;
+                println!("Watching {} (X) against {} (O). Type mute or unmute to hide or show the chat, or lobby to leave.",
+                         names.first().unwrap_or(&"?"), names.get(1).unwrap_or(&"?"));
+            }
+            "played" => {                       // Spectators only
+                match what {
+                    "pass" => println!("Player {} can't move and passes.", name),
+                    "resign" => println!("Player {} resigns.", name),
+                    location => println!("Player {} plays {}.", name, location),
+                }
+            }
+            "illegal" => println!("{} is not a legal move.", rest),
+            "opponent" => {
+                let other = if self.piece == _X { _O } else { _X };
+                match rest {
+                    "pass" => println!("Player {} can't move and passes.", other),
+                    "resign" => println!("Player {} resigns.", other),
+                    _ => println!("Player {} plays {}.", other, rest),
+                }
+            }
+            "over" => {
+                self.playing = false;
+                self.to_move = false;
+                self.offered = None;
+                println!("{}", rest);
+            }
+            "chat" if !self.muted => {
+                println!("{}: {}", name, what);
+                self.said.push_back(format!("{}: {}", name, what));
+                if self.said.len() > CHAT_SHOWN {
+                    self.said.pop_front();
+                }
+            }
+            "offer" => {
+                self.offered = Some(what.to_string());
+                println!("{} offers a {}. Type accept or decline.", name, what);
+            }
+            "declined" => println!("{} declines the {}.", name, what),
+            "agreed" => {
+                self.offered = None;
+                println!("Both players agree to a {}.", rest);
+            }
+            "rematch" => println!("Type rematch within {} seconds to offer a rematch, or lobby to go back now.",
+                                  rest),
+            "error" | "notice" => println!("{}", rest),
+            "quit" | "lobby" => return true,
+            _ => {}
+        }
+        false
+    }
+
+    /*******************************************************
+     * TYPED
+     *******************************************************
+     * Act on a line typed at the terminal: a move when
+     * it's this player's turn, or chat and offers at any
+     * time.
+    *******************************************************/
+    fn typed(&mut self, connection: &mut Connection, line: &str) {
+        let mut parts = line.splitn(2, ' ');
+        let word = parts.next().unwrap_or("").to_lowercase();
+        let rest = parts.next().unwrap_or("").trim();
+        if let Some(reply) = accessible::answer(&self.board, &self.highlights, line) {
+            println!("{}", reply);          // A question about the board
+            return;
+        }
+        let mut send = |line: String| { let _ = connection.send(&line); };
+        match word.as_str() {
+            "" => {}
+            "help" => println!("{}", GAME_HELP),
+            "mute" | "unmute" => {
+                self.muted = word == "mute";
+                println!("Chat {}.", if self.muted { "muted" } else { "unmuted" });
+            }
+            "lobby" if !self.started => send("cancel".to_string()),    // Still waiting for an opponent
+            "lobby" if self.watching => send("leave".to_string()),
+            _ if self.watching => println!("Spectators can only mute or unmute the chat, or go back to the lobby."),
+            "say" | "shout" if rest.is_empty() => println!("Type a message after {}.", word),
+            "say" | "shout" if rest.chars().count() > MAX_CHAT => {
+                println!("Messages can be at most {} characters.", MAX_CHAT);
+            }
+            "say" => send(format!("chat {}", rest)),
+            "shout" => send(format!("shout {}", rest)),
+            "draw" | "rematch" => send(format!("offer {}", word)),
+            "accept" | "decline" => match self.offered.take() {
+                Some(what) => send(format!("{} {}", word, what)),
+                None => println!("Nobody has offered anything."),
+            },
+            "lobby" if self.playing => println!("Finish the game first, or resign."),
+            "lobby" => send("decline rematch".to_string()),
+            "resign" if self.to_move => {
+                self.to_move = false;
+                send("move resign".to_string());
+            }
+            _ if self.to_move => {
+                let is_player_one = self.piece == _X;
+                let mut num: usize = 0;         // The column number index
+                let mut letter: usize = 0;      // The row letter index
+                let parsed = get_coordinates(&mut num, &mut letter, line.to_string());
+                let nl = NumLet { n: num, l: letter };
+                if !parsed || !legal_moves(&self.board, is_player_one).contains(&nl) {
+                    println!("{} is not a legal move.", line);
+                    return;
+                }
+                self.to_move = false;
+                let before = self.board.clone();
+                place(&mut self.board, nl, is_player_one);
+                let legal = legal_moves(&self.board, !is_player_one);
+                self.show(Highlights::between(&before, &self.board, &legal));
+                println!("Player {} plays {}.", self.piece, coordinates(nl));
+                send(format!("move {}", coordinates(nl)));
+            }
+            _ => println!("It isn't your turn. Type help for what you can do."),
+        }
+    }
+
+    /*******************************************************
+     * SHOW
+     *******************************************************
+     * Display the board, picking out the highlights, with
+     * the latest chat under it.
+    *******************************************************/
+    fn show(&mut self, highlights: Highlights) {
+        display_highlighted(&self.board, &highlights);
+        self.highlights = highlights;
+        for said in &self.said {
+            println!("  {}", said);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::time::Duration;
+    use crate::create_board;
+
+    // A host's player connected to a joining side that has
+    // already sent the lines given, and the joining side's
+    // reader
+    fn connected(sent: &str) -> (RemotePlayer, BufReader<TcpStream>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut guest = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        guest.write_all(sent.as_bytes()).unwrap();
+        guest.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        (RemotePlayer::new(Connection::new(stream).unwrap()), BufReader::new(guest))
+    }
+
+    // The next line the joining side was sent
+    fn next(guest: &mut BufReader<TcpStream>) -> String {
+        let mut line = String::new();
+        guest.read_line(&mut line).unwrap();
+        line.trim_end().to_string()
+    }
+
+    // The starting position
+    fn start() -> Board {
+        let mut board = Board::new(4);
+        create_board(&mut board);
+        board
+    }
+
+    #[test]
+    fn ports_listen_everywhere() {
+        assert_eq!(address("7777"), "0.0.0.0:7777");
+        assert_eq!(address("127.0.0.1:7777"), "127.0.0.1:7777");
+    }
+
+    #[test]
+    fn illegal_moves_are_asked_for_again() {
+        let (mut player, mut guest) = connected("ready\nmove A1\nmove C4\n");
+        let board = start();
+        player.new_game(&board, true);
+        assert_eq!(player.choose_move(&board, true, &legal_moves(&board, true)),
+                   Move::Place(NumLet { n: 3, l: 2 }));
+        let position = format!("position {}", position_string(&board, true));
+        for expected in ["new 4 X", &position, "go 0", "illegal A1", &position, "go 0"].iter() {
+            assert_eq!(next(&mut guest), *expected);
+        }
+    }
+
+    #[test]
+    fn the_joining_player_hears_the_host_and_the_result() {
+        let (mut player, mut guest) = connected("ready\n");
+        let mut board = start();
+        player.new_game(&board, false);
+        place(&mut board, NumLet { n: 3, l: 2 }, true);
+        player.opponent_moved(&board, Move::Place(NumLet { n: 3, l: 2 }));
+        player.game_over(&board, Some(_O));
+        drop(player);
+        let position = format!("position {}", position_string(&board, false));
+        for expected in ["new 4 O", "opponent C4", &position, &position,
+                         "over Player O resigns. Player X wins!", "quit"].iter() {
+            assert_eq!(next(&mut guest), *expected);
+        }
+    }
+
+    #[test]
+    fn remote_players_can_resign() {
+        let (mut player, _guest) = connected("ready\nmove resign\n");
+        let board = start();
+        player.new_game(&board, true);
+        assert_eq!(player.choose_move(&board, true, &legal_moves(&board, true)), Move::Resign);
+        assert!(!player.dead);
+    }
+
+    #[test]
+    fn players_who_leave_forfeit() {
+        let (mut player, guest) = connected("ready\n");
+        let board = start();
+        player.new_game(&board, true);
+        drop(guest);
+        assert_eq!(player.choose_move(&board, true, &legal_moves(&board, true)), Move::Resign);
+        assert!(player.dead);
+    }
+
+    #[test]
+    fn players_who_never_get_ready_forfeit() {
+        let (mut player, _guest) = connected("");
+        assert_eq!(player.expect("ready", Some(Duration::from_millis(100))), None);
+        assert!(player.dead);
+        let board = start();
+        assert_eq!(player.choose_move(&board, true, &legal_moves(&board, true)), Move::Resign);
+    }
+
+    // A client that hasn't heard anything yet
+    fn client() -> Client {
+        Client { board: start(), highlights: Highlights::default(), piece: _O, token: None, started: false,
+                 playing: false, to_move: false, watching: false, offered: None, muted: false,
+                 said: VecDeque::new() }
+    }
+
+    #[test]
+    fn creators_type_lobby_to_stop_waiting() {
+        let (player, mut guest) = connected("");
+        let mut connection = player.into_connection().unwrap();
+        let mut client = client();
+        client.typed(&mut connection, "lobby");
+        assert_eq!(next(&mut guest), "cancel");
+        client.heard(&mut connection, "new 4 X");
+        assert_eq!(next(&mut guest), "ready");
+        client.heard(&mut connection, "over Player O resigns. Player X wins!");
+        client.typed(&mut connection, "lobby");
+        assert_eq!(next(&mut guest), "decline rematch");
+    }
+
+    #[test]
+    fn spectators_type_lobby_to_leave() {
+        let (player, mut guest) = connected("");
+        let mut connection = player.into_connection().unwrap();
+        let mut client = client();
+        client.heard(&mut connection, "watching 4 alice bob");
+        client.typed(&mut connection, "say well played");
+        client.typed(&mut connection, "lobby");
+        assert_eq!(next(&mut guest), "leave");
+    }
+
+    // A host's player chatting with the host's own player,
+    // who plays X at the terminal, and their chat
+    fn hosted(sent: &str) -> (RemotePlayer, BufReader<TcpStream>, Arc<Mutex<Chat>>) {
+        let (player, guest) = connected(sent);
+        let chat = Chat::new(["host".to_string(), "guest".to_string()], Some(0), None);
+        (player.chatting(&chat, 1), guest, chat)
+    }
+
+    #[test]
+    fn hosted_games_can_be_drawn_by_agreement() {
+        let (mut player, mut guest, chat) = hosted("ready\n");
+        let board = start();
+        player.new_game(&board, false);
+        assert_eq!(next(&mut guest), "new 4 O");
+        chat.lock().unwrap().heard(1, "offer draw");
+        assert!(!player.agreed_draw());
+        assert!(player.chat("accept"));
+        assert_eq!(next(&mut guest), "agreed draw");
+        assert!(player.agreed_draw());
+        player.game_over(&board, None);
+        next(&mut guest);
+        assert_eq!(next(&mut guest), "over The game is drawn by agreement.");
+    }
+
+    #[test]
+    fn the_host_offers_draws_and_rematches() {
+        let (mut player, mut guest, chat) = hosted("ready\n");
+        let board = start();
+        player.new_game(&board, false);
+        next(&mut guest);
+        assert!(player.chat("offer draw"));
+        assert_eq!(next(&mut guest), "offer host draw");
+        player.game_over(&board, None);
+        next(&mut guest);
+        assert_eq!(next(&mut guest), "over It's a tie at 2 each!");
+        chat.lock().unwrap().heard(1, "offer rematch");
+        assert!(player.rematch());
+        assert_eq!(next(&mut guest), "agreed rematch");
+        chat.lock().unwrap().heard(1, "decline rematch");
+        assert!(!player.rematch());
+    }
+
+    #[test]
+    fn the_host_sees_the_latest_chat() {
+        let (mut player, _guest, chat) = hosted("");
+        for text in ["one", "two", "three", "four"].iter() {
+            chat.lock().unwrap().heard(1, &format!("chat {}", text));
+        }
+        assert_eq!(player.said(), ["guest: two", "guest: three", "guest: four"]);
+        player.chat("mute");
+        chat.lock().unwrap().heard(1, "chat five");
+        assert_eq!(player.said().len(), 3);
+    }
+
+    #[test]
+    fn joining_players_answer_new_games_and_stop_at_quit() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let host = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            writeln!(stream, "new 4 O\nposition {}\nover It's a tie at 2 each!\nquit",
+                     position_string(&start(), true)).unwrap();
+            next(&mut BufReader::new(stream))
+        });
+        assert_eq!(join(&address, None), Ok(()));
+        assert_eq!(host.join().unwrap(), "ready");
+    }
+}
@@ -0,0 +1,227 @@
+/***********************************************************
+ * PLAYERS
+ ***********************************************************
+ * Everything that can play a side of the game implements
+ * the Player trait. othello() asks the current player for
+ * a move and tells the other player what was played, so
+ * any combination of humans and computers can play each
+ * other without the game loop knowing which is which.
+ * A new kind of player (a bot, a remote opponent) only
+ * needs its own implementation of the trait.
+***********************************************************/
+
+use std::time::Duration;
+use text_io::read;  // For the read!() function to accept user input
+use crate::{Board, NumLet, get_coordinates, place};
+use crate::accessible;
+use crate::color::Highlights;
+use crate::position::position_string;
+
+/***********************************************************
+ * MOVE
+ ***********************************************************
+ * A player's answer when it's their turn, and what the
+ * opponent is told afterwards. Passes are never chosen by
+ * a player; the game makes them when a player can't place
+ * and reports them to the opponent. Save, Load, Diagram
+ * and Chat are requests from a person at the move prompt;
+ * the game carries them out and asks for a move again.
+***********************************************************/
+#[derive(Clone, Debug, PartialEq)]
+pub enum Move {
+    Place(NumLet),  // Place a piece at the location
+    Pass,           // The player couldn't place and was skipped
+    Resign,         // The player gives up the game
+    Save(String),   // Save the game to the file
+    Load(String),   // Replace the game with the one in the file
+    Diagram(String),// Draw the board to an image file
+    Chat(String)    // A line for the chat with a network opponent
+}
+
+/***********************************************************
+ * PLAYER
+ ***********************************************************
+ * A side of the game. Only choose_move() has to be written;
+ * the notifications do nothing unless a player cares about
+ * them. is_player_one is true when the player is "X".
+***********************************************************/
+pub trait Player {
+    // A new game is starting from the board given and the
+    // player will play "X" if is_player_one is true,
+    // otherwise "O".
+    fn new_game(&mut self, _board: &Board, _is_player_one: bool) {}
+
+    // Choose a move for the position on the board. legal
+    // holds every location the player can place and is
+    // never empty. Returning anything that isn't in legal
+    // (other than Move::Resign) makes the game ask again.
+    fn choose_move(&mut self, board: &Board,
+                   is_player_one: bool, legal: &[NumLet]) -> Move;
+
+    // The opponent has moved, passed or resigned; board is
+    // the position after their move.
+    fn opponent_moved(&mut self, _board: &Board, _mv: Move) {}
+
+    // The game has ended on the given board. resigned holds
+    // the piece of the player who resigned, if anyone did.
+    fn game_over(&mut self, _board: &Board, _resigned: Option<&'static str>) {}
+
+    // How long the player has been away since this was last
+    // asked (waiting for a dropped connection to come back),
+    // which isn't counted on their clock.
+    fn time_away(&mut self) -> Duration { Duration::from_secs(0) }
+
+    // The person playing against this player typed a line
+    // for the chat ("chat <text>", "offer draw", "accept",
+    // "decline", "mute" or "unmute", see network.rs).
+    // Returns false if nobody is there to hear it.
+    fn chat(&mut self, _line: &str) -> bool { false }
+
+    // True once the person playing against this player and
+    // they have agreed to a draw, which ends the game.
+    fn agreed_draw(&mut self) -> bool { false }
+
+    // The person playing against this player wants another
+    // game. Returns true if this player does too.
+    fn rematch(&mut self) -> bool { true }
+
+    // The latest of what this player has said, shown under
+    // the board.
+    fn said(&self) -> Vec<String> { Vec::new() }
+}
+
+/***********************************************************
+ * HUMAN_PLAYER
+ ***********************************************************
+ * A person at the terminal. They type a location such as
+ * "3b" or "b3", "resign" to give up the game, or "save" or
+ * "load" followed by a file name. "position" shows the
+ * position string for the board (see position.rs), and
+ * "diagram" followed by a file name draws the board as an
+ * image (see diagram.rs). Against a player over the
+ * network, "say" followed by a message chats with them,
+ * "draw" offers them a draw, "accept" or "decline" answers
+ * their offer and "mute" or "unmute" hides or shows what
+ * they say.
+ * Questions about the board ("what is on d4", "row c",
+ * "count", "moves", "describe") are answered in words (see
+ * accessible.rs).
+***********************************************************/
+pub struct HumanPlayer {
+    seen: Board                 // The board after their last move, for what has changed since
+}
+
+impl HumanPlayer {
+    pub fn new() -> HumanPlayer {
+        HumanPlayer { seen: Board::new(0) }
+    }
+}
+
+impl Default for HumanPlayer {
+    fn default() -> HumanPlayer {
+        HumanPlayer::new()
+    }
+}
+
+impl Player for HumanPlayer {
+    fn new_game(&mut self, board: &Board, _is_player_one: bool) {
+        self.seen = board.clone();
+    }
+
+    fn choose_move(&mut self, board: &Board,
+                   is_player_one: bool, legal: &[NumLet]) -> Move {
+        let highlights = Highlights::between(&self.seen, board, legal);
+        loop {                                  // Until the player enters a legal location...
+            let pos: String = read!();          // Get the input from the user
+            let question = match pos.to_lowercase().as_str() {
+                "what" | "what's" | "row" => {
+                    let rest: String = read!("{}\n");  // The rest of the question
+                    format!("{} {}", pos, rest)
+                }
+                _ => pos.clone(),
+            };
+            if let Some(reply) = accessible::answer(board, &highlights, &question) {
+                println!("{}", reply);
+                continue;
+            }
+            match pos.to_lowercase().as_str() {
+                "resign" => return Move::Resign,        // The player gives up
+                "save" => return Move::Save(read!()),   // The file name comes next
+                "load" => return Move::Load(read!()),
+                "diagram" => return Move::Diagram(read!()),
+                "say" => {
+                    let text: String = read!("{}\n");  // The rest of the line
+                    return Move::Chat(format!("chat {}", text.trim()));
+                }
+                "mute" | "unmute" | "accept" | "decline" => return Move::Chat(pos.to_lowercase()),
+                "draw" => return Move::Chat("offer draw".to_string()),
+                "position" => {
+                    println!("{}", position_string(board, is_player_one));
+                    continue;
+                }
+                _ => {}
+            }
+            let mut num: usize = 0;             // The column number index
+            let mut letter: usize = 0;          // The row letter index
+            if get_coordinates(&mut num, &mut letter, pos) {
+                let nl = NumLet { n: num, l: letter };
+                if legal.contains(&nl) {        // Only hand back locations that capture
+                    self.seen = board.clone();
+                    place(&mut self.seen, nl, is_player_one);
+                    return Move::Place(nl);
+                }
+            }
+        }
+    }
+}
+
+/***********************************************************
+ * COMPUTER_PLAYER
+ ***********************************************************
+ * A simple computer opponent. It takes a corner whenever it
+ * can and otherwise plays the move that captures the most
+ * pieces, choosing the first one found on a tie.
+***********************************************************/
+pub struct ComputerPlayer;
+
+impl ComputerPlayer {
+    /*******************************************************
+     * SCORE
+     *******************************************************
+     * How good a legal move looks to the computer: the
+     * number of its pieces minus the opponent's after the
+     * move, plus a bonus for corners. Higher is better.
+    *******************************************************/
+    pub fn score(board: &Board, nl: NumLet, is_player_one: bool) -> i32 {
+        let mut test_board = board.clone();                 // Try the move on a copy of the board
+        crate::place(&mut test_board, nl, is_player_one);
+        let (x_count, o_count) = crate::count_pieces(&test_board);
+        let mut score = if is_player_one {
+            x_count as i32 - o_count as i32
+        }
+        else {
+            o_count as i32 - x_count as i32
+        };
+        let edge = board.size() - 1;                        // Index of the last row and column
+        if (nl.n == 0 || nl.n == edge) && (nl.l == 0 || nl.l == edge) {
+            score += (board.size() * board.size()) as i32;  // Corners can never be taken back
+        }
+        score
+    }
+}
+
+impl Player for ComputerPlayer {
+    fn choose_move(&mut self, board: &Board,
+                   is_player_one: bool, legal: &[NumLet]) -> Move {
+        let mut best = legal[0];                            // Best location found so far
+        let mut best_score = i32::MIN;                      // Score of the best location
+        for nl in legal {
+            let score = ComputerPlayer::score(board, *nl, is_player_one);
+            if score > best_score {
+                best = *nl;
+                best_score = score;
+            }
+        }
+        Move::Place(best)
+    }
+}
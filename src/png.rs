@@ -0,0 +1,404 @@
+/***********************************************************
+ * PNG IMAGES
+ ***********************************************************
+ * Diagrams drawn as PNG images, for tools that only take
+ * raster images. Everything is done here with no outside
+ * program or library:
+ *     Raster      A canvas of RGB pixels. Shapes are
+ *                 anti-aliased at their edges and text
+ *                 uses a small built in 5*7 pixel font
+ *                 (the digits and the row letters).
+ *     to_png()    Encodes the pixels as a PNG file: the
+ *                 IHDR, IDAT and IEND chunks, each with
+ *                 its CRC-32. The IDAT data is a zlib
+ *                 stream compressed with deflate's fixed
+ *                 Huffman codes, using repeats of the
+ *                 pixel to the left and the row above,
+ *                 which is most of a board diagram.
+***********************************************************/
+
+use crate::diagram::{Canvas, Sheet};
+
+// The 5*7 font: each row of a glyph is 5 bits, the left
+// column being the highest bit
+static GLYPHS: [(char, [u8; 7]); 18] = [
+    ('0', [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E]),
+    ('1', [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E]),
+    ('2', [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F]),
+    ('3', [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E]),
+    ('4', [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02]),
+    ('5', [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E]),
+    ('6', [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E]),
+    ('7', [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08]),
+    ('8', [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E]),
+    ('9', [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C]),
+    ('A', [0x0E, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11]),
+    ('B', [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E]),
+    ('C', [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E]),
+    ('D', [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C]),
+    ('E', [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F]),
+    ('F', [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10]),
+    ('G', [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F]),
+    ('H', [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11]),
+];
+
+/***********************************************************
+ * RASTER
+ ***********************************************************
+ * A canvas of width * height RGB pixels, row by row.
+***********************************************************/
+pub struct Raster {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<[u8; 3]>
+}
+
+impl Raster {
+    // A white raster
+    pub fn new(width: usize, height: usize) -> Raster {
+        Raster { width, height, pixels: vec![[0xff; 3]; width * height] }
+    }
+
+    // Mix a color into a pixel; coverage 1 paints over it
+    fn blend(&mut self, x: usize, y: usize, color: [u8; 3], coverage: f64) {
+        if x >= self.width || y >= self.height || coverage <= 0.0 {
+            return;
+        }
+        let pixel = &mut self.pixels[y * self.width + x];
+        for i in 0..3 {
+            let mixed = pixel[i] as f64 + (color[i] as f64 - pixel[i] as f64) * coverage.min(1.0);
+            pixel[i] = mixed.round() as u8;
+        }
+    }
+
+    // Paint every pixel within a box whose coverage is
+    // given by a function of the pixel's centre
+    fn shade(&mut self, left: f64, top: f64, right: f64, bottom: f64,
+             color: [u8; 3], coverage: impl Fn(f64, f64) -> f64) {
+        let first_x = left.floor().max(0.0) as usize;
+        let first_y = top.floor().max(0.0) as usize;
+        let last_x = (right.ceil().max(0.0) as usize).min(self.width);
+        let last_y = (bottom.ceil().max(0.0) as usize).min(self.height);
+        for y in first_y..last_y {
+            for x in first_x..last_x {
+                self.blend(x, y, color, coverage(x as f64 + 0.5, y as f64 + 0.5));
+            }
+        }
+    }
+}
+
+impl Canvas for Raster {
+    fn rect(&mut self, x: f64, y: f64, width: f64, height: f64, color: [u8; 3]) {
+        // How much of the pixel at (px, py) the rectangle covers
+        let overlap = |from: f64, to: f64, p: f64| ((p + 0.5).min(to) - (p - 0.5).max(from)).clamp(0.0, 1.0);
+        self.shade(x, y, x + width, y + height, color,
+                   |px, py| overlap(x, x + width, px) * overlap(y, y + height, py));
+    }
+
+    fn circle(&mut self, x: f64, y: f64, radius: f64, color: [u8; 3]) {
+        self.shade(x - radius, y - radius, x + radius, y + radius, color,
+                   |px, py| radius - ((px - x).powi(2) + (py - y).powi(2)).sqrt() + 0.5);
+    }
+
+    fn ring(&mut self, x: f64, y: f64, radius: f64, thickness: f64, color: [u8; 3]) {
+        let outer = radius + thickness / 2.0;
+        self.shade(x - outer, y - outer, x + outer, y + outer, color, |px, py| {
+            let distance = ((px - x).powi(2) + (py - y).powi(2)).sqrt();
+            thickness / 2.0 - (distance - radius).abs() + 0.5
+        });
+    }
+
+    fn text(&mut self, x: f64, y: f64, size: f64, text: &str, color: [u8; 3]) {
+        let dot = (size * 0.7 / 7.0).max(1.0);     // Size of a font pixel; capitals are 7 high
+        let width = (text.chars().count() * 6 - 1) as f64 * dot;
+        let left = x - width / 2.0;
+        let top = y - 3.5 * dot;
+        for (i, c) in text.chars().enumerate() {
+            let rows = match GLYPHS.iter().find(|(g, _)| *g == c.to_ascii_uppercase()) {
+                Some((_, rows)) => rows,
+                None => continue,               // Only the characters a diagram needs are drawn
+            };
+            for (row, bits) in rows.iter().enumerate() {
+                for column in 0..5 {
+                    if bits & (0x10 >> column) != 0 {
+                        self.rect(left + (i * 6 + column) as f64 * dot, top + row as f64 * dot, dot, dot, color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/***********************************************************
+ * TO_PNG
+ ***********************************************************
+ * Draw a sheet of diagrams and encode it as a PNG file.
+***********************************************************/
+pub fn to_png(sheet: &Sheet) -> Vec<u8> {
+    let (width, height) = sheet.dimensions();
+    let mut raster = Raster::new(width, height);
+    sheet.draw(&mut raster);
+    encode(&raster)
+}
+
+/***********************************************************
+ * ENCODE
+ ***********************************************************
+ * Encode a raster as an 8 bit RGB PNG file.
+***********************************************************/
+pub fn encode(raster: &Raster) -> Vec<u8> {
+    let mut data = Vec::with_capacity((raster.width * 3 + 1) * raster.height);
+    for row in raster.pixels.chunks(raster.width.max(1)) {
+        data.push(0);                                   // Each row is stored unfiltered
+        data.extend(row.iter().flatten());
+    }
+    let mut header = Vec::new();
+    header.extend((raster.width as u32).to_be_bytes());
+    header.extend((raster.height as u32).to_be_bytes());
+    header.extend([8, 2, 0, 0, 0]);                     // 8 bits, RGB, deflate, no filter, no interlace
+
+    let mut png = vec![0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+    chunk(&mut png, b"IHDR", &header);
+    chunk(&mut png, b"IDAT", &zlib(&data, raster.width * 3 + 1));
+    chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+/***********************************************************
+ * CHUNK
+ ***********************************************************
+ * Add a chunk to a PNG file: its length, type, data and
+ * the CRC-32 of the type and data.
+***********************************************************/
+fn chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend((data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend(kind);
+    png.extend(data);
+    let crc = crc32(&png[start..]);
+    png.extend(crc.to_be_bytes());
+}
+
+/***********************************************************
+ * CRC32 / ADLER32
+ ***********************************************************
+ * The checksums PNG chunks and zlib streams end with.
+***********************************************************/
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in bytes {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+/***********************************************************
+ * BITS
+ ***********************************************************
+ * Deflate output, filled from the lowest bit of each byte.
+***********************************************************/
+struct Bits {
+    bytes: Vec<u8>,
+    used: usize         // Bits used in the last byte
+}
+
+impl Bits {
+    // Add the lowest count bits of value, lowest first
+    fn put(&mut self, value: u32, count: usize) {
+        for i in 0..count {
+            if self.used == 0 {
+                self.bytes.push(0);
+            }
+            let last = self.bytes.len() - 1;
+            self.bytes[last] |= (((value >> i) & 1) as u8) << self.used;
+            self.used = (self.used + 1) % 8;
+        }
+    }
+
+    // Add a Huffman code, which goes highest bit first
+    fn code(&mut self, code: u32, count: usize) {
+        for i in (0..count).rev() {
+            self.put(code >> i, 1);
+        }
+    }
+
+    // Add a literal byte or end of block (256) code
+    fn literal(&mut self, value: u32) {
+        match value {
+            0..=143 => self.code(0x30 + value, 8),
+            144..=255 => self.code(0x190 + value - 144, 9),
+            _ => self.code(value - 256, 7),
+        }
+    }
+}
+
+// Deflate's length codes (from 257) and distance codes:
+// the first value of each and its number of extra bits
+static LENGTHS: [(u32, usize); 29] = [
+    (3, 0), (4, 0), (5, 0), (6, 0), (7, 0), (8, 0), (9, 0), (10, 0), (11, 1), (13, 1),
+    (15, 1), (17, 1), (19, 2), (23, 2), (27, 2), (31, 2), (35, 3), (43, 3), (51, 3), (59, 3),
+    (67, 4), (83, 4), (99, 4), (115, 4), (131, 5), (163, 5), (195, 5), (227, 5), (258, 0)];
+static DISTANCES: [(u32, usize); 30] = [
+    (1, 0), (2, 0), (3, 0), (4, 0), (5, 1), (7, 1), (9, 2), (13, 2), (17, 3), (25, 3),
+    (33, 4), (49, 4), (65, 5), (97, 5), (129, 6), (193, 6), (257, 7), (385, 7), (513, 8), (769, 8),
+    (1025, 9), (1537, 9), (2049, 10), (3073, 10), (4097, 11), (6145, 11), (8193, 12), (12289, 12),
+    (16385, 13), (24577, 13)];
+
+/***********************************************************
+ * ZLIB
+ ***********************************************************
+ * Compress data into a zlib stream holding one deflate
+ * block with the fixed Huffman codes. Only two kinds of
+ * repeat are looked for: of the pixel to the left (3 bytes
+ * back) and of the row above (stride bytes back), taking
+ * whichever is longer.
+***********************************************************/
+fn zlib(data: &[u8], stride: usize) -> Vec<u8> {
+    let mut bits = Bits { bytes: vec![0x78, 0x01], used: 0 };   // Deflate, 32K window, no dictionary
+    bits.put(1, 1);                                             // The last block
+    bits.put(1, 2);                                             // uses the fixed codes
+    let repeat = |at: usize, back: usize| {
+        if back > at || back > 32768 {
+            return 0;
+        }
+        (0..258.min(data.len() - at)).take_while(|i| data[at + i] == data[at + i - back]).count()
+    };
+    let mut at = 0;
+    while at < data.len() {
+        let (length, back) = [(repeat(at, 3), 3), (repeat(at, stride), stride)]
+            .iter().copied().max_by_key(|(length, _)| *length).unwrap_or((0, 0));
+        if length < 3 {
+            bits.literal(data[at] as u32);
+            at += 1;
+            continue;
+        }
+        let code = LENGTHS.iter().rposition(|(first, _)| *first as usize <= length).unwrap_or(0);
+        let (first, extra) = LENGTHS[code];
+        let code = code as u32 + 257;
+        if code < 280 { bits.code(code - 256, 7) } else { bits.code(0xC0 + code - 280, 8) }
+        bits.put(length as u32 - first, extra);
+        let code = DISTANCES.iter().rposition(|(first, _)| *first as usize <= back).unwrap_or(0);
+        let (first, extra) = DISTANCES[code];
+        bits.code(code as u32, 5);
+        bits.put(back as u32 - first, extra);
+        at += length;
+    }
+    bits.literal(256);                                          // End of the block
+    bits.bytes.extend(adler32(data).to_be_bytes());
+    bits.bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Reads a zlib stream back, for the fixed codes zlib() uses
+    struct Reader<'a> {
+        bytes: &'a [u8],
+        at: usize           // Bits read so far
+    }
+
+    impl Reader<'_> {
+        // The next count bits, lowest first
+        fn bits(&mut self, count: usize) -> u32 {
+            let mut value = 0;
+            for i in 0..count {
+                value |= (((self.bytes[self.at / 8] >> (self.at % 8)) & 1) as u32) << i;
+                self.at += 1;
+            }
+            value
+        }
+
+        // A Huffman code of count more bits, highest first
+        fn code(&mut self, code: u32, count: usize) -> u32 {
+            (0..count).fold(code, |code, _| code << 1 | self.bits(1))
+        }
+
+        // A literal, end of block or length code
+        fn symbol(&mut self) -> u32 {
+            let code = self.code(0, 7);
+            if code <= 0x17 {
+                return code + 256;
+            }
+            let code = self.code(code, 1);
+            match code {
+                0x30..=0xBF => code - 0x30,
+                0xC0..=0xC7 => code - 0xC0 + 280,
+                _ => self.code(code, 1) - 0x190 + 144,
+            }
+        }
+    }
+
+    fn inflate(stream: &[u8]) -> Vec<u8> {
+        assert_eq!(&stream[..2], &[0x78, 0x01]);
+        let mut reader = Reader { bytes: stream, at: 16 };
+        assert_eq!(reader.bits(3), 0b011);                  // The last block, fixed codes
+        let mut data: Vec<u8> = Vec::new();
+        loop {
+            match reader.symbol() {
+                literal @ 0..=255 => data.push(literal as u8),
+                256 => break,
+                code => {
+                    let (first, extra) = LENGTHS[code as usize - 257];
+                    let length = (first + reader.bits(extra)) as usize;
+                    let (first, extra) = DISTANCES[reader.code(0, 5) as usize];
+                    let back = (first + reader.bits(extra)) as usize;
+                    for _ in 0..length {
+                        data.push(data[data.len() - back]);
+                    }
+                }
+            }
+        }
+        let end = reader.at.div_ceil(8);
+        assert_eq!(&stream[end..], &adler32(&data).to_be_bytes());
+        data
+    }
+
+    #[test]
+    fn checksums_match_known_values() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+        assert_eq!(adler32(b""), 1);
+    }
+
+    #[test]
+    fn zlib_streams_decode() {
+        let mut rows = Vec::new();
+        for y in 0..40u32 {
+            rows.push(0);
+            rows.extend((0..300u32).map(|x| if (x / 7 + y / 5) % 2 == 0 { 30 } else { (x * y % 251) as u8 }));
+        }
+        let bytes: Vec<u8> = (0..=255).collect();
+        for (data, stride) in [(Vec::new(), 1), (bytes, 4), (vec![7; 1000], 3), (rows, 301)] {
+            assert_eq!(inflate(&zlib(&data, stride)), data);
+        }
+    }
+
+    #[test]
+    fn images_hold_their_pixels() {
+        let mut raster = Raster::new(3, 2);
+        raster.rect(0.0, 0.0, 3.0, 1.0, [255, 0, 0]);
+        let png = encode(&raster);
+        assert_eq!(&png[..8], &[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]);
+        assert_eq!(&png[12..16], b"IHDR");
+        let length = u32::from_be_bytes([png[33], png[34], png[35], png[36]]) as usize;
+        assert_eq!(&png[37..41], b"IDAT");
+        let data = inflate(&png[41..41 + length]);
+        assert_eq!(data.len(), 2 * (3 * 3 + 1));
+        assert_eq!(&data[..4], &[0, 255, 0, 0]);
+        assert_eq!(crc32(&png[37..41 + length]).to_be_bytes(), png[41 + length..45 + length]);
+    }
+}
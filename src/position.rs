@@ -0,0 +1,106 @@
+/***********************************************************
+ * POSITION STRINGS
+ ***********************************************************
+ * A position written on one line: one character for each
+ * location, row by row starting with row "A", then a space
+ * and the piece to move. For the standard 8*8 start:
+ *     ---------------------------OX------XO--------------------------- X
+ * "X" is player one (black), "O" player two (white) and
+ * "-" an empty location. This is the style used by Othello
+ * test suites, so when reading "*" is also taken for "X",
+ * "." and "_" for "-", and the letters may be lower case.
+ * The board size comes from the number of locations (16
+ * for 4*4, 64 for 8*8).
+***********************************************************/
+
+use crate::{Board, _X, _O, valid_size};
+
+/***********************************************************
+ * POSITION_STRING
+ ***********************************************************
+ * Write a board and the player to move as a position
+ * string.
+***********************************************************/
+pub fn position_string(board: &Board, is_player_one: bool) -> String {
+    let cells: String = board.iter().flatten()
+        .map(|p| if *p == " " { "-" } else { p })
+        .collect();
+    format!("{} {}", cells, if is_player_one { _X } else { _O })
+}
+
+/***********************************************************
+ * PARSE_POSITION
+ ***********************************************************
+ * Read a position string into a board and whether "X" is
+ * to move. Whitespace inside the locations is ignored so
+ * positions can be split into rows. Returns a message
+ * saying what is wrong if the string can't be read.
+***********************************************************/
+pub fn parse_position(text: &str) -> Result<(Board, bool), String> {
+    let chars: Vec<char> = text.chars().filter(|c| !c.is_whitespace()).collect();
+    let side = match chars.last() {
+        Some(side) => *side,
+        None => return Err("The position is empty".to_string()),
+    };
+    let is_player_one = match side {
+        'X' | 'x' | '*' => true,
+        'O' | 'o' => false,
+        _ => return Err(format!("\"{}\" is not a piece to move", side)),
+    };
+    let cells = &chars[..chars.len() - 1];
+    let size = (1..=cells.len()).find(|s| s * s >= cells.len()).unwrap_or(0);
+    if size * size != cells.len() || !valid_size(size) {
+        return Err(format!("{} locations don't make a board this program can play", cells.len()));
+    }
+    let mut board = Board::new(size);
+    for (i, c) in cells.iter().enumerate() {
+        board[i / size][i % size] = match c {
+            'X' | 'x' | '*' => _X,
+            'O' | 'o' => _O,
+            '-' | '.' | '_' => " ",
+            _ => return Err(format!("\"{}\" is not a piece or an empty location", c)),
+        };
+    }
+    Ok((board, is_player_one))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_board;
+
+    #[test]
+    fn positions_round_trip() {
+        for size in [4, 6, 8] {
+            let mut board = Board::new(size);
+            create_board(&mut board);
+            for is_player_one in [true, false] {
+                let text = position_string(&board, is_player_one);
+                assert_eq!(parse_position(&text), Ok((board.clone(), is_player_one)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_suite_styles_are_read() {
+        let mut board = Board::new(8);
+        create_board(&mut board);
+        let rows = "........ ........ ........ ...o*... ...*o... ........ ........ ________ *";
+        assert_eq!(position_string(&board, true),
+                   "---------------------------OX------XO--------------------------- X");
+        assert_eq!(parse_position(rows), Ok((board, true)));
+    }
+
+    #[test]
+    fn bad_positions_are_refused() {
+        let bad = [("", "The position is empty"),
+                   ("-OX--XO-------- -", "\"-\" is not a piece to move"),
+                   ("-----OX--XO---- X", "15 locations don't make a board this program can play"),
+                   ("-----OX--XO----? O", "\"?\" is not a piece or an empty location")];
+        for (text, message) in bad {
+            assert_eq!(parse_position(text), Err(message.to_string()));
+        }
+        assert_eq!(parse_position(&format!("{} X", "-".repeat(100))),
+                   Err("100 locations don't make a board this program can play".to_string()));
+    }
+}
@@ -0,0 +1,296 @@
+/***********************************************************
+ * RATINGS
+ ***********************************************************
+ * The server's ladder: an Elo rating for every registered
+ * player, kept in a CSV file that a spreadsheet can open,
+ * one player to a line after a heading:
+ *     name,rating,games,wins,losses,draws,password
+ *     ann,1516.0,1,1,0,0,3f2a...:9c41...
+ * Everyone starts at START. After a rated game each
+ * player's rating moves by K times the difference between
+ * their score (1 for a win, 1/2 for a draw, 0 for a loss)
+ * and the score the ratings expected:
+ *     1 / (1 + 10^((their rating - your rating) / 400))
+ * so beating a stronger player gains more than beating a
+ * weaker one, and the points one player gains the other
+ * loses.
+ *
+ * Passwords are kept as a random salt and the SHA-1 hash
+ * of the salt and password (see digest.rs): enough to
+ * stop one player playing as another on a friendly server,
+ * not a secure password store.
+***********************************************************/
+
+use std::collections::BTreeMap;
+use std::fs;
+use crate::digest::{new_token, sha1};
+
+// The rating every player starts with
+pub const START: f64 = 1500.0;
+
+// The most a rating can move in one game
+const K: f64 = 32.0;
+
+// The first line of the file
+static HEADING: &str = "name,rating,games,wins,losses,draws,password";
+
+/***********************************************************
+ * RECORD
+ ***********************************************************
+ * A registered player's rating and results.
+***********************************************************/
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+    pub rating: f64,
+    pub games: u32,         // Rated games finished
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+    password: String        // "<salt>:<hash>"
+}
+
+/***********************************************************
+ * RATINGS
+ ***********************************************************
+ * Every registered player by name, and the file they are
+ * saved to (none for a ladder that isn't kept).
+***********************************************************/
+#[derive(Default)]
+pub struct Ratings {
+    players: BTreeMap<String, Record>,
+    path: Option<String>
+}
+
+impl Ratings {
+    /*******************************************************
+     * LOAD / SAVE
+     *******************************************************
+     * Read the ladder from a file, starting an empty one if
+     * the file doesn't exist yet; and write it back, to a
+     * new file first so a crash can't leave half a ladder.
+    *******************************************************/
+    pub fn load(path: &str) -> Result<Ratings, String> {
+        let mut ratings = Ratings { players: BTreeMap::new(), path: Some(path.to_string()) };
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(ratings),
+            Err(e) => return Err(format!("Couldn't read {}: {}", path, e)),
+        };
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line == HEADING {
+                continue;
+            }
+            let bad = || format!("Line {} of {} isn't a player's rating: {}", i + 1, path, line);
+            let fields: Vec<&str> = line.split(',').collect();
+            if fields.len() != 7 {
+                return Err(bad());
+            }
+            let count = |j: usize| fields[j].trim().parse::<u32>().map_err(|_| bad());
+            let record = Record {
+                rating: fields[1].trim().parse().map_err(|_| bad())?,
+                games: count(2)?,
+                wins: count(3)?,
+                losses: count(4)?,
+                draws: count(5)?,
+                password: fields[6].trim().to_string(),
+            };
+            ratings.players.insert(fields[0].trim().to_string(), record);
+        }
+        Ok(ratings)
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let mut text = format!("{}\n", HEADING);
+        for (name, r) in &self.players {
+            text += &format!("{},{:.1},{},{},{},{},{}\n", name, r.rating, r.games, r.wins, r.losses,
+                             r.draws, r.password);
+        }
+        let new = format!("{}.new", path);
+        fs::write(&new, text).and_then(|_| fs::rename(&new, path))
+            .map_err(|e| format!("Couldn't save the ratings to {}: {}", path, e))
+    }
+
+    /*******************************************************
+     * REGISTER / CHECK
+     *******************************************************
+     * Add a player to the ladder with a password; and find
+     * whether a password is the one a player registered.
+    *******************************************************/
+    pub fn register(&mut self, name: &str, password: &str) -> Result<(), String> {
+        if self.players.contains_key(name) {
+            return Err(format!("{} is already registered.", name));
+        }
+        if name.contains(',') || password.contains(',') {
+            return Err("Names and passwords for rated play can't contain commas.".to_string());
+        }
+        let salt = new_token();
+        let password = format!("{}:{}", salt, hash(&salt, password));
+        self.players.insert(name.to_string(), Record { rating: START, games: 0, wins: 0, losses: 0,
+                                                       draws: 0, password });
+        self.save()
+    }
+
+    pub fn check(&self, name: &str, password: &str) -> bool {
+        self.players.get(name).is_some_and(|r| match r.password.split_once(':') {
+            Some((salt, hashed)) => hash(salt, password) == hashed,
+            None => false,
+        })
+    }
+
+    /*******************************************************
+     * RATING / IS_REGISTERED / LADDER
+     *******************************************************
+     * A registered player's rating; whether a name is
+     * registered; and everyone, highest rated first.
+    *******************************************************/
+    pub fn rating(&self, name: &str) -> Option<f64> {
+        self.players.get(name).map(|r| r.rating)
+    }
+
+    pub fn is_registered(&self, name: &str) -> bool {
+        self.players.contains_key(name)
+    }
+
+    pub fn ladder(&self) -> Vec<(&String, &Record)> {
+        let mut ladder: Vec<(&String, &Record)> = self.players.iter().collect();
+        ladder.sort_by(|a, b| b.1.rating.total_cmp(&a.1.rating));
+        ladder
+    }
+
+    /*******************************************************
+     * RECORD_GAME
+     *******************************************************
+     * Update the ratings of the players of X and O after a
+     * game in which X scored x_score (1, 1/2 or 0) and save
+     * them, returning how much each rating changed. Games
+     * with a player who isn't registered change nothing.
+    *******************************************************/
+    pub fn record_game(&mut self, x: &str, o: &str, x_score: f64) -> Result<Option<[f64; 2]>, String> {
+        let (x_rating, o_rating) = match (self.rating(x), self.rating(o)) {
+            (Some(x_rating), Some(o_rating)) if x != o => (x_rating, o_rating),
+            _ => return Ok(None),
+        };
+        let x_change = K * (x_score - expected(x_rating, o_rating));
+        let changes = [x_change, -x_change];
+        for (name, (change, score)) in [x, o].iter().zip(changes.iter().zip([x_score, 1.0 - x_score])) {
+            let record = self.players.get_mut(*name).unwrap();
+            record.rating += change;
+            record.games += 1;
+            match score {
+                s if s > 0.5 => record.wins += 1,
+                s if s < 0.5 => record.losses += 1,
+                _ => record.draws += 1,
+            }
+        }
+        self.save()?;
+        Ok(Some(changes))
+    }
+}
+
+/***********************************************************
+ * EXPECTED
+ ***********************************************************
+ * The score a player rated rating is expected to make
+ * against one rated other.
+***********************************************************/
+pub fn expected(rating: f64, other: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf((other - rating) / 400.0))
+}
+
+/***********************************************************
+ * HASH
+ ***********************************************************
+ * A salted password's hash, in hex.
+***********************************************************/
+fn hash(salt: &str, password: &str) -> String {
+    sha1(format!("{}{}", salt, password).as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    // A ladder that isn't kept, with players at the ratings given
+    fn ladder(players: &[(&str, f64)]) -> Ratings {
+        let mut ratings = Ratings::default();
+        for (name, rating) in players {
+            ratings.register(name, "secret").unwrap();
+            ratings.players.get_mut(*name).unwrap().rating = *rating;
+        }
+        ratings
+    }
+
+    #[test]
+    fn expected_scores_follow_elo() {
+        assert_eq!(expected(1500.0, 1500.0), 0.5);
+        assert!((expected(1900.0, 1500.0) - 10.0 / 11.0).abs() < 1e-12);
+        assert!((expected(1500.0, 1900.0) - 1.0 / 11.0).abs() < 1e-12);
+        assert!((expected(1700.0, 1600.0) + expected(1600.0, 1700.0) - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn games_move_ratings() {
+        let mut ratings = ladder(&[("ann", 1500.0), ("bob", 1500.0), ("cat", 1900.0)]);
+        assert_eq!(ratings.record_game("ann", "bob", 1.0), Ok(Some([16.0, -16.0])));
+        assert_eq!(ratings.rating("ann"), Some(1516.0));
+        assert_eq!(ratings.rating("bob"), Some(1484.0));
+
+        // An upset gains more than beating an equal
+        let [gain, loss] = ratings.record_game("cat", "bob", 0.0).unwrap().unwrap();
+        assert!(gain < -16.0 && loss > 16.0 && gain + loss == 0.0);
+
+        assert_eq!(ratings.record_game("ann", "cat", 0.5).unwrap().map(|c| c[0] > 0.0), Some(true));
+        let ann = &ratings.players["ann"];
+        assert_eq!((ann.games, ann.wins, ann.losses, ann.draws), (2, 1, 0, 1));
+        let bob = &ratings.players["bob"];
+        assert_eq!((bob.games, bob.wins, bob.losses, bob.draws), (2, 1, 1, 0));
+    }
+
+    #[test]
+    fn unregistered_players_change_nothing() {
+        let mut ratings = ladder(&[("ann", 1500.0)]);
+        assert_eq!(ratings.record_game("ann", "dan", 1.0), Ok(None));
+        assert_eq!(ratings.record_game("ann", "ann", 1.0), Ok(None));
+        assert_eq!(ratings.players["ann"].games, 0);
+    }
+
+    #[test]
+    fn passwords_are_checked() {
+        let mut ratings = ladder(&[("ann", 1500.0)]);
+        assert!(ratings.check("ann", "secret"));
+        assert!(!ratings.check("ann", "Secret"));
+        assert!(!ratings.check("bob", "secret"));
+        assert!(ratings.register("ann", "other").is_err());
+        assert!(ratings.register("b,ob", "secret").is_err());
+    }
+
+    #[test]
+    fn the_same_password_is_salted_differently() {
+        let ratings = ladder(&[("ann", 1500.0), ("bob", 1500.0)]);
+        let (ann, bob) = (&ratings.players["ann"].password, &ratings.players["bob"].password);
+        assert_eq!(ann.split_once(':').map(|(salt, _)| salt.len()), Some(32));
+        assert!(ann != bob);
+    }
+
+    #[test]
+    fn ladders_are_saved_and_loaded() {
+        let path = env::temp_dir().join(format!("othello-test-{}.csv", std::process::id()));
+        let path = path.to_string_lossy().to_string();
+        let mut ratings = Ratings::load(&path).unwrap();
+        ratings.register("ann", "secret").unwrap();
+        ratings.register("bob", "secret").unwrap();
+        ratings.record_game("ann", "bob", 1.0).unwrap();
+        let loaded = Ratings::load(&path);
+        let _ = fs::remove_file(&path);
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.players, ratings.players);
+        assert_eq!(loaded.ladder().iter().map(|(name, _)| name.as_str()).collect::<Vec<&str>>(), ["ann", "bob"]);
+        assert!(loaded.check("bob", "secret"));
+    }
+}
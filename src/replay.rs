@@ -0,0 +1,166 @@
+/***********************************************************
+ * REPLAY VIEWER
+ ***********************************************************
+ * With "--replay FILE" the program loads a saved game and
+ * steps through it instead of playing. The board after the
+ * current move is drawn by display(), followed by the move
+ * and the pieces it flipped. Commands, one per line:
+ *     n, next or Enter    Forward one move.
+ *     b, back             Back one move.
+ *     <number>            Jump to the position after that
+ *                         move (0 is the start).
+ *     start / end         Jump to the start or the end.
+ *     q, quit             Stop.
+***********************************************************/
+
+use std::io::{self, BufRead};
+use crate::game::{move_text, Game};
+use crate::color::Highlights;
+use crate::{Board, NumLet, _X, _O, coordinates, count_pieces, display_highlighted, legal_moves};
+
+/***********************************************************
+ * RUN
+ ***********************************************************
+ * Load the game in the file and step through it until the
+ * user quits or stdin ends.
+***********************************************************/
+pub fn run(path: &str) -> Result<(), String> {
+    let game = Game::load(path)?;
+    let positions = game.positions()?;
+    let last = game.moves.len();        // Number of the last move
+    let mut current = 0;                // Number of moves played on the board shown
+    show(&game, &positions, current);
+    let stdin = io::stdin();
+    for line in stdin.lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        match step(&line, current, last) {
+            Ok(Some(number)) => current = number,
+            Ok(None) => break,
+            Err(message) => {
+                println!("{}", message);
+                continue;
+            }
+        }
+        show(&game, &positions, current);
+    }
+    Ok(())
+}
+
+/***********************************************************
+ * SHOW
+ ***********************************************************
+ * Draw the board after the given number of moves, then
+ * say which move led to it and what it flipped.
+***********************************************************/
+fn show(game: &Game, positions: &[(Board, bool)], current: usize) {
+    let (board, is_player_one) = &positions[current];
+    let before = &positions[current.saturating_sub(1)].0;
+    display_highlighted(board, &Highlights::between(before, board, &legal_moves(board, *is_player_one)));
+    let (x_count, o_count) = count_pieces(board);
+    if current == 0 {
+        println!("Start of the game ({} moves).", game.moves.len());
+    }
+    else {
+        let (before, mover) = &positions[current - 1];
+        let turn = &game.moves[current - 1];
+        let piece = if *mover { _X } else { _O };
+        let flipped: Vec<String> = flips(before, board, *mover).into_iter().map(coordinates).collect();
+        print!("Move {} of {}: {} {} ({:.1}s)", current, game.moves.len(), piece,
+               move_text(&turn.mv), turn.time.as_secs_f64());
+        if flipped.is_empty() {
+            println!();
+        }
+        else {
+            println!(", flipping {}", flipped.join(" "));
+        }
+    }
+    println!("X: {}  O: {}  {} to move", x_count, o_count, if *is_player_one { _X } else { _O });
+}
+
+/***********************************************************
+ * FLIPS
+ ***********************************************************
+ * The locations whose pieces the move between two boards
+ * turned over to the mover's piece.
+***********************************************************/
+fn flips(before: &Board, after: &Board, is_player_one: bool) -> Vec<NumLet> {
+    let (piece, other) = if is_player_one { (_X, _O) } else { (_O, _X) };
+    let mut v = Vec::new();
+    for letter in 0..before.size() {
+        for num in 0..before.size() {
+            if before[letter][num] == other && after[letter][num] == piece {
+                v.push(NumLet { n: num, l: letter });
+            }
+        }
+    }
+    v
+}
+
+/***********************************************************
+ * STEP
+ ***********************************************************
+ * The move number a command moves to from the current one,
+ * None if the command is to quit, or what to tell the
+ * user if it can't be followed.
+***********************************************************/
+fn step(command: &str, current: usize, last: usize) -> Result<Option<usize>, String> {
+    match command.trim().to_lowercase().as_str() {
+        "" | "n" | "next" if current == last => Err("That was the last move.".to_string()),
+        "" | "n" | "next" => Ok(Some(current + 1)),
+        "b" | "back" if current == 0 => Err("That is the start of the game.".to_string()),
+        "b" | "back" => Ok(Some(current - 1)),
+        "start" => Ok(Some(0)),
+        "end" => Ok(Some(last)),
+        "q" | "quit" => Ok(None),
+        text => match text.parse::<usize>() {
+            Ok(number) if number <= last => Ok(Some(number)),
+            Ok(_) => Err(format!("The game only has {} moves.", last)),
+            Err(_) => Err("Type n (next), b (back), a move number, start, end or q (quit).".to_string()),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use crate::player::Move;
+
+    #[test]
+    fn commands_step_through_the_moves() {
+        assert_eq!(step("", 0, 3), Ok(Some(1)));
+        assert_eq!(step(" N ", 1, 3), Ok(Some(2)));
+        assert_eq!(step("back", 2, 3), Ok(Some(1)));
+        assert_eq!(step("b", 1, 3), Ok(Some(0)));
+        assert_eq!(step("end", 0, 3), Ok(Some(3)));
+        assert_eq!(step("start", 3, 3), Ok(Some(0)));
+        assert_eq!(step("2", 0, 3), Ok(Some(2)));
+        assert_eq!(step("0", 3, 3), Ok(Some(0)));
+        assert_eq!(step("q", 1, 3), Ok(None));
+        assert_eq!(step("Quit", 1, 3), Ok(None));
+    }
+
+    #[test]
+    fn stepping_past_either_end_is_refused() {
+        assert_eq!(step("next", 3, 3), Err("That was the last move.".to_string()));
+        assert_eq!(step("b", 0, 3), Err("That is the start of the game.".to_string()));
+        assert_eq!(step("4", 0, 3), Err("The game only has 3 moves.".to_string()));
+        assert!(step("sideways", 0, 3).is_err());
+    }
+
+    #[test]
+    fn flips_are_the_pieces_the_move_turned() {
+        let mut game = Game::new(4, ["X".to_string(), "O".to_string()]);
+        game.play(Move::Place(NumLet { n: 3, l: 2 }), true, Duration::from_secs(1));
+        game.play(Move::Place(NumLet { n: 3, l: 3 }), false, Duration::from_secs(1));
+        let positions = game.positions().unwrap();
+        let (before, mover) = &positions[0];
+        let (after, _) = &positions[1];
+        let flipped: Vec<String> = flips(before, after, *mover).into_iter().map(coordinates).collect();
+        assert_eq!(flipped, ["C3"]);
+        assert!(flips(before, before, *mover).is_empty());
+    }
+}
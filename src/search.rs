@@ -0,0 +1,336 @@
+/***********************************************************
+ * POSITION SEARCH
+ ***********************************************************
+ * With "--search POSITION" the program looks for a
+ * position in a collection of games and lists every game
+ * that reached it, with the move played next and the
+ * game's result. The collection is made of:
+ *     --games FILE        Saved games: transcripts, JSON
+ *                         records, or GGF files (every game
+ *                         in them). Can be given many times.
+ *     --wthor FILE.wtb    WTHOR archive files, with their
+ *                         names found as for listing them.
+ * A game also matches if it reached the position turned
+ * or reflected (any of the board's 8 symmetries), with the
+ * same player to move. Next moves are shown as they would
+ * be played on the position searched for, so games found
+ * under different symmetries can be compared, and a
+ * summary counts each next move with how the games went.
+***********************************************************/
+
+use std::fs;
+use crate::game::Game;
+use crate::player::Move;
+use crate::{Board, NumLet, _X, _O, coordinates, count_pieces, ggf, legal_moves, wthor};
+
+// The symmetries of the board, by name, in the order
+// they are tried
+static SYMMETRIES: [&str; 8] = ["as played", "turned 90 degrees", "turned 180 degrees",
+                                "turned 270 degrees", "flipped left to right",
+                                "flipped top to bottom", "flipped on the diagonal through A1",
+                                "flipped on the other diagonal"];
+
+/***********************************************************
+ * TRANSFORM
+ ***********************************************************
+ * Where a location goes under one of the symmetries (an
+ * index into SYMMETRIES) on a size*size board.
+***********************************************************/
+pub fn transform(nl: NumLet, symmetry: usize, size: usize) -> NumLet {
+    let last = size - 1;
+    let (n, l) = match symmetry {
+        0 => (nl.n, nl.l),
+        1 => (last - nl.l, nl.n),
+        2 => (last - nl.n, last - nl.l),
+        3 => (nl.l, last - nl.n),
+        4 => (last - nl.n, nl.l),
+        5 => (nl.n, last - nl.l),
+        6 => (nl.l, nl.n),
+        _ => (last - nl.l, last - nl.n),
+    };
+    NumLet { n, l }
+}
+
+/***********************************************************
+ * TRANSFORM_BOARD
+ ***********************************************************
+ * A whole board moved by one of the symmetries.
+***********************************************************/
+pub fn transform_board(board: &Board, symmetry: usize) -> Board {
+    let size = board.size();
+    let mut moved = Board::new(size);
+    for l in 0..size {
+        for n in 0..size {
+            let to = transform(NumLet { n, l }, symmetry, size);
+            moved[to.l][to.n] = board[l][n];
+        }
+    }
+    moved
+}
+
+/***********************************************************
+ * STORED_GAME
+ ***********************************************************
+ * A game from the collection and how to refer to it.
+***********************************************************/
+pub struct StoredGame {
+    pub label: String,      // The file, the game's number in it and the players
+    pub game: Game
+}
+
+/***********************************************************
+ * HIT
+ ***********************************************************
+ * A game that reached the position searched for.
+***********************************************************/
+pub struct Hit<'a> {
+    pub stored: &'a StoredGame,     // The game
+    pub moves: usize,               // Number of moves played before the position
+    pub symmetry: usize,            // How the game's board was moved to match
+    pub next: Option<Move>,         // The move played next, turned to match
+    pub result: Option<i32>         // "X"'s discs minus "O"'s at the end, if known
+}
+
+/***********************************************************
+ * LOAD_COLLECTION
+ ***********************************************************
+ * Read every game in the files. WTHOR names come from jou
+ * and trn or from next to the first .wtb file.
+***********************************************************/
+pub fn load_collection(files: &[String], wtb: &[String], jou: Option<&str>, trn: Option<&str>)
+                       -> Result<Vec<StoredGame>, String> {
+    let mut collection = Vec::new();
+    let players = |game: &Game| match &game.info.names {
+        [Some(black), Some(white)] => format!(" ({} - {})", black, white),
+        _ => String::new(),
+    };
+    for path in files {
+        let text = fs::read_to_string(path).map_err(|e| format!("Couldn't read \"{}\": {}", path, e))?;
+        if text.trim_start().starts_with("(;") {
+            for (i, game) in ggf::parse(&text)?.into_iter().enumerate() {
+                let label = format!("{} #{}{}", path, i + 1, players(&game));
+                collection.push(StoredGame { label, game });
+            }
+        }
+        else {
+            let game = Game::load(path)?;
+            collection.push(StoredGame { label: format!("{}{}", path, players(&game)), game });
+        }
+    }
+    if let Some(first) = wtb.first() {
+        let (names, tournaments) = wthor::read_all_names(first, jou, trn)?;
+        for path in wtb {
+            let (games, _) = wthor::read_games(path, &names, &tournaments)?;
+            for (i, wthor) in games.into_iter().enumerate() {
+                let label = format!("{} #{} ({} {}: {} - {})", path, i + 1, wthor.year,
+                                    wthor.tournament, wthor.black, wthor.white);
+                collection.push(StoredGame { label, game: wthor.game });
+            }
+        }
+    }
+    Ok(collection)
+}
+
+/***********************************************************
+ * SEARCH
+ ***********************************************************
+ * Find every game in the collection that reached the
+ * board with the given player to move, under any of the
+ * symmetries. A game is only listed once, for the first
+ * symmetry that matches.
+***********************************************************/
+pub fn search<'a>(collection: &'a [StoredGame], board: &Board, is_player_one: bool) -> Vec<Hit<'a>> {
+    let targets: Vec<Board> = (0..SYMMETRIES.len()).map(|s| transform_board(board, inverse(s))).collect();
+    let mut hits = Vec::new();
+    for stored in collection.iter().filter(|s| s.game.size == board.size()) {
+        let positions = match stored.game.positions() {
+            Ok(positions) => positions,
+            Err(_) => continue,                 // Games that break the rules can't be searched
+        };
+        let found = positions.iter().enumerate().find_map(|(i, (position, player))| {
+            if *player != is_player_one {
+                return None;
+            }
+            targets.iter().position(|t| t == position).map(|symmetry| (i, symmetry))
+        });
+        if let Some((moves, symmetry)) = found {
+            let next = stored.game.moves.get(moves).map(|turn| match turn.mv {
+                Move::Place(nl) => Move::Place(transform(nl, symmetry, board.size())),
+                ref mv => mv.clone(),
+            });
+            hits.push(Hit { stored, moves, symmetry, next, result: result(&stored.game) });
+        }
+    }
+    hits
+}
+
+/***********************************************************
+ * INVERSE
+ ***********************************************************
+ * The symmetry that undoes another: the quarter turns undo
+ * each other and everything else undoes itself.
+***********************************************************/
+fn inverse(symmetry: usize) -> usize {
+    match symmetry {
+        1 => 3,
+        3 => 1,
+        s => s,
+    }
+}
+
+/***********************************************************
+ * RESULT
+ ***********************************************************
+ * "X"'s discs minus "O"'s at the end of a game: from the
+ * final board if the game was played out, 64 (or the
+ * number of locations) for a resignation, otherwise the
+ * result the game was stored with, if it has one.
+***********************************************************/
+pub fn result(game: &Game) -> Option<i32> {
+    let (board, is_player_one) = game.position().ok()?;
+    if game.moves.last().map(|t| &t.mv) == Some(&Move::Resign) {
+        let all = (game.size * game.size) as i32;
+        return Some(if is_player_one { all } else { -all });  // The player before resigned
+    }
+    if legal_moves(&board, true).is_empty() && legal_moves(&board, false).is_empty() {
+        let (x_count, o_count) = count_pieces(&board);
+        return Some(x_count as i32 - o_count as i32);
+    }
+    let text = game.info.result.as_ref()?;
+    let number: String = text.chars().take_while(|c| *c == '+' || *c == '-' || *c == '.' || c.is_ascii_digit())
+        .collect();
+    number.parse::<f64>().ok().map(|r| r.round() as i32)
+}
+
+/***********************************************************
+ * RUN
+ ***********************************************************
+ * Search the collection for a position and print every
+ * game found, then each next move with the number of
+ * games and how they ended.
+***********************************************************/
+pub fn run(position: &(Board, bool), files: &[String], wtb: &[String],
+           jou: Option<&str>, trn: Option<&str>) -> Result<(), String> {
+    let collection = load_collection(files, wtb, jou, trn)?;
+    let (board, is_player_one) = position;
+    let hits = search(&collection, board, *is_player_one);
+    let piece = if *is_player_one { _X } else { _O };
+    for hit in hits.iter() {
+        println!("{}", hit.stored.label);
+        println!("    after move {}, {}: next {} {}, result {}", hit.moves, SYMMETRIES[hit.symmetry],
+                 piece, next_text(&hit.next), result_text(hit.result));
+    }
+    println!("{} of {} game(s) reached the position.", hits.len(), collection.len());
+
+    // Each next move in the order first seen, with the
+    // games it was played in
+    let mut summary: Vec<(String, Vec<Option<i32>>)> = Vec::new();
+    for hit in hits.iter() {
+        let next = next_text(&hit.next);
+        match summary.iter_mut().find(|(mv, _)| *mv == next) {
+            Some((_, results)) => results.push(hit.result),
+            None => summary.push((next, vec![hit.result])),
+        }
+    }
+    summary.sort_by_key(|(_, results)| std::cmp::Reverse(results.len()));
+    for (mv, results) in summary.iter() {
+        let wins = |won: fn(i32) -> bool| results.iter().filter(|r| r.is_some_and(won)).count();
+        println!("{:>6}  {} game(s): X won {}, O won {}, drawn {}, unknown {}", mv, results.len(),
+                 wins(|r| r > 0), wins(|r| r < 0), wins(|r| r == 0),
+                 results.iter().filter(|r| r.is_none()).count());
+    }
+    Ok(())
+}
+
+/***********************************************************
+ * NEXT_TEXT / RESULT_TEXT
+ ***********************************************************
+ * How a next move and a result are shown.
+***********************************************************/
+fn next_text(next: &Option<Move>) -> String {
+    match next {
+        Some(Move::Place(nl)) => coordinates(*nl),
+        Some(Move::Pass) => "pass".to_string(),
+        Some(_) => "resign".to_string(),
+        None => "(end)".to_string(),
+    }
+}
+
+fn result_text(result: Option<i32>) -> String {
+    match result {
+        Some(r) => format!("{:+}", r),
+        None => "unknown".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::tests::sample;
+
+    // Every location on a size*size board
+    fn locations(size: usize) -> Vec<NumLet> {
+        (0..size * size).map(|i| NumLet { n: i % size, l: i / size }).collect()
+    }
+
+    #[test]
+    fn symmetries_are_undone_by_their_inverses() {
+        for size in [4, 6, 8] {
+            for symmetry in 0..SYMMETRIES.len() {
+                for nl in locations(size) {
+                    assert_eq!(transform(transform(nl, symmetry, size), inverse(symmetry), size), nl);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn symmetries_are_all_different() {
+        let corner = NumLet { n: 1, l: 0 };        // Next to A1, where no two symmetries agree
+        let mut seen: Vec<NumLet> = (0..SYMMETRIES.len()).map(|s| transform(corner, s, 8)).collect();
+        seen.sort_by_key(|nl| (nl.l, nl.n));
+        seen.dedup();
+        assert_eq!(seen.len(), SYMMETRIES.len());
+        let quarter = |nl| transform(nl, 1, 8);
+        for nl in locations(8) {
+            assert_eq!(quarter(quarter(nl)), transform(nl, 2, 8));
+            assert_eq!(quarter(quarter(quarter(nl))), transform(nl, 3, 8));
+        }
+    }
+
+    #[test]
+    fn moved_boards_keep_their_legal_moves() {
+        let (board, is_player_one) = sample().positions().unwrap()[5].clone();
+        for symmetry in 0..SYMMETRIES.len() {
+            let moved = transform_board(&board, symmetry);
+            let mut legal: Vec<NumLet> = legal_moves(&board, is_player_one).into_iter()
+                .map(|nl| transform(nl, symmetry, board.size())).collect();
+            let mut moved_legal = legal_moves(&moved, is_player_one);
+            legal.sort_by_key(|nl| (nl.l, nl.n));
+            moved_legal.sort_by_key(|nl| (nl.l, nl.n));
+            assert_eq!(legal, moved_legal);
+        }
+    }
+
+    #[test]
+    fn games_are_found_under_any_symmetry() {
+        let game = sample();
+        let (board, is_player_one) = game.positions().unwrap()[3].clone();
+        let collection = [StoredGame { label: "sample".to_string(), game: game.clone() }];
+        for symmetry in 0..SYMMETRIES.len() {
+            let hits = search(&collection, &transform_board(&board, symmetry), is_player_one);
+            assert_eq!(hits.len(), 1);
+            assert_eq!(hits[0].moves, 3);
+            assert_eq!(transform_board(&board, hits[0].symmetry), transform_board(&board, symmetry));
+            let played = game.positions().unwrap()[4].0.clone();
+            let mut expected = transform_board(&board, symmetry);
+            match hits[0].next {
+                Some(Move::Place(nl)) => crate::place(&mut expected, nl, is_player_one),
+                _ => panic!("the next move should be a placement"),
+            };
+            assert_eq!(expected, transform_board(&played, hits[0].symmetry));
+            assert_eq!(hits[0].result, result(&game));
+        }
+        assert!(search(&collection, &board, !is_player_one).is_empty());
+    }
+}
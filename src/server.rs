@@ -0,0 +1,856 @@
+/***********************************************************
+ * MULTI-GAME SERVER
+ ***********************************************************
+ * The server program (othello-server) lets many people
+ * connect at once and play each other in as many games as
+ * they like at the same time. Everyone connects with
+ * "--connect HOST:PORT" and starts in the lobby, where
+ * they type commands, one per line:
+ *     name <name> [password]
+ *                         Choose the name others see; a
+ *                         registered name needs its
+ *                         password.
+ *     register <password> Register the name chosen, to
+ *                         play rated games (see
+ *                         ratings.rs).
+ *     list                The games waiting for a player
+ *                         and the games being played:
+ *                         "game <id> <size> <X> waiting",
+ *                         "game <id> <size> <X> matching"
+ *                         (waiting for a rated opponent) or
+ *                         "game <id> <size> <X> <O> playing
+ *                         [rated]".
+ *     create [size]       Create a game (8x8 unless a size
+ *                         is given) and wait for someone to
+ *                         join it. The creator plays X.
+ *                         While waiting, "cancel" gives the
+ *                         game up and sends "lobby".
+ *     join <id>           Join a waiting game and play O.
+ *     match [size]        Play a rated game against the
+ *                         waiting registered player rated
+ *                         nearest, within WINDOW points
+ *                         (more the longer they have
+ *                         waited), or wait for one (until
+ *                         "cancel", as for create).
+ *     ratings             The ladder, highest first:
+ *                         "rating <rank> <name> <rating>
+ *                         <games> <wins> <losses> <draws>".
+ *     watch <id>          Watch a game as a spectator.
+ *     resume <token>      Go back to a game after the
+ *                         connection to it dropped (see
+ *                         network.rs).
+ *     quit                Leave the server.
+ * Every command is answered with "ok [message]" or
+ * "error <message>", after any lines it lists.
+ *
+ * Each game is refereed by the server, exactly as the host
+ * referees in network play (see network.rs): both players
+ * are RemotePlayers, every move is checked against the
+ * rules and the game is recorded. The players can chat
+ * during the game and agree to a draw (see Chat in
+ * network.rs). When it's over they have REMATCH to agree
+ * to a rematch with the pieces swapped; otherwise both are
+ * sent "lobby" and can create or join another. After each
+ * rated game both ratings are updated and saved, and the
+ * players are told their new ratings.
+ *
+ * Spectators (only the server has them; games played with
+ * "--host" have none) are listened to by the game's
+ * broadcaster, never by the game, so nothing they send can
+ * reach it. The one line they can send is "leave", to go
+ * back to the lobby before the game is over; anything else
+ * is answered with "error <message>". They are sent, as it
+ * happens but held back by the server's delay (so nobody
+ * watching can help a player during the game):
+ *     watching <size> <X> <O>
+ *                         The game has started, or is
+ *                         already being played.
+ *     position <cells> <X|O>
+ *                         The board, whenever it changes.
+ *     played <X|O> <location|pass|resign>
+ *                         What a player did.
+ *     chat <name> <text>  What a player shouted.
+ *     over <message>      The game has ended, and how.
+ *     lobby               Back to the lobby, once the game
+ *                         is over or abandoned, or they
+ *                         leave.
+***********************************************************/
+
+use std::net::TcpListener;
+use std::collections::VecDeque;
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, SendError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::game::Game;
+use crate::network::{self, Chat, Connection, Received, RemotePlayer, Sessions};
+use crate::ratings::Ratings;
+use crate::player::{Move, Player};
+use crate::position::position_string;
+use crate::{MAX_SIZE, _X, _O, coordinates, count_pieces, legal_moves, place, valid_size};
+
+// How long the players have to agree to a rematch
+const REMATCH: Duration = Duration::from_secs(15);
+
+// How far apart in rating players can be matched, and how
+// much further for every WIDEN_AFTER the longer has waited
+const WINDOW: f64 = 100.0;
+const WIDEN: f64 = 50.0;
+const WIDEN_AFTER: Duration = Duration::from_secs(10);
+
+// How often a game's spectators are listened to
+const LISTEN: Duration = Duration::from_millis(250);
+
+/***********************************************************
+ * SEAT
+ ***********************************************************
+ * A player taking the empty side of a waiting game: their
+ * connection, their name, and where to hand the connection
+ * back once the game is over (None if it dropped).
+***********************************************************/
+struct Seat {
+    connection: Connection,
+    name: String,
+    back: Sender<Option<Connection>>
+}
+
+/***********************************************************
+ * EVENT
+ ***********************************************************
+ * What a game's broadcaster is told: a spectator to add,
+ * with where to hand their connection back, or a line
+ * for every spectator.
+***********************************************************/
+enum Event {
+    Spectator(Connection, Sender<Option<Connection>>),
+    Line(String)
+}
+
+/***********************************************************
+ * TABLE
+ ***********************************************************
+ * A game in the lobby, waiting for a player or being
+ * played.
+***********************************************************/
+struct Table {
+    id: usize,                      // What players join it by
+    size: usize,                    // The board size
+    names: [Option<String>; 2],     // Who plays "X" and "O"
+    seat: Option<Sender<Seat>>,     // How to sit down, while it's waiting
+    events: Sender<Event>,          // The game's broadcaster
+    rating: Option<f64>,            // The creator's rating, for a rated game
+    since: Instant                  // When it was created
+}
+
+/***********************************************************
+ * LOBBY
+ ***********************************************************
+ * Every game on the server, shared by the connections'
+ * threads.
+***********************************************************/
+#[derive(Default)]
+struct Lobby {
+    tables: Vec<Table>,
+    next_id: usize,                 // The id of the last game created
+    delay: Duration,                // How long spectators are kept behind
+    grace: Duration,                // How long players get to come back
+    sessions: Sessions,             // The games players can come back to
+    ratings: Ratings                // The registered players' ratings
+}
+
+/***********************************************************
+ * RUN
+ ***********************************************************
+ * Listen on the address and give every connection its own
+ * thread in the lobby. Spectators see everything the delay
+ * after it happens, players whose connection drops have
+ * the grace period to come back, and rated games update
+ * the ratings.
+***********************************************************/
+pub fn run(address: &str, delay: Duration, grace: Duration, ratings: Ratings) -> Result<(), String> {
+    let listener = TcpListener::bind(address)
+        .map_err(|e| format!("Couldn't listen on {}: {}", address, e))?;
+    println!("Othello server listening on {}.", address);
+    let lobby = Arc::new(Mutex::new(Lobby { delay, grace, ratings, ..Lobby::default() }));
+    for stream in listener.incoming() {
+        let connection = match stream.map_err(|e| e.to_string()).and_then(|s| Connection::new(s).map_err(|e| e.to_string())) {
+            Ok(connection) => connection,
+            Err(message) => {
+                println!("A connection failed: {}", message);
+                continue;
+            }
+        };
+        let lobby = Arc::clone(&lobby);
+        thread::spawn(move || visit(connection, lobby));
+    }
+    Ok(())
+}
+
+/***********************************************************
+ * VISIT
+ ***********************************************************
+ * Answer one connection's lobby commands until it quits or
+ * drops, playing the games it creates or joins.
+***********************************************************/
+fn visit(mut connection: Connection, lobby: Arc<Mutex<Lobby>>) {
+    let peer = connection.peer();
+    let mut name = peer.clone();        // Until they choose one
+    let mut signed_in = false;          // True once they have proved a registered name is theirs
+    println!("{} connected.", name);
+
+    while let Some(line) = connection.read_line() {
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or("");
+        let argument = words.next();
+        let answer = match (command, argument) {
+            ("name", Some(chosen)) => {
+                let password = words.next().unwrap_or("");
+                let (registered, correct) = {
+                    let ratings = &lobby.lock().unwrap().ratings;
+                    (ratings.is_registered(chosen), ratings.check(chosen, password))
+                };
+                if registered && !correct {
+                    format!("error {} is registered; give its password after the name.", chosen)
+                }
+                else {
+                    println!("{} is now called {}.", name, chosen);
+                    name = chosen.to_string();
+                    signed_in = registered;
+                    format!("ok Your name is {}.", name)
+                }
+            }
+            ("register", Some(_)) if name == peer => "error Choose a name with the name command first.".to_string(),
+            ("register", Some(password)) => match lobby.lock().unwrap().ratings.register(&name, password) {
+                Ok(()) => {
+                    println!("{} has registered.", name);
+                    signed_in = true;
+                    format!("ok {} is registered, rated {}.", name, crate::ratings::START)
+                }
+                Err(message) => format!("error {}", message),
+            },
+            ("ratings", _) => {
+                let lines: Vec<String> = lobby.lock().unwrap().ratings.ladder().iter().enumerate()
+                    .map(|(i, (player, r))| format!("rating {} {} {:.0} {} {} {} {}", i + 1, player, r.rating,
+                                                    r.games, r.wins, r.losses, r.draws)).collect();
+                for line in lines {
+                    if connection.send(&line).is_err() {
+                        break;
+                    }
+                }
+                "ok".to_string()
+            }
+            ("list", _) => {
+                for line in list(&lobby) {
+                    if connection.send(&line).is_err() {
+                        break;
+                    }
+                }
+                "ok".to_string()
+            }
+            ("create", size) => match size.map_or(Ok(MAX_SIZE), |s| s.parse::<usize>()) {
+                Ok(size) if valid_size(size) => {
+                    let (id, seats, events) = create(&lobby, size, &name, None);
+                    if connection.send(&format!("ok Created game {}.", id)).is_err() {
+                        close(&lobby, id);
+                        break;
+                    }
+                    match host(connection, &name, id, size, seats, events, &lobby) {
+                        Some(back) => connection = back,
+                        None => break,              // They left
+                    }
+                    continue;
+                }
+                _ => format!("error The size must be even and from 4 to {}.", MAX_SIZE),
+            },
+            ("join", Some(id)) => match id.parse().ok().and_then(|id| sit(&lobby, id, &name)) {
+                Some(seat) => {
+                    if connection.send(&format!("ok Joined game {}.", id)).is_err() {
+                        break;
+                    }
+                    match take_seat(connection, &name, seat) {
+                        Some(back) => connection = back,
+                        None => break,              // They left during the game
+                    }
+                    continue;
+                }
+                None => format!("error There is no game {} waiting for a player.", id),
+            },
+            ("match", _) if !signed_in => {
+                "error Register (or give your name's password) to play rated games.".to_string()
+            }
+            ("match", size) => match size.map_or(Ok(MAX_SIZE), |s| s.parse::<usize>()) {
+                Ok(size) if valid_size(size) => {
+                    let rating = lobby.lock().unwrap().ratings.rating(&name).unwrap_or(crate::ratings::START);
+                    let back = match pair(&lobby, None, size, rating, &name) {
+                        Some((id, seat)) => {
+                            if connection.send(&format!("ok Matched in game {}.", id)).is_err() {
+                                break;
+                            }
+                            take_seat(connection, &name, seat)
+                        }
+                        None => {
+                            let (id, seats, events) = create(&lobby, size, &name, Some(rating));
+                            let waiting = format!("ok Waiting in game {} for an opponent rated near {:.0}.",
+                                                  id, rating);
+                            if connection.send(&waiting).is_err() {
+                                close(&lobby, id);
+                                break;
+                            }
+                            host(connection, &name, id, size, seats, events, &lobby)
+                        }
+                    };
+                    match back {
+                        Some(back) => connection = back,
+                        None => break,              // They left
+                    }
+                    continue;
+                }
+                _ => format!("error The size must be even and from 4 to {}.", MAX_SIZE),
+            },
+            ("watch", Some(id)) => match id.parse().ok().and_then(|id| watch(&lobby, id)) {
+                Some(events) => {
+                    if connection.send(&format!("ok Watching game {}.", id)).is_err() {
+                        break;
+                    }
+                    let (back, returned) = channel();
+                    let sent = events.send(Event::Spectator(connection, back));
+                    drop(events);                   // Only the game keeps its broadcaster going
+                    match sent {
+                        Ok(()) => match returned.recv().ok().flatten() {
+                            Some(back) => connection = back,
+                            None => break,          // They left while watching
+                        },
+                        Err(SendError(Event::Spectator(back, _))) => {  // The game has just ended
+                            connection = back;
+                            if connection.send("lobby").is_err() {
+                                break;
+                            }
+                        }
+                        Err(_) => break,
+                    }
+                    continue;
+                }
+                None => format!("error There is no game {}.", id),
+            },
+            ("resume", Some(token)) => {
+                let sessions = Arc::clone(&lobby.lock().unwrap().sessions);
+                match network::resume(&sessions, token, connection) {
+                    Ok(()) => {
+                        println!("{} is back in their game.", name);
+                        return;                     // The game's thread has the connection now
+                    }
+                    Err(back) => {
+                        connection = back;
+                        "error No game is waiting for that token.".to_string()
+                    }
+                }
+            }
+            ("quit", _) => break,
+            _ => "error Commands are name, register, list, create, join, match, watch, ratings, resume and quit."
+                .to_string(),
+        };
+        if connection.send(&answer).is_err() {
+            break;
+        }
+    }
+    println!("{} left.", name);
+}
+
+/***********************************************************
+ * LIST
+ ***********************************************************
+ * A line for every game on the server.
+***********************************************************/
+fn list(lobby: &Mutex<Lobby>) -> Vec<String> {
+    let lobby = lobby.lock().unwrap();
+    lobby.tables.iter().map(|table| match (&table.names, table.rating) {
+        ([Some(x), Some(o)], None) => format!("game {} {} {} {} playing", table.id, table.size, x, o),
+        ([Some(x), Some(o)], Some(_)) => format!("game {} {} {} {} playing rated", table.id, table.size, x, o),
+        ([x, _], rating) => format!("game {} {} {} {}", table.id, table.size, x.as_deref().unwrap_or("?"),
+                                    if rating.is_some() { "matching" } else { "waiting" }),
+    }).collect()
+}
+
+/***********************************************************
+ * CREATE / SIT / WATCH / CLOSE / ABANDON
+ ***********************************************************
+ * Add a waiting game to the lobby (a rated one if the
+ * creator's rating is given) and start its broadcaster,
+ * returning its id, where the player who joins it will
+ * arrive and where to send what happens; take the empty
+ * side of a waiting game that isn't rated; find where to
+ * send a spectator; remove a game once it is over; and
+ * remove one its creator has left before it started,
+ * sending back anyone who joined it as they left.
+***********************************************************/
+fn create(lobby: &Mutex<Lobby>, size: usize, name: &str,
+          rating: Option<f64>) -> (usize, Receiver<Seat>, Sender<Event>) {
+    let mut lobby = lobby.lock().unwrap();
+    let (seat, seats) = channel();
+    let (events, received) = channel();
+    let delay = lobby.delay;
+    thread::spawn(move || broadcast(received, delay));
+    lobby.next_id += 1;
+    let id = lobby.next_id;
+    lobby.tables.push(Table { id, size, names: [Some(name.to_string()), None], seat: Some(seat),
+                              events: events.clone(), rating, since: Instant::now() });
+    (id, seats, events)
+}
+
+fn sit(lobby: &Mutex<Lobby>, id: usize, name: &str) -> Option<Sender<Seat>> {
+    let mut lobby = lobby.lock().unwrap();
+    let table = lobby.tables.iter_mut().find(|t| t.id == id && t.rating.is_none())?;
+    let seat = table.seat.take()?;          // Nobody else can join it now
+    table.names[1] = Some(name.to_string());
+    Some(seat)
+}
+
+fn watch(lobby: &Mutex<Lobby>, id: usize) -> Option<Sender<Event>> {
+    let lobby = lobby.lock().unwrap();
+    lobby.tables.iter().find(|t| t.id == id).map(|t| t.events.clone())
+}
+
+fn close(lobby: &Mutex<Lobby>, id: usize) {
+    lobby.lock().unwrap().tables.retain(|t| t.id != id);
+}
+
+fn abandon(lobby: &Mutex<Lobby>, id: usize, seats: &Receiver<Seat>) {
+    close(lobby, id);
+    if let Ok(mut seat) = seats.try_recv() {
+        let sent = seat.connection.send("lobby").is_ok();
+        let _ = seat.back.send(if sent { Some(seat.connection) } else { None });
+    }
+}
+
+/***********************************************************
+ * PAIR
+ ***********************************************************
+ * Find the rated game of the size waiting for a player
+ * rated nearest the rating, within WINDOW (widened for
+ * however long the game, or the player's own game, has
+ * waited), and take its empty side. A player already
+ * waiting in a game of their own (mine) gives it up for
+ * an older one, so that two waiting players are only
+ * paired once.
+***********************************************************/
+fn pair(lobby: &Mutex<Lobby>, mine: Option<usize>, size: usize, rating: f64,
+        name: &str) -> Option<(usize, Sender<Seat>)> {
+    let mut lobby = lobby.lock().unwrap();
+    let window = |since: Instant| WINDOW + WIDEN * (since.elapsed().as_secs() / WIDEN_AFTER.as_secs()) as f64;
+    let own = match mine {
+        Some(id) => {
+            let table = lobby.tables.iter().find(|t| t.id == id && t.seat.is_some())?;
+            Some((id, table.since))
+        }
+        None => None,
+    };
+    let found = lobby.tables.iter()
+        .filter(|t| t.size == size && t.seat.is_some() && t.id < own.map_or(usize::MAX, |(id, _)| id))
+        .filter_map(|t| t.rating.map(|r| (t, (r - rating).abs())))
+        .filter(|(t, gap)| *gap <= window(t.since).max(own.map_or(0.0, |(_, since)| window(since))))
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(t, _)| t.id)?;
+    if let Some((id, _)) = own {
+        lobby.tables.retain(|t| t.id != id);    // Nobody can sit at it now
+    }
+    let table = lobby.tables.iter_mut().find(|t| t.id == found)?;
+    table.names[1] = Some(name.to_string());
+    table.seat.take().map(|seat| (found, seat))
+}
+
+/***********************************************************
+ * TAKE_SEAT
+ ***********************************************************
+ * Sit down at a waiting game and play it, returning the
+ * connection once it is over, or None if it dropped.
+***********************************************************/
+fn take_seat(connection: Connection, name: &str, seat: Sender<Seat>) -> Option<Connection> {
+    let (back, returned) = channel();
+    match seat.send(Seat { connection, name: name.to_string(), back }) {
+        Ok(()) => returned.recv().ok().flatten(),
+        Err(SendError(seat)) => {           // The creator has just left
+            let mut connection = seat.connection;
+            connection.send("lobby").ok().map(|_| connection)
+        }
+    }
+}
+
+/***********************************************************
+ * HOST
+ ***********************************************************
+ * Wait for someone to join the creator's game, then
+ * referee it (and any rematches) and send both players
+ * back to the lobby. Returns the creator's connection, or
+ * None if they left.
+***********************************************************/
+fn host(mut connection: Connection, name: &str, id: usize, size: usize, seats: Receiver<Seat>,
+        events: Sender<Event>, lobby: &Mutex<Lobby>) -> Option<Connection> {
+    // Wait for the other player, giving up on the game if
+    // the creator leaves or cancels first
+    let rating = lobby.lock().unwrap().tables.iter().find(|t| t.id == id).and_then(|t| t.rating);
+    let seat = loop {
+        match seats.recv_timeout(Duration::from_millis(250)) {
+            Ok(seat) => break seat,
+            Err(RecvTimeoutError::Timeout) => match connection.poll_line(Some(Duration::from_millis(0))) {
+                Received::Line(line) if line.trim() == "cancel" => {
+                    abandon(lobby, id, &seats);
+                    println!("Game {}: {} stops waiting.", id, name);
+                    return connection.send("lobby").is_ok().then_some(connection);
+                }
+                Received::Line(_) => {
+                    let _ = connection.send("error Still waiting for an opponent; send cancel to stop.");
+                }
+                Received::Nothing => {
+                    // A rated game can move to an older one as
+                    // the window widens
+                    if let Some((other, seat)) = rating.and_then(|r| pair(lobby, Some(id), size, r, name)) {
+                        println!("Game {}: {} moves to game {}.", id, name, other);
+                        return take_seat(connection, name, seat);
+                    }
+                }
+                Received::Closed => {
+                    abandon(lobby, id, &seats);
+                    return None;
+                }
+            },
+            Err(RecvTimeoutError::Disconnected) => {
+                abandon(lobby, id, &seats);
+                return None;
+            }
+        }
+    };
+
+    println!("Game {}: {} (X) against {} (O) on {}x{}.", id, name, seat.name, size, size);
+    let mut names = [name.to_string(), seat.name.clone()];
+    let (sessions, grace) = {
+        let lobby = lobby.lock().unwrap();
+        (Arc::clone(&lobby.sessions), lobby.grace)
+    };
+    let resumable = |player: RemotePlayer| {
+        if grace.is_zero() { player } else { player.resumable(&sessions, grace) }
+    };
+    let spectators = events.clone();
+    let chat = Chat::new(names.clone(), None,
+                         Some(Box::new(move |line| { let _ = spectators.send(Event::Line(line)); })));
+    let mut players = [resumable(RemotePlayer::new(connection)).chatting(&chat, 0),
+                       resumable(RemotePlayer::new(seat.connection)).chatting(&chat, 1)];
+    let mut swapped = false;                // True when the creator plays O
+    loop {
+        let mut game = Game::new(size, names.clone());
+        let (message, x_score) = referee(&mut game, &mut players, &events, &chat);
+        println!("Game {}: {} after {} moves.", id, message, game.moves.len());
+        if rating.is_some() {
+            rate(lobby, &names, x_score, &chat);
+        }
+        chat.lock().unwrap().invite(REMATCH);
+        if !Chat::wait_rematch(&chat, REMATCH) {
+            break;
+        }
+        players.swap(0, 1);
+        names.swap(0, 1);
+        swapped = !swapped;
+        if let Some(table) = lobby.lock().unwrap().tables.iter_mut().find(|t| t.id == id) {
+            table.names.swap(0, 1);
+        }
+        println!("Game {}: a rematch, {} (X) against {} (O).", id, names[0], names[1]);
+    }
+    chat.lock().unwrap().close();
+    close(lobby, id);
+
+    // Both players go back to the lobby
+    let [x, o] = players;
+    let mut back = [x.into_connection(), o.into_connection()];
+    for connection in back.iter_mut().flatten() {
+        let _ = connection.send("lobby");   // A dropped connection shows up in the lobby
+    }
+    if swapped {
+        back.swap(0, 1);
+    }
+    let [creator, joiner] = back;
+    let _ = seat.back.send(joiner);
+    creator
+}
+
+/***********************************************************
+ * REFEREE
+ ***********************************************************
+ * Play a game between two players, accepting only legal
+ * moves, and return how it ended. The same rules as the
+ * terminal game, without showing anything; spectators are
+ * told everything that happens. The game also ends as
+ * soon as the players agree to a draw in the chat. X's
+ * score (1 for a win, 1/2 for a draw) comes with how it
+ * ended.
+***********************************************************/
+fn referee(game: &mut Game, players: &mut [RemotePlayer; 2], events: &Sender<Event>,
+           chat: &Mutex<Chat>) -> (String, f64) {
+    let drawn = || chat.lock().unwrap().agreed("draw");
+    let tell = |line: String| { let _ = events.send(Event::Line(line)); };
+    let (start, _) = game.start_position();
+    players[0].new_game(&start, true);
+    players[1].new_game(&start, false);
+    let (mut board, mut is_player_one) = game.position().unwrap();
+    tell(format!("watching {} {} {}", game.size, game.players[0], game.players[1]));
+    tell(format!("position {}", position_string(&board, is_player_one)));
+    let mut was_skipped = false;            // True if the last player passed
+    let mut resigned = None;                // The piece of a player who resigned
+    let mut agreed = false;                 // True if the players agreed to a draw
+    chat.lock().unwrap().start();
+
+    while resigned.is_none() && !agreed {
+        agreed = drawn();
+        if agreed {
+            break;
+        }
+        let current = if is_player_one { 0 } else { 1 };
+        let legal = legal_moves(&board, is_player_one);
+        if legal.is_empty() {
+            if was_skipped {
+                break;                      // Neither player can place
+            }
+            game.play(Move::Pass, is_player_one, Duration::from_secs(0));
+            tell(format!("played {} pass", if is_player_one { _X } else { _O }));
+            players[1 - current].opponent_moved(&board, Move::Pass);
+            was_skipped = true;
+        }
+        else {
+            let started = Instant::now();
+            let mv = players[current].choose_move(&board, is_player_one, &legal);
+            agreed = drawn();               // Whatever the answer, the game is drawn
+            if agreed {
+                break;
+            }
+            let mv = match mv {
+                Move::Place(nl) if legal.contains(&nl) => {
+                    place(&mut board, nl, is_player_one);
+                    Move::Place(nl)
+                }
+                _ => {                      // Resigned, or broke the rules
+                    resigned = Some(if is_player_one { _X } else { _O });
+                    Move::Resign
+                }
+            };
+            let time = started.elapsed().saturating_sub(players[current].time_away());
+            game.play(mv.clone(), is_player_one, time);
+            tell(format!("played {} {}", if is_player_one { _X } else { _O }, match mv {
+                Move::Place(nl) => coordinates(nl),
+                _ => "resign".to_string(),
+            }));
+            tell(format!("position {}", position_string(&board, !is_player_one)));
+            players[1 - current].opponent_moved(&board, mv);
+            was_skipped = false;
+        }
+        is_player_one = !is_player_one;
+    }
+
+    chat.lock().unwrap().stop();
+    if agreed {
+        game.info.result = Some("draw agreed".to_string());
+        for player in players.iter_mut() {
+            player.finish(&board, "The game is drawn by agreement.");
+        }
+    }
+    else {
+        players[0].game_over(&board, resigned);
+        players[1].game_over(&board, resigned);
+    }
+    let (x_count, o_count) = count_pieces(&board);
+    let message = match resigned {
+        _ if agreed => "drawn by agreement".to_string(),
+        Some(piece) => format!("{} resigned", piece),
+        None if x_count > o_count => format!("X won {} to {}", x_count, o_count),
+        None if o_count > x_count => format!("O won {} to {}", o_count, x_count),
+        None => format!("drawn at {} each", x_count),
+    };
+    tell(format!("over Game over: {}.", message));
+    let x_score = match resigned {
+        _ if agreed => 0.5,
+        Some(piece) => if piece == _X { 0.0 } else { 1.0 },
+        None if x_count == o_count => 0.5,
+        None => if x_count > o_count { 1.0 } else { 0.0 },
+    };
+    (message, x_score)
+}
+
+/***********************************************************
+ * RATE
+ ***********************************************************
+ * Update and save the ratings of a rated game's players,
+ * and tell them their new ratings.
+***********************************************************/
+fn rate(lobby: &Mutex<Lobby>, names: &[String; 2], x_score: f64, chat: &Mutex<Chat>) {
+    let mut lobby = lobby.lock().unwrap();
+    match lobby.ratings.record_game(&names[0], &names[1], x_score) {
+        Ok(Some(changes)) => {
+            let news: Vec<String> = names.iter().zip(changes).map(|(name, change)| {
+                format!("{} is now rated {:.0} ({:+.0})", name, lobby.ratings.rating(name).unwrap_or(0.0), change)
+            }).collect();
+            println!("{}.", news.join(", "));
+            chat.lock().unwrap().notice(&format!("{}.", news.join(", ")));
+        }
+        Ok(None) => {}
+        Err(message) => println!("{}", message),
+    }
+}
+
+/***********************************************************
+ * BROADCAST
+ ***********************************************************
+ * Pass a game's lines on to its spectators the delay after
+ * they happen. Spectators arriving late are first sent
+ * who is playing and the latest board they may see, and
+ * any who ask to leave are sent back to the lobby at once.
+ * Once the game is gone and everything has been sent,
+ * every spectator is sent back to the lobby.
+***********************************************************/
+fn broadcast(events: Receiver<Event>, delay: Duration) {
+    let mut spectators: Vec<(Connection, Sender<Option<Connection>>)> = Vec::new();
+    let mut waiting: VecDeque<(Instant, String)> = VecDeque::new();    // Lines not yet due
+    let mut seen: [Option<String>; 2] = [None, None];   // The latest "watching" and "position" sent
+    let mut ended = false;                              // True once nothing more can happen
+
+    while !ended || !waiting.is_empty() {
+        // Wait for the next event, until the next line is
+        // due or until it's time to listen to the spectators
+        let wait = waiting.front().map_or(LISTEN, |(due, _)| due.saturating_duration_since(Instant::now()).min(LISTEN));
+        if ended {                                  // Nothing can arrive, so wait for the rest
+            thread::sleep(wait);
+        }
+        else {
+            match events.recv_timeout(wait) {
+                Ok(Event::Line(line)) => waiting.push_back((Instant::now() + delay, line)),
+                Ok(Event::Spectator(mut connection, back)) => {
+                    let caught_up = seen.iter().flatten().all(|line| connection.send(line).is_ok());
+                    if caught_up {
+                        spectators.push((connection, back));
+                    }
+                    else {
+                        let _ = back.send(None);
+                    }
+                }
+                Err(RecvTimeoutError::Disconnected) => ended = true,
+                Err(RecvTimeoutError::Timeout) => {}
+            }
+        }
+
+        // Send back the spectators who leave
+        spectators = spectators.into_iter().filter_map(|(mut connection, back)| {
+            match connection.poll_line(Some(Duration::from_millis(0))) {
+                Received::Nothing => Some((connection, back)),
+                Received::Line(line) if line.trim() == "leave" => {
+                    let sent = connection.send("lobby").is_ok();
+                    let _ = back.send(if sent { Some(connection) } else { None });
+                    None
+                }
+                Received::Line(_) => {
+                    let _ = connection.send("error Spectators can only leave.");
+                    Some((connection, back))
+                }
+                Received::Closed => {
+                    let _ = back.send(None);
+                    None
+                }
+            }
+        }).collect();
+
+        // Send every line that is due
+        while waiting.front().is_some_and(|(due, _)| *due <= Instant::now()) {
+            let (_, line) = waiting.pop_front().unwrap();
+            if line.starts_with("watching") {
+                seen = [Some(line.clone()), None];
+            }
+            else if line.starts_with("position") {
+                seen[1] = Some(line.clone());
+            }
+            spectators.retain_mut(|(connection, back)| {
+                let sent = connection.send(&line).is_ok();
+                if !sent {
+                    let _ = back.send(None);        // They have left
+                }
+                sent
+            });
+        }
+    }
+
+    for (mut connection, back) in spectators {
+        let sent = connection.send("lobby").is_ok();
+        let _ = back.send(if sent { Some(connection) } else { None });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpStream;
+
+    // A visitor to the lobby: where to write their commands
+    // and where to read the answers
+    fn visiting(lobby: &Arc<Mutex<Lobby>>) -> (TcpStream, BufReader<TcpStream>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let visitor = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        let shared = Arc::clone(lobby);
+        thread::spawn(move || visit(Connection::new(stream).unwrap(), shared));
+        visitor.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        (visitor.try_clone().unwrap(), BufReader::new(visitor))
+    }
+
+    // The next line the visitor was sent
+    fn next(answers: &mut BufReader<TcpStream>) -> String {
+        let mut line = String::new();
+        answers.read_line(&mut line).unwrap();
+        line.trim_end().to_string()
+    }
+
+    #[test]
+    fn creators_can_stop_waiting() {
+        let lobby = Arc::new(Mutex::new(Lobby::default()));
+        let (mut visitor, mut answers) = visiting(&lobby);
+        writeln!(visitor, "name alice").unwrap();
+        assert_eq!(next(&mut answers), "ok Your name is alice.");
+        writeln!(visitor, "create 4").unwrap();
+        assert_eq!(next(&mut answers), "ok Created game 1.");
+        assert_eq!(list(&lobby), ["game 1 4 alice waiting"]);
+        writeln!(visitor, "say anyone?").unwrap();
+        assert_eq!(next(&mut answers), "error Still waiting for an opponent; send cancel to stop.");
+        writeln!(visitor, "cancel").unwrap();
+        assert_eq!(next(&mut answers), "lobby");
+        assert!(list(&lobby).is_empty());
+        writeln!(visitor, "list").unwrap();
+        assert_eq!(next(&mut answers), "ok");
+    }
+
+    #[test]
+    fn games_go_when_their_creator_does() {
+        let lobby = Arc::new(Mutex::new(Lobby::default()));
+        let (mut visitor, mut answers) = visiting(&lobby);
+        writeln!(visitor, "create 6").unwrap();
+        assert_eq!(next(&mut answers), "ok Created game 1.");
+        drop(answers);
+        visitor.shutdown(std::net::Shutdown::Both).unwrap();
+        let gone = Instant::now();
+        while !list(&lobby).is_empty() {
+            assert!(gone.elapsed() < Duration::from_secs(5));
+            thread::sleep(Duration::from_millis(50));
+        }
+    }
+
+    #[test]
+    fn spectators_can_leave() {
+        let lobby = Arc::new(Mutex::new(Lobby::default()));
+        let (mut creator, mut created) = visiting(&lobby);
+        writeln!(creator, "create 4").unwrap();
+        assert_eq!(next(&mut created), "ok Created game 1.");
+        let (mut spectator, mut watched) = visiting(&lobby);
+        writeln!(spectator, "watch 1").unwrap();
+        assert_eq!(next(&mut watched), "ok Watching game 1.");
+        writeln!(spectator, "chat go on").unwrap();
+        assert_eq!(next(&mut watched), "error Spectators can only leave.");
+        writeln!(spectator, "leave").unwrap();
+        assert_eq!(next(&mut watched), "lobby");
+        writeln!(spectator, "list").unwrap();
+        assert!(next(&mut watched).ends_with("waiting"));
+        assert_eq!(next(&mut watched), "ok");
+    }
+}
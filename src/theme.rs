@@ -0,0 +1,280 @@
+/***********************************************************
+ * THEME
+ ***********************************************************
+ * How the board looks at the terminal: the characters
+ * drawn for each piece, an empty location and a legal
+ * move, and the colors (see color.rs). The board itself
+ * always holds "X", "O" and " ", and saved games and the
+ * network protocols always use them; only what is drawn
+ * changes. A theme is chosen once at startup from a
+ * settings file and the command line, one setting per
+ * line in the file:
+ *     # ~/.othello.conf
+ *     theme = unicode
+ *     palette = colorblind
+ * The settings are:
+ *     theme = ascii | unicode
+ *         "X", "O" and "." for legal moves (the default),
+ *         or discs: "●" (X), "○" (O) and "·".
+ *     pieces = XO[E]
+ *         Custom characters for X, O and, if given, an
+ *         empty location, for example "@%" or "XO.".
+ *     palette = classic | colorblind | mono | none
+ *         A green board (the default); a blue board with
+ *         orange and grey highlights that doesn't rely on
+ *         telling red from green; no colors, only bold,
+ *         reverse and underline; or plain text.
+ * Later settings override earlier ones, and the command
+ * line ("--theme", "--pieces", "--palette") overrides the
+ * file.
+***********************************************************/
+
+use std::env;
+use std::fs;
+use std::sync::OnceLock;
+
+// The theme everything is drawn with, once chosen
+static CURRENT: OnceLock<Theme> = OnceLock::new();
+
+// The settings file read if "--config" doesn't name one
+static DEFAULT_CONFIG: &str = ".othello.conf";
+
+/***********************************************************
+ * PALETTE
+ ***********************************************************
+ * The ANSI codes (what follows "ESC [") for each part of a
+ * colored board. Backgrounds and foregrounds are kept
+ * apart so a location can combine one of each.
+***********************************************************/
+#[derive(Debug, PartialEq)]
+pub struct Palette {
+    pub name: &'static str,
+    pub grid: &'static str,         // The grid lines, foreground and background
+    pub board: &'static str,        // Behind an ordinary location
+    pub last: &'static str,         // Behind the last move
+    pub flipped: &'static str,      // Behind the discs it flipped
+    pub x: &'static str,            // Player one's pieces
+    pub o: &'static str,            // Player two's pieces
+    pub dot: &'static str,          // A legal move
+    pub empty: &'static str         // Any other empty location
+}
+
+// Every palette, the default first
+pub static PALETTES: [Palette; 3] = [
+    Palette { name: "classic", grid: "30;42", board: "42", last: "43", flipped: "46",
+              x: "1;30", o: "1;97", dot: "93", empty: "30" },
+    Palette { name: "colorblind", grid: "38;5;252;48;5;31", board: "48;5;31", last: "48;5;214",
+              flipped: "4;48;5;240", x: "1;38;5;16", o: "1;38;5;231", dot: "38;5;226",
+              empty: "38;5;252" },
+    Palette { name: "mono", grid: "39;49", board: "49", last: "7", flipped: "4",
+              x: "1", o: "22", dot: "39", empty: "39" },
+];
+
+/***********************************************************
+ * THEME
+ ***********************************************************
+ * The characters and palette the board is drawn with. No
+ * palette means plain text even at a terminal.
+***********************************************************/
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    pub x: String,                  // Drawn for "X"
+    pub o: String,                  // Drawn for "O"
+    pub empty: String,              // Drawn for " "
+    pub dot: String,                // Drawn on a legal move
+    pub palette: Option<&'static Palette>
+}
+
+impl Default for Theme {
+    // "X" and "O" on the classic green board
+    fn default() -> Theme {
+        Theme { x: "X".to_string(), o: "O".to_string(), empty: " ".to_string(), dot: ".".to_string(),
+                palette: Some(&PALETTES[0]) }
+    }
+}
+
+impl Theme {
+    /*******************************************************
+     * GLYPH
+     *******************************************************
+     * What to draw for a location holding piece ("X", "O"
+     * or " ").
+    *******************************************************/
+    pub fn glyph(&self, piece: &str) -> &str {
+        match piece {
+            "X" => &self.x,
+            "O" => &self.o,
+            _ => &self.empty,
+        }
+    }
+
+    /*******************************************************
+     * KEY
+     *******************************************************
+     * Which character is which piece, for themes that don't
+     * draw them as "X" and "O".
+    *******************************************************/
+    pub fn key(&self) -> Option<String> {
+        if self.x == "X" && self.o == "O" {
+            None
+        }
+        else {
+            Some(format!("X is {}, O is {}", self.x, self.o))
+        }
+    }
+
+    /*******************************************************
+     * SET
+     *******************************************************
+     * Change one setting (see the top of this file).
+    *******************************************************/
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "theme" => {
+                let (x, o, dot) = match value {
+                    "ascii" => ("X", "O", "."),
+                    "unicode" => ("\u{25cf}", "\u{25cb}", "\u{b7}"),
+                    _ => return Err(format!("Unknown theme \"{}\" (ascii or unicode)", value)),
+                };
+                self.x = x.to_string();
+                self.o = o.to_string();
+                self.empty = " ".to_string();
+                self.dot = dot.to_string();
+            }
+            "pieces" => {
+                let chars: Vec<char> = value.chars().collect();
+                if !(2..=3).contains(&chars.len()) || chars.iter().any(|c| c.is_control()) {
+                    return Err(format!("Pieces \"{}\" should be two or three characters: X, O and empty",
+                                       value));
+                }
+                if chars[0] == chars[1] || chars.get(2).is_some_and(|e| chars[..2].contains(e)) {
+                    return Err(format!("Pieces \"{}\" need to be different characters", value));
+                }
+                self.x = chars[0].to_string();
+                self.o = chars[1].to_string();
+                if let Some(empty) = chars.get(2) {
+                    self.empty = empty.to_string();
+                }
+            }
+            "palette" => {
+                self.palette = match PALETTES.iter().find(|p| p.name == value) {
+                    Some(palette) => Some(palette),
+                    None if value == "none" => None,
+                    None => {
+                        let names: Vec<&str> = PALETTES.iter().map(|p| p.name).collect();
+                        return Err(format!("Unknown palette \"{}\" ({} or none)", value, names.join(", ")));
+                    }
+                };
+            }
+            _ => return Err(format!("Unknown setting \"{}\" (theme, pieces or palette)", key)),
+        }
+        Ok(())
+    }
+
+    /*******************************************************
+     * LOAD
+     *******************************************************
+     * Apply the settings in a file, one "key = value" to a
+     * line. Blank lines and lines starting with "#" are
+     * skipped.
+    *******************************************************/
+    pub fn load(&mut self, path: &str) -> Result<(), String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("Couldn't read {}: {}", path, e))?;
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line.split_once('=')
+                .ok_or(format!("Line {} of {} isn't \"setting = value\": {}", i + 1, path, line))?;
+            self.set(key.trim(), value.trim())
+                .map_err(|message| format!("Line {} of {}: {}", i + 1, path, message))?;
+        }
+        Ok(())
+    }
+}
+
+/***********************************************************
+ * START / CURRENT
+ ***********************************************************
+ * Choose the theme: the settings file (config, or
+ * ~/.othello.conf if it exists) and then the settings
+ * from the command line, in order; and the theme chosen
+ * (the default if none was).
+***********************************************************/
+pub fn start(config: Option<&str>, settings: &[(String, String)]) -> Result<(), String> {
+    let mut theme = Theme::default();
+    match config {
+        Some(path) => theme.load(path)?,
+        None => if let Some(home) = env::var_os("HOME") {
+            let path = std::path::Path::new(&home).join(DEFAULT_CONFIG);
+            if path.exists() {
+                theme.load(&path.to_string_lossy())?;
+            }
+        },
+    }
+    for (key, value) in settings {
+        theme.set(key, value)?;
+    }
+    let _ = CURRENT.set(theme);     // Only the first choice counts
+    Ok(())
+}
+
+pub fn current() -> &'static Theme {
+    CURRENT.get_or_init(Theme::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_change_the_theme() {
+        let mut theme = Theme::default();
+        theme.set("theme", "unicode").unwrap();
+        assert_eq!((theme.glyph("X"), theme.glyph("O"), theme.dot.as_str()), ("\u{25cf}", "\u{25cb}", "\u{b7}"));
+        assert_eq!(theme.key(), Some("X is \u{25cf}, O is \u{25cb}".to_string()));
+        theme.set("pieces", "@%.").unwrap();
+        assert_eq!((theme.glyph("X"), theme.glyph("O"), theme.glyph(" ")), ("@", "%", "."));
+        theme.set("palette", "mono").unwrap();
+        assert_eq!(theme.palette.map(|p| p.name), Some("mono"));
+        theme.set("palette", "none").unwrap();
+        assert_eq!(theme.palette, None);
+        theme.set("theme", "ascii").unwrap();
+        assert_eq!(theme.key(), None);
+        assert_eq!(theme.glyph(" "), " ");
+    }
+
+    #[test]
+    fn bad_settings_are_refused() {
+        let bad = [("theme", "fancy", "Unknown theme \"fancy\" (ascii or unicode)"),
+                   ("pieces", "X", "Pieces \"X\" should be two or three characters: X, O and empty"),
+                   ("pieces", "XO.-", "Pieces \"XO.-\" should be two or three characters: X, O and empty"),
+                   ("pieces", "X\tO", "Pieces \"X\tO\" should be two or three characters: X, O and empty"),
+                   ("pieces", "XX", "Pieces \"XX\" need to be different characters"),
+                   ("pieces", "XOX", "Pieces \"XOX\" need to be different characters"),
+                   ("palette", "neon", "Unknown palette \"neon\" (classic, colorblind, mono or none)"),
+                   ("colour", "red", "Unknown setting \"colour\" (theme, pieces or palette)")];
+        for (key, value, message) in bad {
+            let mut theme = Theme::default();
+            assert_eq!(theme.set(key, value), Err(message.to_string()));
+            assert_eq!(theme, Theme::default());
+        }
+    }
+
+    #[test]
+    fn files_are_loaded() {
+        let path = env::temp_dir().join(format!("othello-test-{}.conf", std::process::id()));
+        let path = path.to_string_lossy().to_string();
+        let mut theme = Theme::default();
+        fs::write(&path, "# Comment\n\ntheme = unicode\n  palette=colorblind  \n").unwrap();
+        let loaded = theme.load(&path);
+        fs::write(&path, "theme = unicode\npieces\n").unwrap();
+        let broken = Theme::default().load(&path);
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(loaded, Ok(()));
+        assert_eq!((theme.x.as_str(), theme.palette.map(|p| p.name)), ("\u{25cf}", Some("colorblind")));
+        assert_eq!(broken, Err(format!("Line 2 of {} isn't \"setting = value\": pieces", path)));
+    }
+}
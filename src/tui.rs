@@ -0,0 +1,372 @@
+/***********************************************************
+ * FULL-SCREEN TERMINAL
+ ***********************************************************
+ * With "--tui" the people playing don't type locations:
+ * the board is drawn in place at the top of the terminal
+ * with a cursor on it and a status panel beside it (the
+ * disc counts, whose move it is and what has happened),
+ * and they choose their move with the keys:
+ *     arrows or h j k l   Move the cursor.
+ *     Enter or space      Place a piece under the cursor.
+ *     r                   Resign (y to confirm).
+ *     Ctrl-C              Stop the program.
+ * The terminal is only put into raw mode (by "stty") while
+ * waiting for a key, so everything else the game prints
+ * still appears normally, under the board until the next
+ * time it is drawn. At a terminal that allows color the
+ * board is drawn in color as display() draws it, with the
+ * legal moves, the last move and its flips picked out.
+ * Without "--tui" the game reads typed lines as before,
+ * which is what scripts should use.
+***********************************************************/
+
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::color::{self, Highlights};
+use crate::theme;
+use crate::player::{Move, Player};
+use crate::{Board, NumLet, _X, _O, board_text_at, coordinates, count_pieces, legal_moves, place};
+
+// True while the board is drawn in place, so display()
+// leaves it alone
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+// How many messages the status panel shows
+const MESSAGES: usize = 4;
+
+/***********************************************************
+ * START / ACTIVE
+ ***********************************************************
+ * Draw boards in place from now on, if stdin is a terminal
+ * that stty can put in raw mode; and whether that is on.
+***********************************************************/
+pub fn start() -> Result<(), String> {
+    stty(&["-g"]).ok_or("The full-screen board (--tui) needs a terminal.")?;
+    ACTIVE.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+pub fn active() -> bool {
+    ACTIVE.load(Ordering::SeqCst)
+}
+
+/***********************************************************
+ * TUI_PLAYER
+ ***********************************************************
+ * A person at the terminal choosing moves with a cursor.
+***********************************************************/
+pub struct TuiPlayer {
+    cursor: Option<NumLet>,             // Where the cursor was left
+    piece: &'static str,                // The piece they play
+    seen: Board,                        // The board after their last move, to highlight changes
+    messages: VecDeque<String>          // What has happened lately
+}
+
+impl TuiPlayer {
+    pub fn new() -> TuiPlayer {
+        TuiPlayer { cursor: None, piece: _X, seen: Board::new(0), messages: VecDeque::new() }
+    }
+
+    // Add to the messages, forgetting the oldest
+    fn tell(&mut self, message: String) {
+        self.messages.push_back(message);
+        if self.messages.len() > MESSAGES {
+            self.messages.pop_front();
+        }
+    }
+
+    // Draw the board (in color if allowed) and the status panel
+    fn draw(&self, board: &Board, cursor: Option<NumLet>, highlights: &Highlights, status: &str) {
+        print!("{}", self.screen(board, cursor, highlights, status));
+        let _ = io::stdout().flush();
+    }
+
+    // The whole screen: the board with the panel beside it
+    fn screen(&self, board: &Board, cursor: Option<NumLet>, highlights: &Highlights, status: &str) -> String {
+        let theme = theme::current();
+        let (x_count, o_count) = count_pieces(board);
+        let mut panel = vec![
+            "OTHELLO".to_string(),
+            theme.key().unwrap_or_default(),
+            format!("X {:>2}    O {:>2}", x_count, o_count),
+            status.to_string(),
+            String::new(),
+        ];
+        panel.extend(self.messages.iter().cloned());
+        panel.resize(panel.len().max(MESSAGES + 6), String::new());
+        panel.push("Arrows or hjkl: move".to_string());
+        panel.push("Enter: place   r: resign".to_string());
+
+        let grid = if color::enabled(theme) {
+            color::board_text(board, highlights, cursor, theme)
+        }
+        else {
+            board_text_at(board, cursor, theme)
+        };
+        let grid: Vec<&str> = grid.lines().collect();
+        let width = grid.iter().map(|line| color::width(line)).max().unwrap_or(0);
+        let mut screen = "\x1b[H\x1b[2J".to_string();   // Home, then clear the screen
+        for i in 0..grid.len().max(panel.len()) {
+            let left = grid.get(i).copied().unwrap_or("");
+            let right = panel.get(i).map(String::as_str).unwrap_or("");
+            let padding = width.saturating_sub(color::width(left));
+            screen += &format!("{}{:padding$}   {}\r\n", left, "", right, padding = padding);
+        }
+        screen
+    }
+}
+
+impl Default for TuiPlayer {
+    fn default() -> TuiPlayer {
+        TuiPlayer::new()
+    }
+}
+
+impl Player for TuiPlayer {
+    fn new_game(&mut self, board: &Board, is_player_one: bool) {
+        self.piece = if is_player_one { _X } else { _O };
+        self.seen = board.clone();
+        self.cursor = None;
+        self.messages.clear();
+        self.tell(format!("A new game. You play {}.", self.piece));
+    }
+
+    fn choose_move(&mut self, board: &Board,
+                   is_player_one: bool, legal: &[NumLet]) -> Move {
+        let size = board.size();
+        let mut cursor = match self.cursor {
+            Some(nl) if nl.n < size && nl.l < size => nl,
+            _ => legal[0],                      // Start on a move that can be played
+        };
+        let status = format!("Your move, {}.", if is_player_one { _X } else { _O });
+        let highlights = Highlights::between(&self.seen, board, legal);
+
+        loop {
+            self.draw(board, Some(cursor), &highlights, &status);
+            match read_key() {
+                key @ (Key::Up | Key::Down | Key::Left | Key::Right) => {
+                    cursor = moved(cursor, key, size);
+                }
+                Key::Place if legal.contains(&cursor) => {
+                    self.cursor = Some(cursor);
+                    let mut after = board.clone();
+                    place(&mut after, cursor, is_player_one);
+                    self.tell(format!("You play {}.", coordinates(cursor)));
+                    let waiting = Highlights::between(board, &after, &legal_moves(&after, !is_player_one));
+                    self.draw(&after, None, &waiting, "Waiting for the other player.");
+                    self.seen = after;
+                    return Move::Place(cursor);
+                }
+                Key::Place => self.tell(format!("{} isn't a legal move.", coordinates(cursor))),
+                Key::Resign => {
+                    self.tell("Resign? Press y to resign.".to_string());
+                    self.draw(board, Some(cursor), &highlights, &status);
+                    self.messages.pop_back();
+                    if read_key() == Key::Yes {
+                        self.tell("You resign.".to_string());
+                        return Move::Resign;
+                    }
+                }
+                Key::Yes | Key::Other => {}
+            }
+        }
+    }
+
+    fn opponent_moved(&mut self, _board: &Board, mv: Move) {
+        let other = if self.piece == _X { _O } else { _X };
+        match mv {
+            Move::Place(nl) => self.tell(format!("{} plays {}.", other, coordinates(nl))),
+            Move::Pass => self.tell(format!("{} can't move and passes.", other)),
+            Move::Resign => self.tell(format!("{} resigns.", other)),
+            _ => {}
+        }
+    }
+
+    fn game_over(&mut self, board: &Board, resigned: Option<&'static str>) {
+        let (x_count, o_count) = count_pieces(board);
+        let result = match resigned {
+            Some(piece) => format!("{} resigned.", piece),
+            None if x_count > o_count => "X wins!".to_string(),
+            None if o_count > x_count => "O wins!".to_string(),
+            None => "It's a tie!".to_string(),
+        };
+        if legal_moves(board, true).is_empty() && legal_moves(board, false).is_empty() {
+            self.tell("Neither player can move.".to_string());
+        }
+        self.draw(board, None, &Highlights::between(&self.seen, board, &[]), &format!("Game over. {}", result));
+    }
+}
+
+/***********************************************************
+ * KEY
+ ***********************************************************
+ * What a key press asks for.
+***********************************************************/
+#[derive(Debug, PartialEq)]
+enum Key {
+    Up,
+    Down,
+    Left,
+    Right,
+    Place,
+    Resign,
+    Yes,
+    Other
+}
+
+/***********************************************************
+ * MOVED
+ ***********************************************************
+ * Where an arrow key takes the cursor, wrapping around
+ * the edges of the board.
+***********************************************************/
+fn moved(cursor: NumLet, key: Key, size: usize) -> NumLet {
+    let NumLet { n, l } = cursor;
+    match key {
+        Key::Up => NumLet { n, l: (l + size - 1) % size },
+        Key::Down => NumLet { n, l: (l + 1) % size },
+        Key::Left => NumLet { n: (n + size - 1) % size, l },
+        Key::Right => NumLet { n: (n + 1) % size, l },
+        _ => cursor,
+    }
+}
+
+/***********************************************************
+ * READ_KEY
+ ***********************************************************
+ * Wait for a key with the terminal in raw mode, so it
+ * arrives without Enter and isn't echoed. The arrows
+ * arrive as "ESC [ A" to "ESC [ D". Ctrl-C stops the
+ * program, as it would outside raw mode.
+***********************************************************/
+fn read_key() -> Key {
+    let saved = stty(&["-g"]);
+    stty(&["raw", "-echo"]);
+    let mut bytes = [0u8; 3];
+    let mut stdin = io::stdin().lock();
+    let read = stdin.read(&mut bytes[..1]).unwrap_or(0);
+    if read == 1 && bytes[0] == 0x1b {
+        let _ = stdin.read_exact(&mut bytes[1..3]);
+    }
+    if let Some(saved) = saved {
+        stty(&[saved.trim()]);
+    }
+    if read == 0 {
+        std::process::exit(0);                  // Nothing more will be typed
+    }
+    if bytes[0] == 0x03 {
+        println!();
+        std::process::exit(130);
+    }
+    key(&bytes)
+}
+
+/***********************************************************
+ * KEY
+ ***********************************************************
+ * What the bytes of a key press ask for.
+***********************************************************/
+fn key(bytes: &[u8]) -> Key {
+    match bytes {
+        [0x1b, b'[', b'A', ..] | [b'k', ..] => Key::Up,
+        [0x1b, b'[', b'B', ..] | [b'j', ..] => Key::Down,
+        [0x1b, b'[', b'D', ..] | [b'h', ..] => Key::Left,
+        [0x1b, b'[', b'C', ..] | [b'l', ..] => Key::Right,
+        [b'\r', ..] | [b'\n', ..] | [b' ', ..] => Key::Place,
+        [b'r', ..] => Key::Resign,
+        [b'y', ..] => Key::Yes,
+        _ => Key::Other,
+    }
+}
+
+/***********************************************************
+ * STTY
+ ***********************************************************
+ * Run stty on the terminal, returning what it printed, or
+ * None if it failed (stdin isn't a terminal).
+***********************************************************/
+fn stty(args: &[&str]) -> Option<String> {
+    let output = Command::new("stty").args(args).stdin(Stdio::inherit()).output().ok()?;
+    if output.status.success() { String::from_utf8(output.stdout).ok() } else { None }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_board;
+
+    // The starting position
+    fn start() -> Board {
+        let mut board = Board::new(4);
+        create_board(&mut board);
+        board
+    }
+
+    #[test]
+    fn keys_are_read_from_their_bytes() {
+        assert_eq!(key(b"\x1b[A"), Key::Up);
+        assert_eq!(key(b"j\0\0"), Key::Down);
+        assert_eq!(key(b"\x1b[D"), Key::Left);
+        assert_eq!(key(b"l\0\0"), Key::Right);
+        assert_eq!(key(b"\r\0\0"), Key::Place);
+        assert_eq!(key(b" \0\0"), Key::Place);
+        assert_eq!(key(b"r\0\0"), Key::Resign);
+        assert_eq!(key(b"y\0\0"), Key::Yes);
+        assert_eq!(key(b"\x1b[Z"), Key::Other);
+        assert_eq!(key(b"q\0\0"), Key::Other);
+    }
+
+    #[test]
+    fn the_cursor_wraps_around_the_board() {
+        let corner = NumLet { n: 0, l: 0 };
+        assert_eq!(moved(corner, Key::Up, 4), NumLet { n: 0, l: 3 });
+        assert_eq!(moved(corner, Key::Left, 4), NumLet { n: 3, l: 0 });
+        assert_eq!(moved(corner, Key::Down, 4), NumLet { n: 0, l: 1 });
+        assert_eq!(moved(corner, Key::Right, 4), NumLet { n: 1, l: 0 });
+        assert_eq!(moved(NumLet { n: 3, l: 3 }, Key::Right, 4), NumLet { n: 0, l: 3 });
+        assert_eq!(moved(corner, Key::Place, 4), corner);
+    }
+
+    #[test]
+    fn the_panel_keeps_the_latest_messages() {
+        let mut player = TuiPlayer::new();
+        let board = start();
+        player.new_game(&board, false);
+        player.opponent_moved(&board, Move::Place(NumLet { n: 3, l: 2 }));
+        player.opponent_moved(&board, Move::Pass);
+        player.opponent_moved(&board, Move::Resign);
+        assert_eq!(player.messages, ["A new game. You play O.", "X plays C4.", "X can't move and passes.",
+                                     "X resigns."]);
+        player.tell("One more.".to_string());
+        assert_eq!(player.messages.len(), MESSAGES);
+        assert_eq!(player.messages[0], "X plays C4.");
+    }
+
+    #[test]
+    fn the_screen_shows_the_board_beside_the_panel() {
+        let mut player = TuiPlayer::new();
+        let board = start();
+        player.new_game(&board, true);
+        let highlights = Highlights::between(&board, &board, &[]);
+        let screen = player.screen(&board, Some(NumLet { n: 0, l: 0 }), &highlights, "Your move, X.");
+        assert!(screen.starts_with("\x1b[H\x1b[2J"));
+        let lines: Vec<&str> = screen["\x1b[H\x1b[2J".len()..].split("\r\n").collect();
+        assert!(lines[0].ends_with("   OTHELLO"));
+        assert!(lines[2].ends_with("   X  2    O  2"));
+        assert!(lines[3].ends_with("   Your move, X."));
+        assert!(lines[5].ends_with("   A new game. You play X."));
+        assert!(screen.contains("Enter: place   r: resign"));
+        let theme = theme::current();
+        let grid = if color::enabled(theme) {
+            color::board_text(&board, &highlights, Some(NumLet { n: 0, l: 0 }), theme)
+        }
+        else {
+            board_text_at(&board, Some(NumLet { n: 0, l: 0 }), theme)
+        };
+        for (line, row) in lines.iter().zip(grid.lines()) {
+            assert!(line.starts_with(row));
+        }
+    }
+}
@@ -0,0 +1,176 @@
+/***********************************************************
+ * WEBSOCKETS
+ ***********************************************************
+ * Just enough of WebSockets (RFC 6455) for the HTTP API
+ * to push events to a browser, with no outside library:
+ *     accept_key()    The answer to the browser's
+ *                     Sec-WebSocket-Key in the handshake:
+ *                     SHA-1 of the key and the protocol's
+ *                     fixed GUID, in base64.
+ *     read_message()  A whole text message from the
+ *                     browser, unmasking its frames and
+ *                     putting fragments together, or a
+ *                     ping or close to answer.
+ *     send_text()     A text message to the browser, in
+ *                     one unmasked frame; send_pong() and
+ *                     send_close() answer the others.
+ * Reading and writing are separate so that one thread can
+ * wait for messages while others write.
+ * Binary messages are read like text; nothing else of the
+ * protocol (extensions, subprotocols) is offered.
+***********************************************************/
+
+use std::io::{self, Read, Write};
+use crate::digest::{base64, sha1};
+
+// Added to the browser's key before hashing, by the protocol
+static GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+// Messages bigger than this close the connection
+const MAX_MESSAGE: usize = 64 * 1024;
+
+// Frame opcodes
+const CONTINUATION: u8 = 0x0;
+const TEXT: u8 = 0x1;
+const BINARY: u8 = 0x2;
+const CLOSE: u8 = 0x8;
+const PING: u8 = 0x9;
+const PONG: u8 = 0xA;
+
+/***********************************************************
+ * MESSAGE
+ ***********************************************************
+ * What the browser sent.
+***********************************************************/
+pub enum Message {
+    Text(String),           // A text (or binary) message
+    Ping(Vec<u8>),          // Answer with send_pong()
+    Close                   // Answer with send_close() and stop
+}
+
+/***********************************************************
+ * ACCEPT_KEY
+ ***********************************************************
+ * The Sec-WebSocket-Accept header for a handshake.
+***********************************************************/
+pub fn accept_key(key: &str) -> String {
+    base64(&sha1(format!("{}{}", key.trim(), GUID).as_bytes()))
+}
+
+/***********************************************************
+ * SEND_TEXT / SEND_PONG / SEND_CLOSE
+ ***********************************************************
+ * Write a text message as a single frame, answer a ping,
+ * or say the connection is closing.
+***********************************************************/
+pub fn send_text(stream: &mut impl Write, text: &str) -> io::Result<()> {
+    send_frame(stream, TEXT, text.as_bytes())
+}
+
+pub fn send_pong(stream: &mut impl Write, payload: &[u8]) -> io::Result<()> {
+    send_frame(stream, PONG, payload)
+}
+
+pub fn send_close(stream: &mut impl Write) -> io::Result<()> {
+    send_frame(stream, CLOSE, &[])
+}
+
+fn send_frame(stream: &mut impl Write, opcode: u8, payload: &[u8]) -> io::Result<()> {
+    let mut frame = vec![0x80 | opcode];        // The final frame of its message
+    match payload.len() {
+        n if n < 126 => frame.push(n as u8),
+        n if n <= 0xFFFF => {
+            frame.push(126);
+            frame.extend_from_slice(&(n as u16).to_be_bytes());
+        }
+        n => {
+            frame.push(127);
+            frame.extend_from_slice(&(n as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    stream.write_all(&frame)?;
+    stream.flush()
+}
+
+/***********************************************************
+ * READ_MESSAGE
+ ***********************************************************
+ * Read frames until a whole message, a ping or a close has
+ * arrived. Returns None if the connection drops or the
+ * browser breaks the rules.
+***********************************************************/
+pub fn read_message(stream: &mut impl Read) -> Option<Message> {
+    let mut message = Vec::new();           // The fragments so far
+    loop {
+        let mut head = [0u8; 2];
+        stream.read_exact(&mut head).ok()?;
+        let last = head[0] & 0x80 != 0;     // True for the final fragment
+        let opcode = head[0] & 0x0F;
+        let masked = head[1] & 0x80 != 0;   // Browsers always mask
+        let length = match head[1] & 0x7F {
+            126 => {
+                let mut bytes = [0u8; 2];
+                stream.read_exact(&mut bytes).ok()?;
+                u16::from_be_bytes(bytes) as usize
+            }
+            127 => {
+                let mut bytes = [0u8; 8];
+                stream.read_exact(&mut bytes).ok()?;
+                u64::from_be_bytes(bytes) as usize
+            }
+            n => n as usize,
+        };
+        if !masked || length > MAX_MESSAGE || message.len() + length > MAX_MESSAGE {
+            return None;
+        }
+        let mut mask = [0u8; 4];
+        stream.read_exact(&mut mask).ok()?;
+        let mut payload = vec![0u8; length];
+        stream.read_exact(&mut payload).ok()?;
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[i % 4];
+        }
+
+        match opcode {
+            PING => return Some(Message::Ping(payload)),
+            PONG => {}
+            CLOSE => return Some(Message::Close),
+            TEXT | BINARY | CONTINUATION => {
+                message.extend_from_slice(&payload);
+                if last {
+                    return Some(Message::Text(String::from_utf8_lossy(&message).into_owned()));
+                }
+            }
+            _ => return None,               // Not part of the protocol
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accept_key_matches_rfc_6455() {
+        assert_eq!(accept_key("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+    }
+
+    #[test]
+    fn frames_match_rfc_6455() {
+        let mut frame = Vec::new();
+        send_text(&mut frame, "Hello").unwrap();
+        assert_eq!(frame, [0x81, 0x05, 0x48, 0x65, 0x6c, 0x6c, 0x6f]);
+
+        // A masked "Hello" from a browser, then the same in two fragments
+        let masked = [0x81, 0x85, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d, 0x51, 0x58];
+        assert!(matches!(read_message(&mut &masked[..]), Some(Message::Text(text)) if text == "Hello"));
+        let fragments = [0x01, 0x83, 0, 0, 0, 0, 0x48, 0x65, 0x6c, 0x80, 0x82, 0, 0, 0, 0, 0x6c, 0x6f];
+        assert!(matches!(read_message(&mut &fragments[..]), Some(Message::Text(text)) if text == "Hello"));
+    }
+
+    #[test]
+    fn unmasked_frames_are_refused() {
+        assert!(read_message(&mut &[0x81, 0x05, 0x48, 0x65, 0x6c, 0x6c, 0x6f][..]).is_none());
+    }
+}
@@ -0,0 +1,327 @@
+/***********************************************************
+ * WTHOR DATABASES
+ ***********************************************************
+ * WTHOR is the format of the French Othello federation's
+ * archive of tournament games. An archive is made of:
+ *     .wtb files          The games, usually one file a year.
+ *     WTHOR.JOU           The players' names.
+ *     WTHOR.TRN           The tournaments' names.
+ * Every file starts with a 16 byte header:
+ *     0-3    Date the file was made (century, year, month, day)
+ *     4-7    Number of games (.wtb), little endian
+ *     8-9    Number of names (.jou and .trn), little endian
+ *     10-11  Year the games were played (.wtb), little endian
+ *     12     Board size (0 or 8 for 8*8)
+ *     13-15  Game type, search depth and a spare byte
+ * followed by fixed size records:
+ *     .wtb   68 bytes: tournament number (2 bytes), black and
+ *            white player numbers (2 bytes each), black's
+ *            disc count, black's theoretical disc count, and
+ *            60 bytes of moves. Each move is 10 * row +
+ *            column, counting from 1 (so "f5" is 56), and 0
+ *            fills the bytes after the last move. Passes are
+ *            not stored.
+ *     .jou   20 bytes: a player's name, padded with zeros.
+ *     .trn   26 bytes: a tournament's name, padded with zeros.
+ * Names are in ISO-8859-1. Every game is replayed through
+ * the rules as it is read and turned into a Game record,
+ * with the passes put back in and the names, tournament,
+ * year and result filled in.
+***********************************************************/
+
+use std::fs;
+use crate::game::{Game, Turn};
+use crate::player::Move;
+use crate::{NumLet, coordinates, legal_moves, place};
+
+const HEADER: usize = 16;           // Bytes before the first record
+const GAME_RECORD: usize = 68;      // Bytes in each .wtb record
+const PLAYER_RECORD: usize = 20;    // Bytes in each .jou record
+const TOURNAMENT_RECORD: usize = 26;    // Bytes in each .trn record
+
+/***********************************************************
+ * WTHOR_GAME
+ ***********************************************************
+ * A game from a .wtb file with the names it refers to.
+***********************************************************/
+#[derive(Clone, Debug)]
+pub struct WthorGame {
+    pub tournament: String, // Name of the tournament it was played in
+    pub black: String,      // Name of the player of "X"
+    pub white: String,      // Name of the player of "O"
+    pub year: u16,          // Year it was played
+    pub score: u8,          // Black's final score, as the archive records it
+    pub game: Game          // The moves
+}
+
+/***********************************************************
+ * FILTER
+ ***********************************************************
+ * Which games to list. Names match if they contain the
+ * text given, ignoring case. Anything left as None matches
+ * every game.
+***********************************************************/
+#[derive(Clone, Debug, Default)]
+pub struct Filter {
+    pub player: Option<String>,     // Either player's name
+    pub year: Option<u16>,          // The year played
+    pub tournament: Option<String>  // The tournament's name
+}
+
+impl Filter {
+    // True if the game is one the filter asks for
+    pub fn matches(&self, game: &WthorGame) -> bool {
+        let contains = |name: &str, text: &str| name.to_lowercase().contains(&text.to_lowercase());
+        self.player.as_ref().is_none_or(|p| contains(&game.black, p) || contains(&game.white, p))
+            && self.year.is_none_or(|y| game.year == y)
+            && self.tournament.as_ref().is_none_or(|t| contains(&game.tournament, t))
+    }
+}
+
+/***********************************************************
+ * READ_NAMES
+ ***********************************************************
+ * Read the names from a .jou or .trn file, each record
+ * being record_size bytes long.
+***********************************************************/
+fn read_names(path: &str, record_size: usize) -> Result<Vec<String>, String> {
+    let bytes = fs::read(path).map_err(|e| format!("Couldn't read \"{}\": {}", path, e))?;
+    if bytes.len() < HEADER {
+        return Err(format!("\"{}\" is too short to be a WTHOR file", path));
+    }
+    let count = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+    let records = &bytes[HEADER..];
+    if records.len() < count * record_size {
+        return Err(format!("\"{}\" is missing some of its names", path));
+    }
+    Ok(records.chunks(record_size)
+        .take(count)
+        .map(|record| record.iter()
+            .take_while(|b| **b != 0)
+            .map(|b| *b as char)           // ISO-8859-1 is the first 256 characters
+            .collect::<String>()
+            .trim()
+            .to_string())
+        .collect())
+}
+
+/***********************************************************
+ * READ_PLAYERS / READ_TOURNAMENTS
+ ***********************************************************
+ * The names in a WTHOR.JOU or WTHOR.TRN file.
+***********************************************************/
+pub fn read_players(path: &str) -> Result<Vec<String>, String> {
+    read_names(path, PLAYER_RECORD)
+}
+
+pub fn read_tournaments(path: &str) -> Result<Vec<String>, String> {
+    read_names(path, TOURNAMENT_RECORD)
+}
+
+/***********************************************************
+ * READ_GAMES
+ ***********************************************************
+ * Read every game in a .wtb file, looking the names up in
+ * players and tournaments (numbers that aren't found are
+ * shown as "#<number>"). Returns the games that follow the
+ * rules and the number that had to be skipped because they
+ * don't.
+***********************************************************/
+pub fn read_games(path: &str, players: &[String], tournaments: &[String])
+                  -> Result<(Vec<WthorGame>, usize), String> {
+    let bytes = fs::read(path).map_err(|e| format!("Couldn't read \"{}\": {}", path, e))?;
+    if bytes.len() < HEADER {
+        return Err(format!("\"{}\" is too short to be a WTHOR file", path));
+    }
+    let count = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as usize;
+    let year = u16::from_le_bytes([bytes[10], bytes[11]]);
+    if bytes[12] != 0 && bytes[12] != 8 {
+        return Err(format!("\"{}\" holds {}*{} games; only 8*8 is supported", path, bytes[12], bytes[12]));
+    }
+    let records = &bytes[HEADER..];
+    if records.len() < count * GAME_RECORD {
+        return Err(format!("\"{}\" is missing some of its games", path));
+    }
+    let name = |names: &[String], number: u16| match names.get(number as usize) {
+        Some(name) => name.clone(),
+        None => format!("#{}", number),
+    };
+
+    let mut games = Vec::new();
+    let mut skipped = 0;
+    for record in records.chunks(GAME_RECORD).take(count) {
+        let number = |i: usize| u16::from_le_bytes([record[i], record[i + 1]]);
+        match decode_moves(&record[8..]) {
+            Some(mut game) => {
+                let black = name(players, number(2));
+                let white = name(players, number(4));
+                let tournament = name(tournaments, number(0));
+                game.info.names = [Some(black.clone()), Some(white.clone())];
+                game.info.place = Some(tournament.clone());
+                game.info.date = Some(year.to_string());
+                game.info.result = Some(format!("{:+}", 2 * record[6] as i32 - 64));
+                games.push(WthorGame { tournament, black, white, year, score: record[6], game });
+            }
+            None => skipped += 1,
+        }
+    }
+    Ok((games, skipped))
+}
+
+/***********************************************************
+ * DECODE_MOVES
+ ***********************************************************
+ * Turn the 60 move bytes of a game into a Game record,
+ * adding a pass whenever the player to move can't place.
+ * Returns None if a move breaks the rules.
+***********************************************************/
+fn decode_moves(bytes: &[u8]) -> Option<Game> {
+    let mut game = Game::new(8, ["human".to_string(), "human".to_string()]);
+    let (mut board, mut is_player_one) = game.start_position();
+    for byte in bytes.iter().take_while(|b| **b != 0) {
+        let row = (byte / 10) as usize;
+        let column = (byte % 10) as usize;
+        if !(1..=8).contains(&row) || !(1..=8).contains(&column) {
+            return None;
+        }
+        let nl = NumLet { n: column - 1, l: row - 1 };
+        if legal_moves(&board, is_player_one).is_empty() {
+            game.moves.push(Turn { mv: Move::Pass, time: Default::default(), eval: None });
+            is_player_one = !is_player_one;     // The passes WTHOR leaves out
+        }
+        if !place(&mut board, nl, is_player_one) {
+            return None;
+        }
+        game.moves.push(Turn { mv: Move::Place(nl), time: Default::default(), eval: None });
+        is_player_one = !is_player_one;
+    }
+    Some(game)
+}
+
+/***********************************************************
+ * READ_ALL_NAMES
+ ***********************************************************
+ * The player and tournament names for a .wtb file: from
+ * the .jou and .trn files given, or from WTHOR.JOU and
+ * WTHOR.TRN next to the .wtb file if there are any.
+***********************************************************/
+pub fn read_all_names(wtb: &str, jou: Option<&str>, trn: Option<&str>)
+                      -> Result<(Vec<String>, Vec<String>), String> {
+    let folder = match wtb.rfind(['/', '\\']) {        // Where the names usually are
+        Some(i) => wtb[..=i].to_string(),
+        None => String::new(),
+    };
+    let players = match jou {
+        Some(path) => read_players(path)?,
+        None => read_players(&format!("{}WTHOR.JOU", folder)).unwrap_or_default(),
+    };
+    let tournaments = match trn {
+        Some(path) => read_tournaments(path)?,
+        None => read_tournaments(&format!("{}WTHOR.TRN", folder)).unwrap_or_default(),
+    };
+    Ok((players, tournaments))
+}
+
+/***********************************************************
+ * RUN
+ ***********************************************************
+ * List the games in the .wtb files that match the filter,
+ * one line each followed by the moves. The names come from
+ * the .jou and .trn files given, or from WTHOR.JOU and
+ * WTHOR.TRN next to the first .wtb file if there are any.
+***********************************************************/
+pub fn run(files: &[String], jou: Option<&str>, trn: Option<&str>, filter: &Filter) -> Result<(), String> {
+    let (players, tournaments) = read_all_names(&files[0], jou, trn)?;
+
+    let mut listed = 0;     // Number of games shown
+    for path in files {
+        let (games, skipped) = read_games(path, &players, &tournaments)?;
+        if skipped > 0 {
+            println!("{} game(s) in {} were skipped because their moves break the rules.", skipped, path);
+        }
+        for wthor in games.iter().filter(|g| filter.matches(g)) {
+            listed += 1;
+            // The archive gives empty locations to the winner, so the scores add up to 64
+            println!("{} {} | {} ({}) - {} ({})", wthor.year, wthor.tournament,
+                     wthor.black, wthor.score, wthor.white, 64 - wthor.score as i32);
+            let moves: Vec<String> = wthor.game.moves.iter()
+                .map(|turn| match turn.mv {
+                    Move::Place(nl) => coordinates(nl),
+                    _ => "pass".to_string(),
+                })
+                .collect();
+            println!("    {}", moves.join(" "));
+        }
+    }
+    println!("{} game(s) listed.", listed);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    // A whole game, as WTHOR stores it: "X" has to pass
+    // after the 58th move and "O" after the 59th
+    static MOVES: [u8; 60] = [
+        65, 46, 37, 64, 53, 63, 62, 74, 43, 42, 83, 35, 73, 56, 24, 36, 41, 71, 51, 31,
+        34, 13, 26, 16, 66, 61, 15, 38, 52, 72, 48, 84, 85, 25, 33, 75, 17, 47, 76, 86,
+        67, 32, 27, 14, 57, 58, 21, 11, 81, 78, 87, 18, 68, 28, 22, 12, 88, 23, 82, 77];
+
+    #[test]
+    fn passes_are_put_back() {
+        let game = decode_moves(&MOVES).unwrap();
+        assert_eq!(game.moves.len(), 62);
+        assert_eq!(game.moves[0].mv, Move::Place(NumLet { n: 4, l: 5 }));
+        assert_eq!(game.moves[58].mv, Move::Pass);
+        assert_eq!(game.moves[60].mv, Move::Pass);
+        let (board, _) = game.position().unwrap();
+        assert!(legal_moves(&board, true).is_empty() && legal_moves(&board, false).is_empty());
+    }
+
+    #[test]
+    fn short_games_end_at_the_first_zero() {
+        let mut bytes = MOVES;
+        bytes[10..].fill(0);
+        assert_eq!(decode_moves(&bytes).unwrap().moves.len(), 10);
+    }
+
+    #[test]
+    fn illegal_moves_are_refused() {
+        for first in [11, 99, 9] {
+            let mut bytes = MOVES;
+            bytes[0] = first;
+            assert!(decode_moves(&bytes).is_none());
+        }
+    }
+
+    #[test]
+    fn files_are_read() {
+        let mut wtb = vec![20, 24, 1, 1, 2, 0, 0, 0, 0, 0, 0xE8, 0x07, 8, 0, 0, 0];
+        for moves in [&MOVES[..], &[11; 60][..]] {
+            wtb.extend([1, 0, 0, 0, 7, 0, 40]);     // Tournament 1, players 0 and 7, 40 discs
+            wtb.push(40);
+            wtb.extend(moves);
+        }
+        let mut jou = vec![0; 16];
+        jou[8] = 1;
+        jou.extend(b"Tastet Marc\0\0\0\0\0\0\0\0\0");
+        let dir = env::temp_dir();
+        let wtb_path = dir.join(format!("othello-test-{}.wtb", std::process::id()));
+        let jou_path = dir.join(format!("othello-test-{}.jou", std::process::id()));
+        fs::write(&wtb_path, &wtb).unwrap();
+        fs::write(&jou_path, &jou).unwrap();
+        let players = read_players(&jou_path.to_string_lossy()).unwrap();
+        let read = read_games(&wtb_path.to_string_lossy(), &players, &[]);
+        let _ = fs::remove_file(&wtb_path);
+        let _ = fs::remove_file(&jou_path);
+
+        assert_eq!(players, ["Tastet Marc"]);
+        let (games, skipped) = read.unwrap();
+        assert_eq!((games.len(), skipped), (1, 1));
+        assert_eq!((games[0].black.as_str(), games[0].white.as_str()), ("Tastet Marc", "#7"));
+        assert_eq!((games[0].tournament.as_str(), games[0].year), ("#1", 2024));
+        assert_eq!(games[0].game.info.result.as_deref(), Some("+16"));
+    }
+}